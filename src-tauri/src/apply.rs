@@ -1,28 +1,81 @@
 // Import necessary crates
 use std::process::Command;
+use crate::locations::home_dir;
+use crate::pack::load_installed_pack;
+use crate::types::{RicePack, ThemeComponent, variant_theme_name};
 
 #[tauri::command]
-pub fn apply_theme(theme_name: String) -> Result<String, String> {
+pub fn apply_theme(theme_name: String, variant: Option<String>) -> Result<String, String> {
+    // Variants are installed as separate themes named <theme>-<variant>
+    let theme_name = match variant.as_deref() {
+        Some(variant) if !variant.is_empty() => variant_theme_name(&theme_name, variant),
+        _ => theme_name,
+    };
+
+    // Installed rice packs switch each of their themes
+    let home_dir = home_dir()?;
+    if let Some(pack) = load_installed_pack(&home_dir, &theme_name)? {
+        return apply_pack(&pack);
+    }
+
     let mut results = Vec::new();
     let mut warnings = Vec::new();
     
-    // Apply GTK theme using gsettings
-    let gtk_output = Command::new("gsettings")
+    set_interface_theme("gtk-theme", &theme_name, "GTK theme", &mut results, &mut warnings)?;
+    apply_shell_theme(&theme_name, &mut results, &mut warnings)?;
+    
+    // Apply window manager theme (for other DEs)
+    // Silently ignore WM theme failures as not all DEs support this
+    if let Ok(wm) = apply_window_manager_theme(&theme_name) {
+        results.push(wm);
+    }
+    
+    summarize(results, warnings)
+}
+
+// Switch the desktop to every theme of a rice pack
+fn apply_pack(pack: &RicePack) -> Result<String, String> {
+    let mut results = Vec::new();
+    let mut warnings = Vec::new();
+
+    for theme in &pack.themes {
+        match theme.component {
+            ThemeComponent::Gtk => {
+                set_interface_theme("gtk-theme", &theme.name, "GTK theme", &mut results, &mut warnings)?;
+                if let Ok(wm) = apply_window_manager_theme(&theme.name) {
+                    results.push(wm);
+                }
+            }
+            ThemeComponent::Shell => apply_shell_theme(&theme.name, &mut results, &mut warnings)?,
+            ThemeComponent::Icons => set_interface_theme("icon-theme", &theme.name, "Icon theme", &mut results, &mut warnings)?,
+            ThemeComponent::Cursors => set_interface_theme("cursor-theme", &theme.name, "Cursor theme", &mut results, &mut warnings)?,
+            ThemeComponent::Fonts => {} // Installed fonts are picked up by fontconfig, there is nothing to switch
+        }
+    }
+
+    summarize(results, warnings)
+}
+
+// Set one of the org.gnome.desktop.interface theme keys using gsettings
+fn set_interface_theme(key: &str, theme_name: &str, label: &str, results: &mut Vec<String>, warnings: &mut Vec<String>) -> Result<(), String> {
+    let output = Command::new("gsettings")
         .arg("set")
         .arg("org.gnome.desktop.interface")
-        .arg("gtk-theme")
-        .arg(&theme_name)
+        .arg(key)
+        .arg(theme_name)
         .output()
-        .map_err(|e| format!("Failed to execute gsettings for GTK theme: {}", e))?;
+        .map_err(|e| format!("Failed to execute gsettings for {}: {}", label, e))?;
     
-    if gtk_output.status.success() {
-        results.push("GTK theme".to_string());
+    if output.status.success() {
+        results.push(label.to_string());
     } else {
-        // Throw error on failure
-        let error = String::from_utf8_lossy(&gtk_output.stderr);
-        warnings.push(format!("GTK theme failed: {}", error));
+        let error = String::from_utf8_lossy(&output.stderr);
+        warnings.push(format!("{} failed: {}", label, error));
     }
-    
+    Ok(())
+}
+
+fn apply_shell_theme(theme_name: &str, results: &mut Vec<String>, warnings: &mut Vec<String>) -> Result<(), String> {
     // Check if user-theme extension schema exists
     let schema_check = Command::new("gsettings")
         .arg("list-schemas")
@@ -37,7 +90,7 @@ pub fn apply_theme(theme_name: String) -> Result<String, String> {
             .arg("set")
             .arg("org.gnome.shell.extensions.user-theme")
             .arg("name")
-            .arg(&theme_name)
+            .arg(theme_name)
             .output()
             .map_err(|e| format!("Failed to execute gsettings for Shell theme: {}", e))?;
         
@@ -49,20 +102,16 @@ pub fn apply_theme(theme_name: String) -> Result<String, String> {
         }
     } else {
         // Try alternative methods to apply shell theme
-        let alt_result = try_alternative_shell_theme_methods(&theme_name);
+        let alt_result = try_alternative_shell_theme_methods(theme_name);
         match alt_result {
             Ok(method) => results.push(format!("Shell theme (via {})", method)),
             Err(e) => warnings.push(format!("Shell theme not applied: user-theme extension not found. Install it with: `gnome-extensions install user-theme@gnome-shell-extensions.gcampax.github.com` or via GNOME Extensions app. Error: {}", e))
         }
     }
-    
-    // Apply window manager theme (for other DEs)
-    let wm_result = apply_window_manager_theme(&theme_name);
-    match wm_result {
-        Ok(wm) => results.push(wm),
-        Err(_) => {} // Silently ignore WM theme failures as not all DEs support this
-    }
-    
+    Ok(())
+}
+
+fn summarize(results: Vec<String>, warnings: Vec<String>) -> Result<String, String> {
     let mut message = String::new();
     
     if !results.is_empty() {
@@ -82,8 +131,7 @@ pub fn apply_theme(theme_name: String) -> Result<String, String> {
 
 // Try alternative methods to apply shell theme when user-theme extension isn't available
 fn try_alternative_shell_theme_methods(theme_name: &str) -> Result<String, String> {
-    let home_dir = std::env::var("HOME")
-        .map_err(|_| "Failed to get HOME directory".to_string())?;
+    let home_dir = home_dir()?;
     
     // Method 1: Try to enable user-theme extension first
     let enable_output = Command::new("gnome-extensions")
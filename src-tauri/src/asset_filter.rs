@@ -0,0 +1,235 @@
+// Import necessary crates
+use std::fs;
+use std::path::Path;
+
+pub const IGNORE_FILE: &str = ".reskinignore"; // Gitignore-style file in the theme root
+
+// Left out of every bundle unless .reskinignore re-includes them with a "!" pattern
+const DEFAULT_IGNORES: &[&str] = &[
+    ".*/", // Hidden directories such as .git and .vscode
+    "*.swp",
+    "*.swo",
+    "*~",
+    ".DS_Store",
+    "Thumbs.db",
+    "/.reskinignore",
+    "/reskin-pack.json", // Read into the manifest instead
+];
+
+struct Pattern { // One gitignore-style rule
+    glob: Vec<char>, // Pattern without the leading "!", leading "/" and trailing "/"
+    negated: bool, // Rule starts with "!" and re-includes what it matches
+    directory_only: bool, // Rule ends with "/" and only matches directories
+    anchored: bool, // Rule contains a "/" and matches the whole path instead of the file name
+}
+
+impl Pattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)), // "\!" and "\#" start literal patterns
+        };
+        let (directory_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+
+        Some(Self { glob: line.chars().collect(), negated, directory_only, anchored })
+    }
+
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+        let subject = if self.anchored { path } else { path.rsplit('/').next().unwrap_or(path) };
+        glob_match(&self.glob, &subject.chars().collect::<Vec<_>>())
+    }
+}
+
+pub struct AssetFilter { // Decides which theme files end up in a bundle
+    ignores: Vec<Pattern>, // Defaults, then .reskinignore, then the request's exclude patterns
+    includes: Vec<Pattern>, // When not empty, only matching entries are bundled
+}
+
+impl AssetFilter {
+    // Build a filter from .reskinignore contents and extra include/exclude patterns
+    pub fn new(ignore_file: &str, include: &[String], exclude: &[String]) -> Self {
+        let ignores = DEFAULT_IGNORES.iter().copied()
+            .chain(ignore_file.lines())
+            .chain(exclude.iter().map(String::as_str))
+            .filter_map(Pattern::parse)
+            .collect();
+        let includes = include.iter().filter_map(|line| Pattern::parse(line)).collect();
+        Self { ignores, includes }
+    }
+
+    // Build a filter for a theme directory, reading its .reskinignore if there is one
+    pub fn for_theme_directory(theme_root: &Path, include: &[String], exclude: &[String]) -> Result<Self, String> {
+        let ignore_path = theme_root.join(IGNORE_FILE);
+        let ignore_file = if ignore_path.is_file() {
+            fs::read_to_string(&ignore_path).map_err(|e| format!("Failed to read {}: {}", IGNORE_FILE, e))?
+        } else {
+            String::new()
+        };
+        Ok(Self::new(&ignore_file, include, exclude))
+    }
+
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool { // The last matching rule wins, like in .gitignore
+        self.ignores.iter()
+            .rev()
+            .find(|pattern| pattern.matches(path, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+
+    pub fn is_included(&self, path: &str, is_dir: bool) -> bool { // Everything counts as included without include patterns
+        if self.includes.is_empty() {
+            return true;
+        }
+        self.includes.iter()
+            .rev()
+            .find(|pattern| pattern.matches(path, is_dir))
+            .is_some_and(|pattern| !pattern.negated)
+    }
+}
+
+// Match a glob against a path: "*" and "?" stay within one path component, "**" crosses them,
+// and "[...]" matches one character from a set
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    match glob.first() {
+        None => text.is_empty(),
+        Some('*') if glob.get(1) == Some(&'*') => {
+            let rest = &glob[2..];
+            match rest.split_first() {
+                // "**/" matches zero or more whole directories
+                Some(('/', rest)) => glob_match(rest, text)
+                    || (0..text.len()).any(|i| text[i] == '/' && glob_match(rest, &text[i + 1..])),
+                _ => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+            }
+        }
+        Some('*') => {
+            for i in 0..=text.len() {
+                if glob_match(&glob[1..], &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' {
+                    return false;
+                }
+            }
+            false
+        }
+        Some('?') => text.first().is_some_and(|&c| c != '/') && glob_match(&glob[1..], &text[1..]),
+        Some('[') => {
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            match match_class(&glob[1..], c) {
+                Some((matched, rest)) => c != '/' && matched && glob_match(rest, &text[1..]),
+                None => c == '[' && glob_match(&glob[1..], &text[1..]), // An unclosed "[" is literal
+            }
+        }
+        Some('\\') if glob.len() > 1 => text.first() == Some(&glob[1]) && glob_match(&glob[2..], &text[1..]),
+        Some(&c) => text.first() == Some(&c) && glob_match(&glob[1..], &text[1..]),
+    }
+}
+
+// Match `c` against the character class starting right after "[", returning the result and the rest of
+// the glob, or None if the class is never closed
+fn match_class(class: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, mut i) = match class.first() {
+        Some('!') | Some('^') => (true, 1),
+        _ => (false, 0),
+    };
+    let mut matched = false;
+    let mut first = true;
+    while i < class.len() {
+        if class[i] == ']' && !first {
+            return Some((matched != negated, &class[i + 1..]));
+        }
+        if i + 2 < class.len() && class[i + 1] == '-' && class[i + 2] != ']' {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+        first = false;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(ignore_file: &str) -> AssetFilter {
+        AssetFilter::new(ignore_file, &[], &[])
+    }
+
+    #[test]
+    fn matches_gitignore_style_patterns() {
+        let f = filter("*.xcf\nREADME*\n/build/\ndocs/**/*.md\n[Tt]humbs.db\n");
+        assert!(f.is_ignored("sources/icon.xcf", false));
+        assert!(f.is_ignored("README.md", false));
+        assert!(f.is_ignored("build", true));
+        assert!(!f.is_ignored("gtk-3.0/build", true)); // Anchored to the theme root
+        assert!(!f.is_ignored("build", false)); // Only matches directories
+        assert!(f.is_ignored("docs/a/b/notes.md", false));
+        assert!(f.is_ignored("docs/notes.md", false)); // "**/" also matches no directory at all
+        assert!(f.is_ignored("thumbs.db", false));
+        assert!(!f.is_ignored("gtk-3.0/gtk.css", false));
+    }
+
+    #[test]
+    fn later_rules_and_negations_win() {
+        let f = filter("*.png\n!keep.png\n");
+        assert!(f.is_ignored("icons/a.png", false));
+        assert!(!f.is_ignored("icons/keep.png", false));
+
+        // Hidden directories are skipped by default but can be re-included
+        assert!(filter("").is_ignored(".git", true));
+        assert!(!filter("!.config/\n").is_ignored(".config", true));
+        assert!(filter("").is_ignored("swap.css.swp", false));
+    }
+
+    #[test]
+    fn include_and_exclude_patterns() {
+        let f = AssetFilter::new("", &["*.svg".to_string(), "index.theme".to_string()], &["scalable/".to_string()]);
+        assert!(f.is_included("16x16/apps/firefox.svg", false));
+        assert!(f.is_included("index.theme", false));
+        assert!(!f.is_included("16x16/apps/firefox.png", false));
+        assert!(f.is_ignored("scalable", true));
+        assert!(AssetFilter::new("", &[], &[]).is_included("anything", false));
+    }
+
+    #[test]
+    fn bundler_honours_reskinignore() {
+        let root = std::env::temp_dir().join(format!("reskin-filter-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["gtk-3.0", "sources", ".git"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["gtk-3.0/gtk.css", "gtk-3.0/.gtk.css.swp", "sources/icon.xcf", "README.md", ".git/config"] {
+            fs::write(root.join(file), b"x").unwrap();
+        }
+        fs::write(root.join(IGNORE_FILE), "# Sources stay out\n*.xcf\nREADME.md\n").unwrap();
+
+        let mut assets = crate::bundle::resolve_theme_assets(&root, &[], &[]).unwrap();
+        assets.sort();
+        assert_eq!(assets, ["gtk-3.0", "gtk-3.0/gtk.css", "sources"]);
+
+        // Include patterns keep only matching files and the directories that hold them
+        let assets = crate::bundle::resolve_theme_assets(&root, &["*.css".to_string()], &[]).unwrap();
+        assert_eq!(assets, ["gtk-3.0", "gtk-3.0/gtk.css"]);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,31 +1,49 @@
 // Import necessary crates
 use std::path::{Path, PathBuf};
 use crate::types::BundleRequest;
+use crate::reskin_format::{BundleWriter, Codec, EntryKind, EntryMetadata};
+use crate::extract::{is_plain_dir, symlink_stays_inside};
+use crate::asset_filter::AssetFilter;
+use crate::lint::ensure_manifest_is_valid;
+use crate::pack::read_pack_file;
+use crate::signing::load_author_key;
+use ed25519_dalek::SigningKey;
+use crate::progress::{emit_to, Progress, ProgressReader};
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::BufWriter;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use tauri::AppHandle;
 
-// Helper function to recursively find all files relative to the root directory
-fn collect_relative_files_recursive(root_dir: &Path, dir: &Path, files: &mut Vec<String>) -> Result<(), String> {
+// Helper function to recursively find all files, directories and symlinks relative to the root directory
+// that the filter keeps. `included` is set once a parent directory matched an include pattern.
+fn collect_relative_files_recursive(root_dir: &Path, dir: &Path, filter: &AssetFilter, included: bool, files: &mut Vec<String>) -> Result<(), String> {
     let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
 
     for entry in entries {
         let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
         let path = entry.path();
+        let is_dir = entry.file_type().map_err(|e| format!("Failed to read entry: {}", e))?.is_dir(); // Does not follow symlinks
 
-        // Skip hidden directories (like .git, .vscode)
-        if path.is_dir() {
-            if let Some(dir_name) = path.file_name() {
-                if dir_name.to_string_lossy().starts_with('.') {
-                    continue;
-                }
+        let relative_path = path
+            .strip_prefix(root_dir)
+            .map_err(|e| format!("Failed to strip prefix for {}: {}", path.display(), e))?
+            .to_string_lossy()
+            .into_owned();
+
+        // Skip hidden directories (like .git, .vscode) and whatever .reskinignore lists
+        if filter.is_ignored(&relative_path, is_dir) {
+            continue;
+        }
+        let included = included || filter.is_included(&relative_path, is_dir);
+
+        if is_dir {
+            // Keep a directory if it was included itself or ends up holding something that was
+            let first_child = files.len();
+            collect_relative_files_recursive(root_dir, &path, filter, included, files)?; // Recursive call
+            if included || files.len() > first_child {
+                files.insert(first_child, relative_path);
             }
-            collect_relative_files_recursive(root_dir, &path, files)?; // Recursive call
-        } else if path.is_file() {
-            let relative_path = path
-                .strip_prefix(root_dir)
-                .map_err(|e| format!("Failed to strip prefix for {}: {}", path.display(), e))?
-                .to_string_lossy()
-                .into_owned();
+        } else if included {
             files.push(relative_path); // Push relative path
         }
     }
@@ -33,6 +51,101 @@ fn collect_relative_files_recursive(root_dir: &Path, dir: &Path, files: &mut Vec
     Ok(())
 }
 
+// List the entries of a theme directory that .reskinignore and the include/exclude patterns keep
+pub fn resolve_theme_assets(theme_root: &Path, include: &[String], exclude: &[String]) -> Result<Vec<String>, String> {
+    let filter = AssetFilter::for_theme_directory(theme_root, include, exclude)?;
+    let mut relative_files = Vec::new();
+    collect_relative_files_recursive(theme_root, theme_root, &filter, false, &mut relative_files)?;
+    relative_files.sort(); // Same order as in the bundle
+    Ok(relative_files)
+}
+
+enum AssetSource { // Where the data of an entry comes from
+    File(u64), // Regular file of the given size
+    Directory, // Directory, which has no data
+    Symlink(String), // Symlink with the given target
+}
+
+#[derive(Clone, Copy)]
+enum MetadataRule { // What a bundle records as modes and modification times
+    OnDisk(Option<i64>), // The modes and times on disk, with times clamped to the given time when there is one
+    Normalized(i64), // Modes reduced to the executable bit and one time for every entry
+}
+
+impl MetadataRule {
+    // Bundles record the metadata on disk unless the caller asks for it to be normalized, for checkouts
+    // whose umask and times differ. Either way SOURCE_DATE_EPOCH, when set, is the latest time recorded.
+    fn for_request(request: &BundleRequest) -> Self {
+        match (request.normalize_metadata, source_date_epoch()) {
+            (false, clamp) => MetadataRule::OnDisk(clamp),
+            (true, epoch) => MetadataRule::Normalized(epoch.unwrap_or(0)),
+        }
+    }
+}
+
+// Record the metadata of an entry as `rule` says. Normalized permissions keep only the executable bit,
+// like git does.
+fn entry_metadata(kind: EntryKind, metadata: &fs::Metadata, rule: MetadataRule) -> EntryMetadata {
+    let mode = match (kind, rule) {
+        (EntryKind::Symlink, _) => 0o777,
+        (_, MetadataRule::OnDisk(_)) => metadata.permissions().mode() & 0o777,
+        (EntryKind::Directory, MetadataRule::Normalized(_)) => 0o755,
+        (EntryKind::File, MetadataRule::Normalized(_)) if metadata.permissions().mode() & 0o111 != 0 => 0o755,
+        (EntryKind::File, MetadataRule::Normalized(_)) => 0o644,
+    };
+    let mtime = match rule {
+        MetadataRule::OnDisk(Some(clamp)) => metadata.mtime().min(clamp),
+        MetadataRule::OnDisk(None) => metadata.mtime(),
+        MetadataRule::Normalized(mtime) => mtime,
+    };
+    EntryMetadata { kind, mode, mtime }
+}
+
+fn source_date_epoch() -> Option<i64> { // Reproducible-builds timestamp, see reproducible-builds.org
+    std::env::var("SOURCE_DATE_EPOCH").ok()?.trim().parse().ok()
+}
+
+// Work out how to store one asset. Symlinks that stay inside the theme are kept as links, while links
+// that point outside it are replaced by what they point at.
+fn describe_asset(theme_root: &Path, relative_path: &str, rule: MetadataRule) -> Result<(EntryMetadata, AssetSource), String> {
+    let full_path = theme_root.join(relative_path);
+    let read_error = |e: std::io::Error| format!("Failed to read asset {}: {}", full_path.display(), e);
+    let mut metadata = fs::symlink_metadata(&full_path).map_err(read_error)?;
+
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(&full_path).map_err(read_error)?;
+        match target.to_str() {
+            Some(target) if symlink_stays_inside(relative_path, target, |path| is_plain_dir(&theme_root.join(path))) => {
+                return Ok((entry_metadata(EntryKind::Symlink, &metadata, rule), AssetSource::Symlink(target.to_string())));
+            }
+            _ => metadata = fs::metadata(&full_path).map_err(read_error)?,
+        }
+    }
+
+    if metadata.is_dir() {
+        Ok((entry_metadata(EntryKind::Directory, &metadata, rule), AssetSource::Directory))
+    } else {
+        Ok((entry_metadata(EntryKind::File, &metadata, rule), AssetSource::File(metadata.len())))
+    }
+}
+
+// Pick how an asset is stored, skipping formats that are already compressed
+fn choose_codec(relative_path: &str) -> Codec {
+    let precompressed = ["png", "jpg", "jpeg", "gif", "webp", "svgz", "woff", "woff2",
+                         "gz", "xz", "bz2", "zst", "zip", "7z", "ogg", "oga", "mp3"];
+
+    let extension = Path::new(relative_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    if precompressed.contains(&extension.as_str()) {
+        Codec::Store
+    } else {
+        Codec::Zstd
+    }
+}
+
 #[tauri::command]
 #[allow(non_snake_case)] // Allow variables to be camelCase
 pub fn _create_theme_dir(path: String) -> Result<String, String> {
@@ -43,60 +156,108 @@ pub fn _create_theme_dir(path: String) -> Result<String, String> {
 
 #[tauri::command]
 #[allow(non_snake_case)] // Allow variables to be camelCase
-pub fn bundle_theme(mut request: BundleRequest) -> Result<String, String> {
-    let magic = b"RSKN"; // Magic number
+pub fn bundle_theme(app: AppHandle, request: BundleRequest) -> Result<String, String> {
+    write_bundle(request, &mut emit_to(app, "bundle-progress"))
+}
 
-    let manifest_json = serde_json::to_vec(&request.manifest)
-        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+// Write the bundle described by `request`, streaming each asset and reporting the bytes read so far. It is
+// signed with the author's key when one has been generated.
+pub fn write_bundle(request: BundleRequest, progress: &mut Progress) -> Result<String, String> {
+    let key = load_author_key()?;
+    write_bundle_signed_by(request, key, progress)
+}
+
+// Write a bundle of files the author did not make, such as a downloaded archive, which their key must not vouch for
+pub fn write_unsigned_bundle(request: BundleRequest, progress: &mut Progress) -> Result<String, String> {
+    write_bundle_signed_by(request, None, progress)
+}
 
+fn write_bundle_signed_by(mut request: BundleRequest, key: Option<SigningKey>, progress: &mut Progress) -> Result<String, String> {
     let theme_root = request.theme_directory
         .as_ref()
         .map(PathBuf::from)
         .ok_or_else(|| "Theme directory must be provided if assets are listed".to_string())?;
 
+    // A reskin-pack.json in the theme root turns the bundle into a rice pack
+    if request.manifest.pack.is_none() {
+        request.manifest.pack = read_pack_file(&theme_root)?;
+    }
+    ensure_manifest_is_valid(&request.manifest)?;
+
     // Auto-collect files if assets list is empty
     if request.assets.is_empty() {
         println!("Auto-collecting assets from theme directory...");
-        let mut relative_files = Vec::new();
-        collect_relative_files_recursive(&theme_root, &theme_root, &mut relative_files)?;
+        let relative_files = resolve_theme_assets(&theme_root, &request.include, &request.exclude)?;
         println!("Found {} files", relative_files.len());
         request.assets = relative_files;
     }
 
+    // Sort so the output does not depend on directory listing order; parents sort before their contents
+    request.assets.sort();
+    request.assets.dedup();
+
+    // Asset sizes go into the entry headers and make up the progress total
+    let rule = MetadataRule::for_request(&request);
+    let mut sources = Vec::with_capacity(request.assets.len());
+    for relative_path_str in &request.assets {
+        sources.push(describe_asset(&theme_root, relative_path_str, rule)?);
+    }
+    let total_size = sources.iter()
+        .map(|(_, source)| if let AssetSource::File(size) = source { *size } else { 0 })
+        .sum();
+    progress.start("bundling", total_size);
+
     // Create .reskin output file
-    let mut file = File::create(&request.output_path)
+    let file = File::create(&request.output_path)
         .map_err(|e| format!("Failed to create file: {}", e))?;
 
-    // Write header (magic + manifest length + manifest JSON)
-    file.write_all(magic).map_err(|e| format!("Write error: {}", e))?;
-    let len_bytes = (manifest_json.len() as u64).to_le_bytes();
-    file.write_all(&len_bytes).map_err(|e| format!("Failed to write manifest length: {}", e))?;
-    file.write_all(&manifest_json).map_err(|e| format!("Failed to write manifest data: {}", e))?;
+    // Write header (magic + version + manifest)
+    let mut writer = BundleWriter::new(BufWriter::new(file), &request.manifest)?;
 
     // Write each asset
-    for relative_path_str in &request.assets {
+    for (relative_path_str, (metadata, source)) in request.assets.iter().zip(sources) {
         let full_path = theme_root.join(relative_path_str);
         println!("Bundling asset: {}", full_path.display());
 
-        let asset_data = fs::read(&full_path)
-            .map_err(|e| format!("Failed to read asset {}: {}", full_path.display(), e))?;
+        match source {
+            AssetSource::File(size) => {
+                let asset_file = File::open(&full_path)
+                    .map_err(|e| format!("Failed to read asset {}: {}", full_path.display(), e))?;
+                writer.add_asset(relative_path_str, metadata, size, ProgressReader::new(asset_file, progress), choose_codec(relative_path_str))?;
+            }
+            AssetSource::Directory => writer.add_asset(relative_path_str, metadata, 0, std::io::empty(), Codec::Store)?,
+            AssetSource::Symlink(target) => {
+                writer.add_asset(relative_path_str, metadata, target.len() as u64, target.as_bytes(), Codec::Store)?
+            }
+        }
+    }
+
+    let signed = match key {
+        Some(key) => {
+            writer.sign_with(key);
+            true
+        }
+        None => false,
+    };
 
-        let filename_bytes = relative_path_str.as_bytes();
-        let filename_len = (filename_bytes.len() as u32).to_le_bytes();
-        let asset_len = (asset_data.len() as u64).to_le_bytes();
+    writer.finish()?;
+    progress.finish();
 
-        file.write_all(&filename_len).map_err(|e| format!("Failed to write filename length: {}", e))?; // Write filename length
-        file.write_all(filename_bytes).map_err(|e| format!("Failed to write filename: {}", e))?; // Write filename bytes
-        file.write_all(&asset_len).map_err(|e| format!("Failed to write asset length: {}", e))?; // Write asset length
-        file.write_all(&asset_data).map_err(|e| format!("Failed to write asset data: {}", e))?; // Write asset data
+    if signed {
+        Ok(format!("Signed bundle created successfully at {}", request.output_path)) // Return success
+    } else {
+        Ok(format!("Bundle created successfully at {}", request.output_path)) // Return success
     }
-
-    Ok(format!("Bundle created successfully at {}", request.output_path)) // Return success
 }
 
 #[tauri::command]
 #[allow(non_snake_case)] // Allow variables to be camelCase
-pub fn bundle_theme_from_directory(mut request: BundleRequest) -> Result<String, String> {
+pub fn bundle_theme_from_directory(app: AppHandle, request: BundleRequest) -> Result<String, String> {
+    write_bundle(collect_directory_assets(request)?, &mut emit_to(app, "bundle-progress")) // Bundle theme with the request data
+}
+
+// Fill in the asset list of `request` with every file in its theme directory
+pub fn collect_directory_assets(mut request: BundleRequest) -> Result<BundleRequest, String> {
     let dir = request.theme_directory.clone().ok_or_else(|| "No base directory provided".to_string())?;
     let dir_path = Path::new(&dir);
 
@@ -105,8 +266,7 @@ pub fn bundle_theme_from_directory(mut request: BundleRequest) -> Result<String,
     }
 
     // Collect all files
-    let mut relative_files = Vec::new();
-    collect_relative_files_recursive(dir_path, dir_path, &mut relative_files)?;
+    let relative_files = resolve_theme_assets(dir_path, &request.include, &request.exclude)?;
 
     if relative_files.is_empty() {
         eprintln!("Warning: No files found in theme directory {}", dir); // Return error when theme directory is empty
@@ -118,5 +278,97 @@ pub fn bundle_theme_from_directory(mut request: BundleRequest) -> Result<String,
     }
 
     request.assets = relative_files;
-    bundle_theme(request) // Bundle theme with the request data
+    Ok(request)
+}
+
+#[tauri::command]
+pub fn preview_bundle_assets(theme_directory: String, include: Option<Vec<String>>, exclude: Option<Vec<String>>) -> Result<Vec<String>, String> {
+    // Dry run: the entries bundle_theme_from_directory would pack, without writing anything
+    let dir_path = Path::new(&theme_directory);
+    if !dir_path.is_dir() {
+        return Err(format!("Theme directory '{}' does not exist or is not a directory", theme_directory));
+    }
+    resolve_theme_assets(dir_path, &include.unwrap_or_default(), &exclude.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scratch_dir, test_manifest};
+    use filetime::{set_symlink_file_times, FileTime};
+
+    // Build the same theme tree, creating entries in the given order with the given file mode
+    fn build_tree(root: &Path, order: &[&str], mode: u32) {
+        let _ = fs::remove_dir_all(root);
+        fs::create_dir_all(root).unwrap();
+        for name in order {
+            match *name {
+                "icons" => fs::create_dir(root.join("icons")).unwrap(),
+                "icons/alias.svg" => std::os::unix::fs::symlink("real.svg", root.join(name)).unwrap(),
+                _ => {
+                    fs::write(root.join(name), format!("contents of {}", name).repeat(100)).unwrap();
+                    fs::set_permissions(root.join(name), fs::Permissions::from_mode(mode)).unwrap();
+                }
+            }
+        }
+        for name in order.iter().rev() { // Children first, so setting their times does not touch the parent's
+            let time = FileTime::from_unix_time(1_650_000_000, 0);
+            set_symlink_file_times(root.join(name), time, time).unwrap();
+        }
+    }
+
+    fn bundle(root: &Path, output: &Path, normalize_metadata: bool) -> Vec<u8> {
+        let request = collect_directory_assets(BundleRequest {
+            manifest: test_manifest("Repro"),
+            output_path: output.to_string_lossy().into_owned(),
+            assets: Vec::new(),
+            theme_directory: Some(root.to_string_lossy().into_owned()),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            normalize_metadata,
+        }).unwrap();
+        write_bundle(request, &mut Progress::none()).unwrap();
+        fs::read(output).unwrap()
+    }
+
+    #[test]
+    fn bundles_are_reproducible() {
+        let base = scratch_dir("bundle-repro");
+        let first = ["index.theme", "icons", "icons/real.svg", "icons/alias.svg", "gtk.css"];
+        let second = ["gtk.css", "icons", "icons/alias.svg", "icons/real.svg", "index.theme"];
+
+        build_tree(&base.join("a"), &first, 0o644);
+        build_tree(&base.join("b"), &second, 0o644);
+        let a = bundle(&base.join("a"), &base.join("a.reskin"), false);
+        let b = bundle(&base.join("b"), &base.join("b.reskin"), false);
+        assert!(a == b, "bundles of identical trees differ");
+        assert!(bundle(&base.join("a"), &base.join("again.reskin"), false) == a);
+
+        // The real modes and times are recorded unless normalizing is asked for
+        let mut reader = crate::reskin_format::BundleReader::new(std::io::Cursor::new(&a)).unwrap();
+        let css = reader.entries().unwrap().into_iter().find(|entry| entry.path == "gtk.css").unwrap();
+        assert_eq!((css.mode, css.mtime), (Some(0o644), Some(1_650_000_000)));
+
+        // A different umask and touched files, as in a fresh checkout in CI, only bundle the same when normalized
+        build_tree(&base.join("b"), &second, 0o664);
+        let touched = FileTime::from_unix_time(1_700_000_000, 0);
+        for name in &second {
+            set_symlink_file_times(base.join("b").join(name), touched, touched).unwrap();
+        }
+        assert!(bundle(&base.join("b"), &base.join("b.reskin"), false) != a);
+        let a = bundle(&base.join("a"), &base.join("a.reskin"), true);
+        assert!(bundle(&base.join("b"), &base.join("b.reskin"), true) == a, "normalized bundles of identical trees differ");
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn only_embeds_the_named_preview() {
+        let dir = scratch_dir("bundle-preview");
+        build_tree(&dir.join("theme"), &["gtk.css", "preview.png"], 0o644);
+        let bytes = bundle(&dir.join("theme"), &dir.join("theme.reskin"), false);
+
+        let reader = crate::reskin_format::BundleReader::new(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.manifest().preview_image, None); // The linter suggests it instead
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
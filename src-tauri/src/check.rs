@@ -1,12 +1,13 @@
 // Import necessary crates
 use std::fs;
 
+// Top-level folders of GTK, window manager and shell themes
+pub const GTK_WM_DIRS: &[&str] = &["gtk-2.0", "gtk-3.0", "gtk-4.0", "xfwm4", "gnome-shell",
+                                   "cinnamon", "metacity-1", "openbox-3", "plank"];
+
 // Check if theme has GTK or window manager components
 pub fn has_gtk_or_wm_components(theme_path: &std::path::Path) -> bool {
-    let gtk_wm_dirs = ["gtk-2.0", "gtk-3.0", "gtk-4.0", "xfwm4", "gnome-shell", 
-                       "cinnamon", "metacity-1", "openbox-3", "plank"];
-    
-    gtk_wm_dirs.iter().any(|dir| theme_path.join(dir).exists())
+    GTK_WM_DIRS.iter().any(|dir| theme_path.join(dir).exists())
 }
 
 // Check if theme has icons
@@ -38,4 +39,8 @@ pub fn has_fonts(theme_path: &std::path::Path) -> bool {
         }
     }
     false
-}
\ No newline at end of file
+}
+// Check if theme has a desktop shell theme
+pub fn has_shell_theme(theme_path: &std::path::Path) -> bool {
+    ["gnome-shell", "cinnamon"].iter().any(|dir| theme_path.join(dir).exists())
+}
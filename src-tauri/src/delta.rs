@@ -0,0 +1,335 @@
+// Import necessary crates
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use crate::extract::validate_entry_path;
+use crate::locations::InstallRoots;
+use crate::pack::install_config;
+use crate::progress::{emit_to, Progress};
+use crate::registry::{load_installed_theme, save_installed_theme, tree_paths};
+use crate::reskin_format::{to_hex, BundleEntry, BundleReader, BundleWriter, EntryKind, EntryMetadata};
+use crate::signing::load_author_key;
+use crate::types::{InstallScope, ThemeDelta, ThemeManifest, variant_theme_name};
+use crate::transaction::InstallTransaction;
+use crate::routing::{copy_routed, route_path, route_theme, Route};
+use crate::utils::copy_dir_recursive;
+
+#[tauri::command]
+pub fn create_delta(app: AppHandle, base_path: String, new_path: String, output_path: String) -> Result<String, String> {
+    // Package only what changed between two releases of a theme; installing the result upgrades the older one
+    write_delta(Path::new(&base_path), Path::new(&new_path), Path::new(&output_path), &mut emit_to(app, "bundle-progress"))
+}
+
+fn open_bundle(path: &Path) -> Result<BundleReader<BufReader<File>>, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open bundle {}: {}", path.display(), e))?;
+    BundleReader::new(BufReader::new(file))
+}
+
+// Hex bundle hash of a full bundle, which is what deltas are keyed on
+fn full_bundle_hash(reader: &mut BundleReader<BufReader<File>>, path: &Path) -> Result<String, String> {
+    if reader.manifest().delta.is_some() {
+        return Err(format!("{} is already a delta; deltas are made between full bundles", path.display()));
+    }
+    reader.bundle_sha256()?
+        .map(|hash| to_hex(&hash))
+        .ok_or_else(|| format!("{} has no checksums; rebuild it with this version of Reskin", path.display()))
+}
+
+// Touching a file without changing it is not worth a download, so mtimes are not compared
+fn is_changed(old: &BundleEntry, new: &BundleEntry) -> bool {
+    old.kind != new.kind || old.mode != new.mode || old.sha256.is_none() || old.sha256 != new.sha256
+}
+
+// Write a delta bundle with the assets of `new_path` that are new or differ from `base_path`,
+// plus the paths `new_path` no longer has
+pub fn write_delta(base_path: &Path, new_path: &Path, output_path: &Path, progress: &mut Progress) -> Result<String, String> {
+    let mut base = open_bundle(base_path)?;
+    let mut new = open_bundle(new_path)?;
+    if base.manifest().name != new.manifest().name {
+        return Err(format!("Cannot make a delta between different themes ('{}' and '{}')", base.manifest().name, new.manifest().name));
+    }
+    let base_sha256 = full_bundle_hash(&mut base, base_path)?;
+    let target_sha256 = full_bundle_hash(&mut new, new_path)?;
+
+    let base_entries: HashMap<String, BundleEntry> = base.entries()?
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    let new_entries = new.entries()?;
+    let new_paths: HashSet<&str> = new_entries.iter().map(|entry| entry.path.as_str()).collect();
+
+    let changed: Vec<&BundleEntry> = new_entries.iter()
+        .filter(|entry| base_entries.get(&entry.path).is_none_or(|old| is_changed(old, entry)))
+        .collect();
+    let mut deleted: Vec<String> = base_entries.keys()
+        .filter(|path| !new_paths.contains(path.as_str()))
+        .cloned()
+        .collect();
+    deleted.sort();
+
+    let mut manifest = new.manifest().clone();
+    manifest.delta = Some(ThemeDelta {
+        base_version: base.manifest().version.clone(),
+        base_sha256,
+        target_sha256,
+        deleted,
+    });
+
+    progress.start("bundling", changed.iter().map(|entry| entry.size).sum());
+    let result = write_delta_assets(&mut new, &manifest, &changed, output_path, progress);
+    if result.is_err() {
+        let _ = fs::remove_file(output_path);
+    }
+    result?;
+    progress.finish();
+
+    let removed = manifest.delta.as_ref().map_or(0, |delta| delta.deleted.len());
+    Ok(format!("Delta created successfully at {}\nChanged: {}, removed: {}", output_path.display(), changed.len(), removed))
+}
+
+fn write_delta_assets(new: &mut BundleReader<BufReader<File>>, manifest: &ThemeManifest, changed: &[&BundleEntry], output_path: &Path, progress: &mut Progress) -> Result<(), String> {
+    let file = File::create(output_path)
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+    let mut writer = BundleWriter::new(BufWriter::new(file), manifest)?;
+
+    for entry in changed {
+        let default_mode = if entry.kind == EntryKind::Directory { 0o755 } else { 0o644 };
+        let metadata = EntryMetadata { kind: entry.kind, mode: entry.mode.unwrap_or(default_mode), mtime: entry.mtime.unwrap_or(0) };
+        // Streamed straight from the new bundle, so large assets are never held in memory whole
+        let mut asset = writer.start_asset(&entry.path, metadata, entry.size, entry.codec)?;
+        new.copy_entry(entry, &mut asset)?;
+        asset.finish()?;
+        progress.advance(entry.size);
+    }
+
+    // Deltas are signed like full bundles, so requiring signatures also covers updates
+    if let Some(key) = load_author_key()? {
+        writer.sign_with(key);
+    }
+    writer.finish()?
+        .flush()
+        .map_err(|e| format!("Failed to write delta: {}", e))
+}
+
+// Parts of a theme that were installed as themes of their own: the directory inside the bundle
+// each one came from, and the name it was installed under
+fn installed_parts(manifest: &ThemeManifest) -> Vec<(String, String)> {
+    if let Some(pack) = &manifest.pack {
+        pack.themes.iter().map(|theme| (theme.path.clone(), theme.name.clone())).collect()
+    } else if !manifest.variants.is_empty() {
+        manifest.variants.iter()
+            .map(|variant| (variant.path.clone(), variant_theme_name(&manifest.name, &variant.name)))
+            .collect()
+    } else {
+        vec![(String::new(), manifest.name.clone())]
+    }
+}
+
+// Path of a bundle entry relative to the part under `prefix`, None when it belongs to another part
+fn part_relative<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        return Some(path);
+    }
+    path.strip_prefix(prefix)?.strip_prefix('/').filter(|rest| !rest.is_empty())
+}
+
+// Delete an installed file or folder, refusing to follow a symlink on the way there
+fn remove_installed(root: &Path, relative: &str) -> Result<(), String> {
+    let relative = validate_entry_path(relative).map_err(|e| e.to_string())?;
+    let mut current = root.to_path_buf();
+    for component in relative.components() {
+        if fs::symlink_metadata(&current).is_ok_and(|meta| meta.file_type().is_symlink()) {
+            return Err(format!("Refusing to delete {}: {} is a symlink", relative.display(), current.display()));
+        }
+        current.push(component);
+    }
+
+    let result = match fs::symlink_metadata(&current) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(&current),
+        Ok(_) => fs::remove_file(&current),
+        Err(_) => return Ok(()), // Already gone
+    };
+    result.map_err(|e| format!("Failed to delete {}: {}", current.display(), e))
+}
+
+// Upgrade an installed theme in place from a delta unpacked at `extracted_path`: changed and added
+// assets are copied over every copy installed in `roots`, and deleted ones are removed from them
+pub fn apply_delta(extracted_path: &Path, manifest: &ThemeManifest, delta: &ThemeDelta, home_dir: &str, roots: &InstallRoots) -> Result<String, String> {
+    let mut installed = load_installed_theme(home_dir, &manifest.name)?
+        .ok_or_else(|| format!("Theme '{}' is not installed, so this update cannot be applied", manifest.name))?;
+    if installed.scope == InstallScope::System {
+        return Err(format!("'{}' is installed system-wide; install the full bundle for all users instead", manifest.name));
+    }
+    if installed.bundle_sha256.as_deref() != Some(delta.base_sha256.as_str()) {
+        return Err(format!(
+            "This update is for '{}' {}, but the installed copy is {}; install the full bundle instead",
+            manifest.name, delta.base_version, installed.manifest.version
+        ));
+    }
+    if installed.manifest.variants != manifest.variants || installed.manifest.pack != manifest.pack {
+        return Err(format!("'{}' {} is laid out differently; install the full bundle instead", manifest.name, manifest.version));
+    }
+    for path in &delta.deleted {
+        validate_entry_path(path).map_err(|e| e.to_string())?;
+    }
+
+    let mut transaction = InstallTransaction::default(); // Every copy is patched on the side and swapped in
+    let mut updated = Vec::new();
+    let (mut added, mut removed) = (Vec::new(), Vec::new()); // Paths for the install record
+    for (prefix, theme_name) in installed_parts(manifest) {
+        let source = match prefix.trim_matches('/') {
+            "" => extracted_path.to_path_buf(),
+            prefix => extracted_path.join(validate_entry_path(prefix).map_err(|e| e.to_string())?),
+        };
+        let entries = route_theme(&source);
+        let deleted: Vec<(Route, PathBuf)> = delta.deleted.iter()
+            .filter_map(|path| part_relative(path, &prefix))
+            .flat_map(|path| route_path(&source, Path::new(path)))
+            .collect();
+
+        // Only the copies that were installed are touched, each with the entries routed to it at install
+        // time. Files of no component went with the GTK theme when there was one.
+        let others_to_theme = roots.themes.join(&theme_name).is_dir();
+        let copies = [
+            (roots.themes.join(&theme_name), vec![Route::Theme, Route::Other]),
+            (roots.icons.join(&theme_name), if others_to_theme { vec![Route::Icons, Route::Cursors] } else { vec![Route::Icons, Route::Cursors, Route::Other] }),
+            (roots.fonts.join(&theme_name), vec![Route::Fonts]),
+        ];
+        let mut patched = false;
+        for (dest, routes) in copies.iter().filter(|(dest, _)| dest.is_dir()) {
+            let deleted: Vec<&PathBuf> = deleted.iter().filter(|(route, _)| routes.contains(route)).map(|(_, path)| path).collect();
+            transaction.replace_dir(dest, |staging| {
+                copy_dir_recursive(&dest.to_string_lossy(), &staging.to_string_lossy())
+                    .map_err(|e| format!("Failed to copy {}: {}", dest.display(), e))?;
+                copy_routed(&entries, routes, staging)
+                    .map_err(|e| format!("Failed to update {}: {}", dest.display(), e))?;
+                deleted.iter().try_for_each(|path| remove_installed(staging, &path.to_string_lossy()))
+            })?;
+            for entry in entries.iter().filter(|entry| routes.contains(&entry.route)) {
+                for path in tree_paths(&entry.source) {
+                    let relative = Path::new(&path).strip_prefix(&entry.source).unwrap_or(Path::new(""));
+                    added.push(dest.join(&entry.dest).join(relative).to_string_lossy().into_owned());
+                }
+            }
+            removed.extend(deleted.iter().map(|path| dest.join(path)));
+            patched = true;
+        }
+
+        if patched {
+            updated.push(theme_name);
+        }
+    }
+
+    // Config files of a pack are only rewritten when the delta changes them
+    if let Some(pack) = &manifest.pack {
+        for config in &pack.configs {
+            let source = extracted_path.join(validate_entry_path(&config.source).map_err(|e| e.to_string())?);
+            if source.is_file() {
+                let dest = install_config(&source, &config.target, home_dir, &mut transaction)?.to_string_lossy().into_owned();
+                if !installed.configs.contains(&dest) {
+                    installed.configs.push(dest.clone());
+                }
+                updated.push(dest);
+            }
+        }
+    }
+
+    // Keep the install record in step, so uninstalling removes exactly what is there now
+    installed.files.retain(|file| !removed.iter().any(|path| Path::new(file).starts_with(path)));
+    installed.files.extend(added);
+    installed.files.sort();
+    installed.files.dedup();
+    save_installed_theme(home_dir, &manifest.name, &installed)?;
+    transaction.commit();
+
+    Ok(format!(
+        "Theme '{}' updated from {} to {}\nUpdated: {}\nRemoved files: {}",
+        manifest.name, delta.base_version, manifest.version, updated.join(", "), delta.deleted.len()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bundle::write_bundle;
+    use crate::extract::{extract_bundle_to, ExtractLimits};
+    use crate::registry::{bundle_hash, record_install, set_bundle_hash};
+    use crate::types::BundleRequest;
+
+    #[test]
+    fn upgrades_installed_themes_with_deltas() {
+        let root = std::env::temp_dir().join(format!("reskin-delta-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let write_release = |version: &str, files: &[(&str, &str)]| {
+            let folder = root.join(version);
+            for (path, contents) in files {
+                fs::create_dir_all(folder.join(path).parent().unwrap()).unwrap();
+                fs::write(folder.join(path), contents).unwrap();
+            }
+            let output = root.join(format!("{}.reskin", version));
+            let request = BundleRequest {
+                manifest: ThemeManifest {
+                    name: "Nordic".into(),
+                    author: "Ann".into(),
+                    description: "Test".into(),
+                    version: version.into(),
+                    license: "MIT".into(),
+                    ..Default::default()
+                },
+                output_path: output.to_string_lossy().into_owned(),
+                assets: Vec::new(),
+                theme_directory: Some(folder.to_string_lossy().into_owned()),
+                include: Vec::new(),
+                exclude: Vec::new(),
+                normalize_metadata: false,
+            };
+            write_bundle(request, &mut Progress::none()).unwrap();
+            output
+        };
+        let old = write_release("1.0.0", &[("gtk-3.0/gtk.css", "old"), ("gtk-3.0/assets/a.svg", "a"), ("gtk-3.0/unused.css", "x")]);
+        let new = write_release("1.0.1", &[("gtk-3.0/gtk.css", "new"), ("gtk-3.0/assets/a.svg", "a"), ("gtk-3.0/assets/b.svg", "b")]);
+        let unpack = |bundle: &Path, folder: &str| {
+            let mut reader = open_bundle(bundle).unwrap();
+            extract_bundle_to(&mut reader, &root.join(folder), &ExtractLimits::default()).unwrap();
+            (root.join(folder), reader.manifest().clone())
+        };
+
+        // Only the changed and added files go into the delta
+        let delta_path = root.join("1.0.1.delta.reskin");
+        write_delta(&old, &new, &delta_path, &mut Progress::none()).unwrap();
+        let mut reader = open_bundle(&delta_path).unwrap();
+        let paths: Vec<String> = reader.entries().unwrap().into_iter().map(|entry| entry.path).collect();
+        assert_eq!(paths, ["gtk-3.0/assets/b.svg", "gtk-3.0/gtk.css"]);
+        let (delta_dir, manifest) = unpack(&delta_path, "delta");
+        let delta = manifest.delta.clone().unwrap();
+        assert_eq!(delta.deleted, ["gtk-3.0/unused.css"]);
+
+        // Without the base version installed the delta is refused
+        let home = root.join("home");
+        let home_dir = home.to_string_lossy();
+        let roots = InstallRoots::resolve(&home_dir, &Default::default(), None).unwrap();
+        assert!(apply_delta(&delta_dir, &manifest, &delta, &home_dir, &roots).is_err());
+
+        let (old_dir, old_manifest) = unpack(&old, "old");
+        let installed = roots.themes.join("Nordic");
+        copy_dir_recursive(&old_dir.to_string_lossy(), &installed.to_string_lossy()).unwrap();
+        record_install(&home_dir, "Nordic", &old_manifest, InstallScope::User, tree_paths(&installed), Vec::new()).unwrap();
+        set_bundle_hash(&home_dir, &old_manifest, bundle_hash(&File::open(&old).unwrap()).unwrap()).unwrap();
+
+        apply_delta(&delta_dir, &manifest, &delta, &home_dir, &roots).unwrap();
+        assert_eq!(fs::read_to_string(installed.join("gtk-3.0/gtk.css")).unwrap(), "new");
+        assert!(installed.join("gtk-3.0/assets/a.svg").is_file() && installed.join("gtk-3.0/assets/b.svg").is_file());
+        assert!(!installed.join("gtk-3.0/unused.css").exists());
+        assert!(!roots.icons.join("Nordic").exists()); // Parts that were not installed stay that way
+
+        // The install record follows the files
+        let files = load_installed_theme(&home_dir, "Nordic").unwrap().unwrap().files;
+        assert!(files.contains(&installed.join("gtk-3.0/assets/b.svg").to_string_lossy().into_owned()));
+        assert!(!files.contains(&installed.join("gtk-3.0/unused.css").to_string_lossy().into_owned()));
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,388 @@
+// Import necessary crates
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{EntryType, Header};
+use tauri::AppHandle;
+use crate::extract::{symlink_stays_inside, validate_entry_path, validate_theme_name};
+use crate::progress::{emit_to, Progress, ProgressReader};
+use crate::reskin_format::{BundleReader, EntryKind};
+use crate::verify::open_bundle;
+use crate::bundle::{resolve_theme_assets, write_bundle};
+use crate::check::{has_cursors, has_gtk_or_wm_components, has_shell_theme};
+use crate::import::guess_manifest;
+use crate::locations::{cache_dir, home_dir, install_roots, private_dir, InstallRoots};
+use crate::types::{BundleRequest, PackTheme, RicePack, ThemeComponent};
+
+const BLOCK_SIZE: u64 = 512; // Tar data is padded to whole blocks
+
+#[tauri::command]
+pub fn export_bundle(app: AppHandle, bundle_path: String, output_path: String) -> Result<String, String> {
+    // Turn a .reskin into a .tar.gz that unpacks to <theme name>/ with reskin.json next to the assets
+    write_tar_gz(Path::new(&bundle_path), Path::new(&output_path), &mut emit_to(app, "export-progress"))?;
+    Ok(format!("Theme exported to {}", output_path))
+}
+
+// Write the bundle as a gzip-compressed tar archive, removing the half-written archive on failure
+pub fn write_tar_gz(bundle_path: &Path, output_path: &Path, progress: &mut Progress) -> Result<(), String> {
+    let output = File::create(output_path)
+        .map_err(|e| format!("Failed to create archive: {}", e))?;
+    let result = write_tar(bundle_path, GzEncoder::new(BufWriter::new(output), Compression::default()), progress)
+        .and_then(|encoder| encoder.finish().map_err(|e| format!("Failed to write archive: {}", e)))
+        .and_then(|mut writer| writer.flush().map_err(|e| format!("Failed to write archive: {}", e)));
+    if result.is_err() {
+        let _ = fs::remove_file(output_path);
+    }
+    result
+}
+
+// Copy every bundle entry into a tar stream, keeping the recorded modes, mtimes and symlinks
+fn write_tar<W: Write>(bundle_path: &Path, out: W, progress: &mut Progress) -> Result<W, String> {
+    let write_error = |e: io::Error| format!("Failed to write archive: {}", e);
+
+    let file = File::open(bundle_path)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let metadata = file.metadata()
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    // Legacy bundles record no mtimes, so their entries get the time of the bundle itself
+    let bundle_mtime = metadata.modified().ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs());
+    progress.start("exporting", metadata.len());
+
+    // Symlinked folders cannot be checked entry by entry while streaming, so find them all first
+    let links: HashSet<PathBuf> = open_bundle(&file)?
+        .entries()?
+        .into_iter()
+        .filter(|entry| entry.kind == EntryKind::Symlink)
+        .map(|entry| PathBuf::from(entry.path))
+        .collect();
+    (&file).seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+
+    let mut reader = BundleReader::new(BufReader::new(ProgressReader::new(file, progress)))?;
+    let theme_name = reader.manifest().name.clone();
+    validate_theme_name(&theme_name).map_err(|e| e.to_string())?;
+
+    let mut builder = tar::Builder::new(out);
+    let mut header = entry_header(EntryType::Directory, 0o755, bundle_mtime, 0);
+    builder.append_data(&mut header, format!("{}/", theme_name), io::empty()).map_err(write_error)?;
+
+    let manifest_json = reader.manifest_json().to_vec();
+    let mut header = entry_header(EntryType::Regular, 0o644, bundle_mtime, manifest_json.len() as u64);
+    builder.append_data(&mut header, format!("{}/reskin.json", theme_name), &manifest_json[..]).map_err(write_error)?;
+
+    while let Some(entry) = reader.next_entry_header()? {
+        // Whoever unpacks the archive gets none of the checks Reskin makes, so unsafe paths are refused here
+        validate_entry_path(&entry.path).map_err(|e| e.to_string())?;
+        let path = format!("{}/{}", theme_name, entry.path);
+        let mtime = entry.mtime.map_or(bundle_mtime, |mtime| mtime.max(0) as u64);
+
+        match entry.kind {
+            EntryKind::File => {
+                let mode = entry.mode.unwrap_or(0o644);
+                let mut header = entry_header(EntryType::Regular, mode, mtime, entry.size);
+                // The Builder writes the header (and any long-name extension); the data is then streamed
+                // from the bundle straight after it, so large assets never sit in memory
+                builder.append_data(&mut header, &path, io::empty()).map_err(write_error)?;
+                let mut data = CountingWriter { inner: builder.get_mut(), count: 0 };
+                reader.copy_entry_data(&entry, &mut data)?;
+                if data.count != entry.size {
+                    return Err(format!("Invalid .reskin file: asset {} has the wrong size", entry.path));
+                }
+                let padding = (BLOCK_SIZE - entry.size % BLOCK_SIZE) % BLOCK_SIZE;
+                builder.get_mut().write_all(&[0u8; BLOCK_SIZE as usize][..padding as usize]).map_err(write_error)?;
+            }
+            EntryKind::Directory => {
+                reader.copy_entry_data(&entry, &mut io::sink())?;
+                let mut header = entry_header(EntryType::Directory, entry.mode.unwrap_or(0o755), mtime, 0);
+                builder.append_data(&mut header, format!("{}/", path), io::empty()).map_err(write_error)?;
+            }
+            EntryKind::Symlink => {
+                let mut target = Vec::new();
+                reader.copy_entry_data(&entry, &mut target)?;
+                let target = String::from_utf8(target)
+                    .map_err(|_| format!("Symlink {} has a target that is not valid UTF-8", entry.path))?;
+                if !symlink_stays_inside(&entry.path, &target, |path| !links.contains(path)) {
+                    return Err(format!("Symlink {} points outside the theme", entry.path));
+                }
+                let mut header = entry_header(EntryType::Symlink, 0o777, mtime, 0);
+                builder.append_link(&mut header, &path, &target).map_err(write_error)?;
+            }
+        }
+    }
+
+    progress.finish();
+    builder.into_inner().map_err(write_error)
+}
+
+#[tauri::command]
+pub fn export_installed_theme(app: AppHandle, name: String, output_path: Option<String>) -> Result<String, String> {
+    // Bundle a theme that is already installed, by default into ~/.cache/reskin/exports/<name>.reskin,
+    // which unlike /tmp no other account can plant links in. Returns where the bundle was written.
+    validate_theme_name(&name).map_err(|e| e.to_string())?;
+    let home_dir = home_dir()?;
+    let output_path = output_path.map_or_else(|| cache_dir(&home_dir).join("exports").join(format!("{}.reskin", name)), PathBuf::from);
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
+    export_installed(&name, &home_dir, &install_roots(&home_dir)?, &output_path, &mut emit_to(app, "bundle-progress"))?;
+    Ok(output_path.to_string_lossy().into_owned())
+}
+
+// Folders GTK and shell themes are installed to, in the order they are searched after the configured
+// install root, so user installs shadow system-wide ones. Relative paths are inside the home directory.
+const THEME_DIRS: &[&str] = &[".local/share/themes", ".themes", "/usr/share/themes"];
+const ICON_DIRS: &[&str] = &[".local/share/icons", ".icons", "/usr/share/icons"]; // Same for icon and cursor themes
+
+// Every installed copy of `name` in `root` and the given folders, in search order
+fn find_installed(name: &str, home_dir: &str, root: &Path, dirs: &[&str]) -> Vec<PathBuf> {
+    std::iter::once(root.to_path_buf())
+        .chain(dirs.iter().map(|dir| Path::new(home_dir).join(dir))) // Joining an absolute path replaces the home directory
+        .map(|dir| dir.join(name))
+        .filter(|path| path.is_dir())
+        .collect()
+}
+
+fn is_cursor_asset(asset: &str) -> bool {
+    asset == "cursor.theme" || asset == "cursors" || asset.starts_with("cursors/")
+}
+
+// Collect the GTK, icon and cursor parts of an installed theme and write them to `output_path` as a bundle.
+// A theme installed in both places becomes a rice pack, since both folders usually have an index.theme.
+pub fn export_installed(name: &str, home_dir: &str, roots: &InstallRoots, output_path: &Path, progress: &mut Progress) -> Result<String, String> {
+    validate_theme_name(name).map_err(|e| e.to_string())?;
+    let theme_dir = find_installed(name, home_dir, &roots.themes, THEME_DIRS).into_iter()
+        .find(|dir| has_gtk_or_wm_components(dir) || has_shell_theme(dir));
+    // Cursors are often installed apart from the icons of the same name, say in ~/.icons next to icons
+    // in ~/.local/share/icons, so each comes from the first copy that has it
+    let icon_copies = find_installed(name, home_dir, &roots.icons, ICON_DIRS);
+    let icon_dir = icon_copies.iter().find(|dir| icon_component(dir) == ThemeComponent::Icons);
+    let cursor_dir = icon_copies.iter().find(|dir| has_cursors(dir));
+    if theme_dir.is_none() && icon_dir.is_none() && cursor_dir.is_none() {
+        return Err(format!("No installed theme named '{}' was found", name));
+    }
+
+    // Parts from different folders are linked into one instead of copying what may be thousands of icons
+    let staging = private_dir(home_dir, "export")?;
+    let result = (|| {
+        let icon_part = match (icon_dir, cursor_dir) {
+            (Some(icons), Some(cursors)) if icons != cursors => {
+                let merged = staging.join("icons");
+                fs::create_dir(&merged).map_err(|e| format!("Failed to create staging directory: {}", e))?;
+                let mut assets = Vec::new();
+                for (source, cursor_part) in [(icons, false), (cursors, true)] {
+                    for asset in resolve_theme_assets(source, &[], &[])?.into_iter().filter(|asset| is_cursor_asset(asset) == cursor_part) {
+                        if !asset.contains('/') {
+                            symlink(source.join(&asset), merged.join(&asset)).map_err(|e| format!("Failed to stage {}: {}", asset, e))?;
+                        }
+                        assets.push(asset);
+                    }
+                }
+                Some((merged, assets))
+            }
+            (icons, cursors) => match icons.or(cursors) {
+                Some(dir) => Some((dir.clone(), resolve_theme_assets(dir, &[], &[])?)),
+                None => None,
+            },
+        };
+
+        let (theme_root, manifest, assets) = match (theme_dir, icon_part) {
+            (None, None) => unreachable!("checked above"),
+            (Some(dir), None) => {
+                let manifest = guess_manifest(&dir, Some(name), name);
+                (dir, manifest, Vec::new())
+            }
+            (None, Some((dir, assets))) => {
+                let manifest = guess_manifest(&dir, Some(name), name);
+                (dir, manifest, assets)
+            }
+            (Some(theme_dir), Some((icon_dir, icon_assets))) => {
+                let mut assets = Vec::new();
+                for (folder, source, source_assets) in [("gtk", &theme_dir, resolve_theme_assets(&theme_dir, &[], &[])?), ("icons", &icon_dir, icon_assets)] {
+                    let staged = staging.join(folder);
+                    if *source != staged {
+                        symlink(source, &staged).map_err(|e| format!("Failed to stage {}: {}", source.display(), e))?;
+                    }
+                    assets.push(folder.to_string());
+                    assets.extend(source_assets.into_iter().map(|asset| format!("{}/{}", folder, asset)));
+                }
+
+                let mut manifest = guess_manifest(&theme_dir, Some(name), name);
+                let icon_component = icon_component(&icon_dir);
+                manifest.components = vec![ThemeComponent::Gtk, icon_component];
+                if icon_component == ThemeComponent::Icons && has_cursors(&icon_dir) {
+                    manifest.components.push(ThemeComponent::Cursors); // Installed along with the icons
+                }
+                if has_shell_theme(&theme_dir) {
+                    manifest.components.push(ThemeComponent::Shell);
+                }
+                manifest.pack = Some(RicePack {
+                    themes: vec![
+                        PackTheme { name: name.to_string(), path: "gtk".into(), component: ThemeComponent::Gtk },
+                        PackTheme { name: name.to_string(), path: "icons".into(), component: icon_component },
+                    ],
+                    configs: Vec::new(),
+                });
+                (staging.clone(), manifest, assets)
+            }
+        };
+
+        let request = BundleRequest {
+            manifest,
+            output_path: output_path.to_string_lossy().into_owned(),
+            assets,
+            theme_directory: Some(theme_root.to_string_lossy().into_owned()),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            normalize_metadata: false,
+        };
+        write_bundle(request, progress)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+// Cursor themes live next to icon themes; a folder with nothing but cursors is a cursor theme
+fn icon_component(icon_dir: &Path) -> ThemeComponent {
+    let only_cursors = has_cursors(icon_dir) && fs::read_dir(icon_dir).is_ok_and(|entries| {
+        entries.flatten().all(|entry| !entry.path().is_dir() || entry.file_name() == "cursors")
+    });
+    if only_cursors { ThemeComponent::Cursors } else { ThemeComponent::Icons }
+}
+
+// Header owned by root with no user names, so the archive does not leak who exported it
+fn entry_header(entry_type: EntryType, mode: u32, mtime: u64, size: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_mode(mode & 0o7777);
+    header.set_mtime(mtime);
+    header.set_size(size);
+    header.set_uid(0);
+    header.set_gid(0);
+    header
+}
+
+struct CountingWriter<W> { // Counts the bytes passing through to the archive
+    inner: W,
+    count: u64,
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use crate::reskin_format::{BundleWriter, Codec, EntryMetadata};
+    use crate::types::ThemeManifest;
+
+    #[test]
+    fn exports_modes_symlinks_and_the_manifest() {
+        let dir = std::env::temp_dir().join(format!("reskin-export-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let manifest = ThemeManifest { name: "Nordic".into(), author: "Tester".into(), version: "1.0.0".into(), license: "MIT".into(), ..Default::default() };
+        let mut writer = BundleWriter::new(Vec::new(), &manifest).unwrap();
+        let meta = |kind, mode| EntryMetadata { kind, mode, mtime: 1_700_000_000 };
+        let long_name = format!("gtk-3.0/{}.css", "x".repeat(120)); // Needs a GNU long-name entry
+        let css = vec![b'a'; 1000];
+        writer.add_asset("gtk-3.0", meta(EntryKind::Directory, 0o750), 0, io::empty(), Codec::Store).unwrap();
+        writer.add_asset(&long_name, meta(EntryKind::File, 0o644), css.len() as u64, &css[..], Codec::Zstd).unwrap();
+        writer.add_asset("gtk-3.0/install.sh", meta(EntryKind::File, 0o755), 2, &b"#!"[..], Codec::Store).unwrap();
+        writer.add_asset("gtk-4.0", meta(EntryKind::Symlink, 0o777), 7, &b"gtk-3.0"[..], Codec::Store).unwrap();
+        let bundle_path = dir.join("Nordic.reskin");
+        fs::write(&bundle_path, writer.finish().unwrap()).unwrap();
+
+        let archive_path = dir.join("Nordic.tar.gz");
+        write_tar_gz(&bundle_path, &archive_path, &mut Progress::none()).unwrap();
+
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(File::open(&archive_path).unwrap()));
+        let mut entries = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().into_owned();
+            let (mode, mtime) = (entry.header().mode().unwrap(), entry.header().mtime().unwrap());
+            let link = entry.link_name().unwrap().map(|l| l.to_string_lossy().into_owned());
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data).unwrap();
+            entries.push((path, mode, mtime, link, data));
+        }
+
+        let paths: Vec<&str> = entries.iter().map(|e| e.0.as_str()).collect();
+        assert_eq!(paths, ["Nordic/", "Nordic/reskin.json", "Nordic/gtk-3.0/", &format!("Nordic/{}", long_name), "Nordic/gtk-3.0/install.sh", "Nordic/gtk-4.0"]);
+        assert_eq!(serde_json::from_slice::<ThemeManifest>(&entries[1].4).unwrap().name, "Nordic");
+        assert_eq!((entries[2].1, entries[2].2), (0o750, 1_700_000_000));
+        assert_eq!(entries[3].4, css);
+        assert_eq!((entries[4].1, &entries[4].4[..]), (0o755, &b"#!"[..]));
+        assert_eq!(entries[5].3.as_deref(), Some("gtk-3.0"));
+
+        // The exported archive can be imported again
+        let unpacked = dir.join("unpacked");
+        crate::import::unpack_archive(&archive_path, &unpacked, &crate::extract::ExtractLimits::default(), &mut Progress::none()).unwrap();
+        assert!(unpacked.join("Nordic/gtk-4.0/install.sh").is_file());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn exports_installed_themes() {
+        let home = std::env::temp_dir().join(format!("reskin-export-installed-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        let name = format!("Nordic-{}", std::process::id()); // Must not exist in /usr/share
+        for dir in [format!("Themes/{}/gtk-3.0", name), format!(".local/share/icons/{}/48x48/apps", name), format!(".icons/Bibata-{}/cursors", name), format!(".icons/{}/cursors", name)] {
+            fs::create_dir_all(home.join(dir)).unwrap();
+        }
+        fs::write(home.join(format!("Themes/{}/gtk-3.0/gtk.css", name)), "").unwrap();
+        fs::write(home.join(format!("Themes/{}/index.theme", name)), "[Desktop Entry]\nName=Nordic\nComment=Dark and frosty\n").unwrap();
+        fs::write(home.join(format!(".local/share/icons/{}/index.theme", name)), "[Icon Theme]\nName=Nordic\n").unwrap();
+        fs::write(home.join(format!(".local/share/icons/{}/48x48/apps/term.svg", name)), "<svg/>").unwrap();
+        fs::write(home.join(format!(".icons/Bibata-{}/cursors/left_ptr", name)), "Xcur").unwrap();
+        fs::write(home.join(format!(".icons/{}/cursors/left_ptr", name)), "Xcur").unwrap();
+        let home_dir = home.to_string_lossy();
+        // The GTK theme is in a custom install root, which is searched before the standard folders
+        let locations = crate::locations::InstallLocations { themes: Some(home.join("Themes").to_string_lossy().into_owned()), ..Default::default() };
+        let roots = InstallRoots::resolve(&home_dir, &locations, None).unwrap();
+
+        // GTK and icons become a pack, each part in its own folder
+        let output = home.join("out.reskin");
+        export_installed(&name, &home_dir, &roots, &output, &mut Progress::none()).unwrap();
+        let mut reader = BundleReader::new(File::open(&output).unwrap()).unwrap();
+        let manifest = reader.manifest().clone();
+        assert_eq!(manifest.description, "Dark and frosty");
+        let pack = manifest.pack.unwrap();
+        assert_eq!(pack.themes.iter().map(|t| (t.path.as_str(), t.component)).collect::<Vec<_>>(), [("gtk", ThemeComponent::Gtk), ("icons", ThemeComponent::Icons)]);
+        let mut paths = Vec::new();
+        while let Some(entry) = reader.next_entry_header().unwrap() {
+            reader.copy_entry_data(&entry, &mut io::sink()).unwrap();
+            paths.push((entry.path, entry.kind));
+        }
+        assert!(paths.contains(&("gtk".to_string(), EntryKind::Directory))); // Staged as a link, bundled as a folder
+        assert!(paths.contains(&("gtk/gtk-3.0/gtk.css".to_string(), EntryKind::File)));
+        assert!(paths.contains(&("icons/48x48/apps/term.svg".to_string(), EntryKind::File)));
+        assert!(paths.contains(&("icons/cursors/left_ptr".to_string(), EntryKind::File))); // Merged in from ~/.icons
+        assert!(manifest.components.contains(&ThemeComponent::Cursors));
+
+        // A lone cursor theme is bundled straight from its folder
+        let cursors = format!("Bibata-{}", name);
+        export_installed(&cursors, &home_dir, &roots, &output, &mut Progress::none()).unwrap();
+        let reader = BundleReader::new(File::open(&output).unwrap()).unwrap();
+        assert_eq!(reader.manifest().components, [ThemeComponent::Cursors]);
+        assert!(export_installed("Missing-theme-name", &home_dir, &roots, &output, &mut Progress::none()).is_err());
+        let _ = fs::remove_dir_all(&home);
+    }
+}
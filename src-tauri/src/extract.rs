@@ -1,143 +1,495 @@
+use std::fmt;
 use std::fs;
 use std::fs::File;
-use std::io::Read;
-use std::io::Seek;
-use std::path::Path;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::os::unix::fs::{symlink, PermissionsExt};
+use std::path::{Path, PathBuf};
+use filetime::FileTime;
 use crate::types::ThemeManifest;
+use crate::reskin_format::{BundleReader, BundleEntry, EntryKind, MAX_PATH_LEN};
+use crate::progress::{Progress, ProgressReader};
+use crate::locations::{home_dir, private_dir};
 
 #[tauri::command]
 pub fn extract_theme_info(file_data: Vec<u8>) -> Result<ThemeManifest, String> {
     // Read the RSKN header and extract manifest
-    if file_data.len() < 12 {
-        return Err("Invalid .reskin file: too small".to_string());
-    }
+    let reader = BundleReader::new(&file_data[..])?;
+    Ok(reader.manifest().clone()) // Return success
+}
 
-    // Check RSKN magic header
-    if !file_data.starts_with(b"RSKN") {
-        return Err("Invalid .reskin file: missing RSKN header".to_string());
+#[tauri::command]
+pub fn extract_theme_info_from_file(file_path: String) -> Result<ThemeManifest, String> {
+    // Only the header is needed, so there is no need to read the whole file
+    let file = File::open(&file_path)
+        .map_err(|e| format!("Failed to read file '{}': {}", file_path, e))?; // Throw error on failure
+    let reader = BundleReader::new(BufReader::new(file))?;
+    Ok(reader.manifest().clone())
+}
+
+#[derive(Debug)]
+pub enum ExtractError { // Reasons an extraction is refused, naming the offending entry
+    UnsafeName { name: String }, // Theme name cannot be used as a directory name
+    UnsafePath { entry: String, reason: &'static str }, // Entry path would escape the output directory
+    SymlinkInPath { entry: String, link: PathBuf }, // Entry would be written through an existing symlink
+    TooManyFiles { entry: String, limit: usize }, // Bundle holds more entries than allowed
+    TooLarge { entry: String, limit: u64 }, // Unpacking the entry would exceed the size budget
+    Bundle(String), // Bundle is malformed or corrupted
+    Io { entry: String, message: String }, // Writing the entry failed
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractError::UnsafeName { name } => write!(f, "Refusing to extract: theme name '{}' is not a safe directory name", name),
+            ExtractError::UnsafePath { entry, reason } => write!(f, "Refusing to extract '{}': {}", entry, reason),
+            ExtractError::SymlinkInPath { entry, link } => write!(f, "Refusing to extract '{}': {} is a symlink", entry, link.display()),
+            ExtractError::TooManyFiles { entry, limit } => write!(f, "Refusing to extract '{}': bundle has more than {} files", entry, limit),
+            ExtractError::TooLarge { entry, limit } => write!(f, "Refusing to extract '{}': bundle unpacks to more than {} bytes", entry, limit),
+            ExtractError::Bundle(message) => write!(f, "{}", message),
+            ExtractError::Io { entry, message } => write!(f, "Failed to write asset {}: {}", entry, message),
+        }
     }
+}
 
-    // Read manifest size (8 bytes after magic, as usize)
-    let manifest_size = u64::from_le_bytes([
-        file_data[4], file_data[5], file_data[6], file_data[7], file_data[8], file_data[9], file_data[10], file_data[11]
-    ]) as usize;
+pub struct ExtractLimits { // Caps that protect against bundles that unpack to absurd sizes
+    pub max_files: usize, // Maximum number of entries
+    pub max_total_size: u64, // Maximum number of bytes written, summed over all entries
+}
 
-    // Ensure file has enough data for the manifest
-    if file_data.len() < 12 + manifest_size {
-        return Err("Invalid .reskin file: manifest size mismatch".to_string()); // Return error if manifest size is incorrect
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_files: 200_000, // Large icon themes ship tens of thousands of files
+            max_total_size: 4 * 1024 * 1024 * 1024, // 4 GiB
+        }
     }
+}
 
-    // Extract manifest JSON
-    let manifest_bytes = &file_data[12..12 + manifest_size];
-    let manifest_str = String::from_utf8_lossy(manifest_bytes);
+// Check that a name from the manifest can be used as a single directory name
+pub fn validate_theme_name(name: &str) -> Result<(), ExtractError> {
+    let safe = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains('/')
+        && !name.contains('\\')
+        && !name.contains('\0');
+    if safe {
+        Ok(())
+    } else {
+        Err(ExtractError::UnsafeName { name: name.to_string() })
+    }
+}
+
+// Turn an entry path into a relative path that stays inside the output directory
+pub fn validate_entry_path(entry: &str) -> Result<PathBuf, ExtractError> {
+    let unsafe_path = |reason| Err(ExtractError::UnsafePath { entry: entry.to_string(), reason });
+
+    if entry.starts_with('/') {
+        return unsafe_path("absolute paths are not allowed");
+    }
+    if entry.contains('\\') || entry.contains('\0') {
+        return unsafe_path("path contains a backslash or NUL byte");
+    }
 
-    // Parse manifest JSON
-    match serde_json::from_str::<ThemeManifest>(&manifest_str) {
-        Ok(manifest) => Ok(manifest), // Return success
-        Err(e) => Err(format!("Failed to parse manifest: {}", e)) // Throw error
+    let mut relative = PathBuf::new();
+    for component in entry.split('/') {
+        match component {
+            "" | "." => return unsafe_path("path contains an empty or '.' component"),
+            ".." => return unsafe_path("path contains '..'"),
+            _ => relative.push(component),
+        }
     }
+    Ok(relative)
 }
 
-#[tauri::command]
-pub fn extract_theme_info_from_file(file_path: String) -> Result<ThemeManifest, String> {
-    // Read entire file into memory
-    match fs::read(&file_path) {
-        Ok(file_data) => extract_theme_info(file_data), // Extract theme info from file data
-        Err(e) => Err(format!("Failed to read file '{}': {}", file_path, e)) // Throw error on failure
+// Check that a symlink stored at `entry` points somewhere inside the theme. On the way to its last
+// component the target may only pass through real directories, as told by `is_plain_dir` for paths
+// relative to the theme root: a link on the way can lead anywhere once the kernel follows the chain,
+// even if every link in it looks harmless on its own.
+pub fn symlink_stays_inside(entry: &str, target: &str, is_plain_dir: impl Fn(&Path) -> bool) -> bool {
+    if target.is_empty() || target.starts_with('/') || target.contains('\0') {
+        return false;
+    }
+
+    let mut current = Path::new(entry).parent().map(Path::to_path_buf).unwrap_or_default(); // Directory holding the link
+    let components: Vec<&str> = target.split('/').filter(|c| !c.is_empty() && *c != ".").collect();
+    for (i, component) in components.iter().enumerate() {
+        if *component == ".." {
+            if !current.pop() {
+                return false; // Above the theme root
+            }
+        } else {
+            current.push(component);
+            if i + 1 < components.len() && !is_plain_dir(&current) {
+                return false;
+            }
+        }
     }
+    true
 }
 
-#[tauri::command]
-pub fn extract_theme(bundle_path: String) -> Result<String, String> {
-    // Open the .reskin bundle file
-    let mut file = File::open(&bundle_path)
-        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+// Whether `path` is a directory and not a symlink to one
+pub fn is_plain_dir(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|meta| meta.is_dir())
+}
 
-    // Read first 4 bytes for RSKN magic
-    let mut magic = [0u8; 4];
-    file.read_exact(&mut magic)
-        .map_err(|e| format!("Failed to read magic: {}", e))?;
-    if &magic[..] != b"RSKN" { // Compare only first 4 bytes
-        return Err("Invalid bundle format".to_string()); // Error if magic mismatch
+// Restore the recorded permission bits and mtime of an extracted entry
+pub fn restore_metadata(path: &Path, kind: EntryKind, mode: Option<u32>, mtime: Option<i64>) -> std::io::Result<()> {
+    if let Some(mode) = mode.filter(|_| kind != EntryKind::Symlink) { // Linux ignores the mode of a symlink
+        let mode = match kind {
+            EntryKind::Directory => mode & 0o777 | 0o700, // Keep directories writable so themes can be updated and removed
+            _ => mode & 0o777, // Never recreate setuid, setgid or sticky bits
+        };
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+    if let Some(mtime) = mtime {
+        let time = FileTime::from_unix_time(mtime, 0);
+        filetime::set_symlink_file_times(path, time, time)?;
     }
+    Ok(())
+}
 
-    let mut len_bytes = [0u8; 8]; // File length as bytes
-    file.read_exact(&mut len_bytes) // Read length as bytes from the file
-        .map_err(|e| format!("Failed to reach manifest length: {}", e))?; // Throw error on failure
-    let manifest_len = u64::from_le_bytes(len_bytes) as usize;
+// Make sure no existing part of the destination is a symlink, creating missing parent directories
+pub fn prepare_destination(output_dir: &Path, relative: &Path, entry: &str) -> Result<PathBuf, ExtractError> {
+    let io_error = |e: std::io::Error| ExtractError::Io { entry: entry.to_string(), message: e.to_string() };
 
-    let temp_dir = format!("/tmp/reskin_extract_{}", std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH).unwrap().as_secs());
-    fs::create_dir_all(&temp_dir)
-        .map_err(|e| format!("Failed to create temp dir: {}", e))?;
+    let mut current = output_dir.to_path_buf();
+    let components: Vec<_> = relative.components().collect();
+    for (i, component) in components.iter().enumerate() {
+        current.push(component);
+        let is_last = i + 1 == components.len();
 
-    // Read manifest JSON
-    let mut manifest_json = vec![0u8; manifest_len];
-    file.read_exact(&mut manifest_json) // Read manifest data from file
-        .map_err(|e| format!("Failed to read manifest data: {}", e))?; // Throw error on failure
+        match fs::symlink_metadata(&current) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(ExtractError::SymlinkInPath { entry: entry.to_string(), link: current });
+            }
+            Ok(meta) if !is_last && !meta.is_dir() => {
+                return Err(ExtractError::UnsafePath { entry: entry.to_string(), reason: "a parent of this path is a file" });
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if !is_last {
+                    fs::create_dir(&current).map_err(io_error)?;
+                }
+            }
+            Err(e) => return Err(io_error(e)),
+        }
+    }
+    Ok(current)
+}
 
-    // Parse manifest
-    let manifest: ThemeManifest = serde_json::from_slice(&manifest_json)
-        .map_err(|e| format!("Failed to parse manifest: {}", e))?;
+// Unpack every asset of a bundle into `output_dir`, enforcing path safety and the given limits
+pub fn extract_bundle_to<R: Read>(reader: &mut BundleReader<R>, output_dir: &Path, limits: &ExtractLimits) -> Result<(), ExtractError> {
+    let io_error = |entry: &str, e: std::io::Error| ExtractError::Io { entry: entry.to_string(), message: e.to_string() };
 
-    // Prepare output directory
-    let output_dir = format!("/tmp/{}", manifest.name); // Extraction output directory
-    fs::create_dir_all(&output_dir)
-        .map_err(|e| format!("Failed to create output dir: {}", e))?;
+    // Never follow a symlink planted where the output directory should be
+    if fs::symlink_metadata(output_dir).is_ok_and(|meta| meta.file_type().is_symlink()) {
+        return Err(ExtractError::SymlinkInPath { entry: String::new(), link: output_dir.to_path_buf() });
+    }
+    fs::create_dir_all(output_dir).map_err(|e| io_error("", e))?;
 
     // Write manifest to output directory
-    fs::write(Path::new(&output_dir).join("reskin.json"), &manifest_json)
-        .map_err(|e| format!("Failed to write reskin.json: {}", e))?;
-
-    // Extract assets safely
-    loop {
-        let mut filename_len_bytes = [0u8; 4];
-        if file.read_exact(&mut filename_len_bytes).is_err() {
-            break; // EOF reached, stop loop
-        }
-        let filename_len = u32::from_le_bytes(filename_len_bytes) as usize;
+    fs::write(output_dir.join("reskin.json"), reader.manifest_json())
+        .map_err(|e| io_error("reskin.json", e))?;
 
-        // Sanity check filename length
-        if filename_len == 0 || filename_len > 4096 {
-            // Invalid filename length, stop extraction
-            break;
+    let mut file_count = 0;
+    let mut total_size = 0u64;
+    let mut directories = Vec::new(); // Directory metadata is restored last, since writing into them changes their mtime
+    let mut links = Vec::new(); // Symlinks are made once everything else is in place, so their targets can be checked against the real tree
+    while let Some(header) = reader.next_entry_header().map_err(ExtractError::Bundle)? {
+        file_count += 1;
+        if file_count > limits.max_files {
+            return Err(ExtractError::TooManyFiles { entry: header.path, limit: limits.max_files });
         }
 
-        // Read filename bytes
-        let mut filename_bytes = vec![0u8; filename_len];
-        if file.read_exact(&mut filename_bytes).is_err() {
-            break; // Failed to read, stop safely
+        // Check the declared size before decompressing anything
+        total_size = total_size.saturating_add(header.size);
+        if total_size > limits.max_total_size {
+            return Err(ExtractError::TooLarge { entry: header.path, limit: limits.max_total_size });
         }
-        let filename = String::from_utf8_lossy(&filename_bytes).to_string();
 
-        // Read asset length
-        let mut asset_len_bytes = [0u8; 4];
-        if file.read_exact(&mut asset_len_bytes).is_err() {
-            break;
+        let relative = validate_entry_path(&header.path)?;
+        let asset_path = prepare_destination(output_dir, &relative, &header.path)?;
+
+        match header.kind {
+            EntryKind::File => {
+                // Stream the asset straight to disk so large files never sit in memory
+                let mut asset_file = BufWriter::new(File::create(&asset_path).map_err(|e| io_error(&header.path, e))?);
+                reader.copy_entry_data(&header, &mut asset_file).map_err(ExtractError::Bundle)?;
+                asset_file.flush().map_err(|e| io_error(&header.path, e))?;
+                restore_metadata(&asset_path, header.kind, header.mode, header.mtime).map_err(|e| io_error(&header.path, e))?;
+            }
+            EntryKind::Directory => {
+                reader.copy_entry_data(&header, &mut std::io::sink()).map_err(ExtractError::Bundle)?;
+                if !asset_path.is_dir() {
+                    fs::create_dir(&asset_path).map_err(|e| io_error(&header.path, e))?;
+                }
+                directories.push((asset_path, header));
+            }
+            EntryKind::Symlink => {
+                if header.size > MAX_PATH_LEN as u64 {
+                    return Err(ExtractError::UnsafePath { entry: header.path, reason: "symlink target is too long" });
+                }
+                let mut target = Vec::new();
+                reader.copy_entry_data(&header, &mut target).map_err(ExtractError::Bundle)?;
+                let target = String::from_utf8(target)
+                    .map_err(|_| ExtractError::UnsafePath { entry: header.path.clone(), reason: "symlink target is not valid UTF-8" })?;
+                links.push((asset_path, header, target));
+            }
         }
-        let asset_len = u32::from_le_bytes(asset_len_bytes) as usize;
+    }
 
-        // Skip assets that are too large
-        if asset_len > 500_000_000 {
-            let _ = file.seek(std::io::SeekFrom::Current(asset_len as i64));
-            continue;
+    for (asset_path, header, target) in links {
+        if !symlink_stays_inside(&header.path, &target, |path| is_plain_dir(&output_dir.join(path))) {
+            return Err(ExtractError::UnsafePath { entry: header.path, reason: "symlink points outside the theme" });
         }
+        symlink(&target, &asset_path).map_err(|e| io_error(&header.path, e))?;
+        restore_metadata(&asset_path, header.kind, header.mode, header.mtime).map_err(|e| io_error(&header.path, e))?;
+    }
+
+    for (path, header) in directories.iter().rev() {
+        restore_metadata(path, header.kind, header.mode, header.mtime).map_err(|e| io_error(&header.path, e))?;
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn extract_theme(bundle_path: String) -> Result<String, String> {
+    // Open the .reskin bundle file
+    let file = File::open(&bundle_path)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    extract_theme_with_progress(&file, &mut Progress::none())
+}
 
-        // Read asset data
-        let mut asset_data = vec![0u8; asset_len];
-        if file.read_exact(&mut asset_data).is_err() {
-            break;
+// Extract an open bundle into <theme name> inside a new private folder in the cache, reporting how much
+// of the bundle has been read. The caller owns that folder and removes it once done with the theme.
+pub fn extract_theme_with_progress(bundle: &File, progress: &mut Progress) -> Result<String, String> {
+    let mut file = bundle;
+    let bundle_len = file.metadata()
+        .map_err(|e| format!("Failed to open bundle: {}", e))?
+        .len();
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+    progress.start("extracting", bundle_len);
+
+    // Read header and manifest
+    let mut reader = BundleReader::new(BufReader::new(ProgressReader::new(file, progress)))?;
+
+    // The theme name becomes a directory name, so it must not contain path separators
+    validate_theme_name(&reader.manifest().name).map_err(|e| e.to_string())?;
+    // A folder no one else can write to, so no stale files or planted links end up in the theme
+    let extract_dir = private_dir(&home_dir()?, "extract")?;
+    let output_dir = extract_dir.join(&reader.manifest().name);
+
+    if let Err(e) = extract_bundle_to(&mut reader, &output_dir, &ExtractLimits::default()) {
+        let _ = fs::remove_dir_all(&extract_dir);
+        return Err(e.to_string());
+    }
+    progress.finish();
+
+    Ok(output_dir.to_string_lossy().into_owned()) // Extraction success
+}
+
+#[tauri::command]
+pub fn list_bundle_contents(bundle_path: String) -> Result<Vec<BundleEntry>, String> {
+    // List asset paths and sizes from the table of contents without unpacking anything
+    let file = File::open(&bundle_path)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut reader = BundleReader::new(BufReader::new(file))?;
+    reader.entries()
+}
+
+#[tauri::command]
+pub fn read_bundle_asset(bundle_path: String, asset_path: String) -> Result<Vec<u8>, String> {
+    // Seek straight to a single asset, e.g. to preview an image
+    let file = File::open(&bundle_path)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut reader = BundleReader::new(BufReader::new(file))?;
+    reader.read_asset(&asset_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reskin_format::{BundleWriter, Codec, EntryMetadata};
+    use crate::test_support::{bundle_bytes, scratch_dir, test_manifest};
+
+    fn malicious_bundle(name: &str, assets: &[(&str, &[u8], Codec)]) -> Vec<u8> {
+        bundle_bytes(&test_manifest(name), assets)
+    }
+
+    fn extract(bytes: &[u8], output_dir: &Path, limits: &ExtractLimits) -> Result<(), ExtractError> {
+        let mut reader = BundleReader::new(bytes).unwrap();
+        extract_bundle_to(&mut reader, output_dir, limits)
+    }
+
+    #[test]
+    fn extracts_well_formed_bundles() {
+        let dir = scratch_dir("extract-ok");
+        let bytes = malicious_bundle("Ok", &[("gtk-3.0/gtk.css", b"* {}", Codec::Zstd), ("index.theme", b"[Icon Theme]", Codec::Store)]);
+        extract(&bytes, &dir.join("out"), &ExtractLimits::default()).unwrap();
+        assert_eq!(fs::read(dir.join("out/gtk-3.0/gtk.css")).unwrap(), b"* {}");
+        assert!(dir.join("out/reskin.json").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_parent_directory_traversal() {
+        let dir = scratch_dir("extract-dotdot");
+        let bytes = malicious_bundle("Evil", &[("gtk-3.0/../../escaped", b"pwned", Codec::Store)]);
+        let err = extract(&bytes, &dir.join("out"), &ExtractLimits::default()).unwrap_err();
+        assert!(matches!(&err, ExtractError::UnsafePath { entry, .. } if entry == "gtk-3.0/../../escaped"), "{}", err);
+        assert!(!dir.join("escaped").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_absolute_and_odd_paths() {
+        let dir = scratch_dir("extract-absolute");
+        for path in ["/etc/passwd", "a//b", "./a", "a\\..\\b"] {
+            let bytes = malicious_bundle("Evil", &[(path, b"pwned", Codec::Store)]);
+            let err = extract(&bytes, &dir.join("out"), &ExtractLimits::default()).unwrap_err();
+            assert!(matches!(&err, ExtractError::UnsafePath { entry, .. } if entry == path), "{}: {}", path, err);
         }
+        let _ = fs::remove_dir_all(&dir);
+    }
 
-        // Write asset to disk
-        let asset_path = Path::new(&output_dir).join(&filename);
-        if let Some(parent) = asset_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directory for {}: {}", filename, e))?;
+    #[cfg(unix)]
+    #[test]
+    fn refuses_to_write_through_symlinks() {
+        let dir = scratch_dir("extract-symlink");
+        let outside = dir.join("outside");
+        let out = dir.join("out");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&out).unwrap();
+        std::os::unix::fs::symlink(&outside, out.join("icons")).unwrap();
+        std::os::unix::fs::symlink(outside.join("target"), out.join("index.theme")).unwrap();
+
+        let bytes = malicious_bundle("Evil", &[("icons/evil.svg", b"pwned", Codec::Store)]);
+        let err = extract(&bytes, &out, &ExtractLimits::default()).unwrap_err();
+        assert!(matches!(&err, ExtractError::SymlinkInPath { entry, .. } if entry == "icons/evil.svg"), "{}", err);
+
+        let bytes = malicious_bundle("Evil", &[("index.theme", b"pwned", Codec::Store)]);
+        let err = extract(&bytes, &out, &ExtractLimits::default()).unwrap_err();
+        assert!(matches!(&err, ExtractError::SymlinkInPath { entry, .. } if entry == "index.theme"), "{}", err);
+
+        assert_eq!(fs::read_dir(&outside).unwrap().count(), 0);
+
+        // A symlinked output directory is refused outright
+        let linked_out = dir.join("linked-out");
+        std::os::unix::fs::symlink(&outside, &linked_out).unwrap();
+        let bytes = malicious_bundle("Evil", &[("a.css", b"pwned", Codec::Store)]);
+        assert!(matches!(extract(&bytes, &linked_out, &ExtractLimits::default()), Err(ExtractError::SymlinkInPath { .. })));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn caps_file_count() {
+        let dir = scratch_dir("extract-count");
+        let bytes = malicious_bundle("Many", &[("a", b"1", Codec::Store), ("b", b"2", Codec::Store), ("c", b"3", Codec::Store)]);
+        let limits = ExtractLimits { max_files: 2, ..ExtractLimits::default() };
+        let err = extract(&bytes, &dir.join("out"), &limits).unwrap_err();
+        assert!(matches!(&err, ExtractError::TooManyFiles { entry, limit: 2 } if entry == "c"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn caps_unpacked_size_before_decompressing() {
+        // 8 MiB of zeros compresses to a few hundred bytes
+        let dir = scratch_dir("extract-bomb");
+        let zeros = vec![0u8; 8 * 1024 * 1024];
+        let bytes = malicious_bundle("Bomb", &[("small", b"ok", Codec::Store), ("bomb.svg", &zeros, Codec::Zstd)]);
+        assert!(bytes.len() < 64 * 1024);
+
+        let limits = ExtractLimits { max_total_size: 1024 * 1024, ..ExtractLimits::default() };
+        let err = extract(&bytes, &dir.join("out"), &limits).unwrap_err();
+        assert!(matches!(&err, ExtractError::TooLarge { entry, .. } if entry == "bomb.svg"), "{}", err);
+        assert!(!dir.join("out/bomb.svg").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_unsafe_theme_names() {
+        for name in ["", ".", "..", "../../home", "a/b"] {
+            assert!(matches!(validate_theme_name(name), Err(ExtractError::UnsafeName { .. })), "{}", name);
         }
-        fs::write(&asset_path, &asset_data)
-            .map_err(|e| format!("Failed to write asset {}: {}", filename, e))?;
+        assert!(validate_theme_name("Adwaita-dark").is_ok());
+
+        // extract_theme refuses before creating anything
+        let dir = scratch_dir("extract-name");
+        let bundle = dir.join("evil.reskin");
+        fs::write(&bundle, malicious_bundle("../../evil", &[("a.css", b"x", Codec::Store)])).unwrap();
+        let err = extract_theme(bundle.to_string_lossy().into_owned()).unwrap_err();
+        assert!(err.contains("not a safe directory name"), "{}", err);
+        let _ = fs::remove_dir_all(&dir);
     }
 
-    Ok(format!("{}", output_dir)) // Extraction success
-}
\ No newline at end of file
+    #[test]
+    fn recreates_symlinks_modes_and_mtimes() {
+        let dir = scratch_dir("extract-meta");
+        let manifest = test_manifest("Meta");
+        let mut writer = BundleWriter::new(Vec::new(), &manifest).unwrap();
+        let dir_meta = EntryMetadata { kind: EntryKind::Directory, mode: 0o755, mtime: 1_600_000_000 };
+        let script_meta = EntryMetadata { kind: EntryKind::File, mode: 0o4755, mtime: 1_500_000_000 };
+        let link_meta = EntryMetadata { kind: EntryKind::Symlink, mode: 0o777, mtime: 1_400_000_000 };
+        writer.add_asset("apps", dir_meta, 0, std::io::empty(), Codec::Store).unwrap();
+        writer.add_asset("apps/firefox.svg", EntryMetadata::default(), 6, &b"<svg/>"[..], Codec::Store).unwrap();
+        writer.add_asset("apps/web-browser.svg", link_meta, 11, &b"firefox.svg"[..], Codec::Store).unwrap();
+        writer.add_asset("empty", dir_meta, 0, std::io::empty(), Codec::Store).unwrap();
+        writer.add_asset("helper.sh", script_meta, 2, &b"#!"[..], Codec::Store).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let out = dir.join("out");
+        extract(&bytes, &out, &ExtractLimits::default()).unwrap();
+
+        let link = out.join("apps/web-browser.svg");
+        assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from("firefox.svg"));
+        assert_eq!(fs::read(&link).unwrap(), b"<svg/>");
+        assert_eq!(FileTime::from_last_modification_time(&fs::symlink_metadata(&link).unwrap()).unix_seconds(), 1_400_000_000);
+
+        let script = fs::metadata(out.join("helper.sh")).unwrap();
+        assert_eq!(script.permissions().mode() & 0o7777, 0o755); // setuid is dropped
+        assert_eq!(FileTime::from_last_modification_time(&script).unix_seconds(), 1_500_000_000);
+
+        let apps = fs::metadata(out.join("apps")).unwrap();
+        assert_eq!(FileTime::from_last_modification_time(&apps).unix_seconds(), 1_600_000_000);
+        assert!(out.join("empty").is_dir());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_symlinks_that_escape() {
+        let anywhere = |_: &Path| true;
+        assert!(symlink_stays_inside("apps/a.svg", "../mimetypes/b.svg", anywhere));
+        assert!(!symlink_stays_inside("apps/a.svg", "../../etc/passwd", anywhere));
+        assert!(!symlink_stays_inside("a.svg", "/etc/passwd", anywhere));
+        assert!(!symlink_stays_inside("d/l2", "../l1/..", |path| path != Path::new("l1")));
+
+        let dir = scratch_dir("extract-escape");
+        let manifest = test_manifest("Escape");
+        let mut writer = BundleWriter::new(Vec::new(), &manifest).unwrap();
+        let link_meta = EntryMetadata { kind: EntryKind::Symlink, mode: 0o777, mtime: 0 };
+        writer.add_asset("gtk-3.0", link_meta, 11, &b"/etc/passwd"[..], Codec::Store).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let err = extract(&bytes, &dir.join("out"), &ExtractLimits::default()).unwrap_err();
+        assert!(matches!(&err, ExtractError::UnsafePath { entry, .. } if entry == "gtk-3.0"), "{}", err);
+        assert!(fs::symlink_metadata(dir.join("out/gtk-3.0")).is_err());
+
+        // Links that each stay inside can still chain to outside: d/l3 -> d/.. -> l1/.. -> two levels up
+        let dir_meta = EntryMetadata { kind: EntryKind::Directory, mode: 0o755, mtime: 0 };
+        let mut writer = BundleWriter::new(Vec::new(), &manifest).unwrap();
+        writer.add_asset("l1", link_meta, 1, &b"."[..], Codec::Store).unwrap();
+        writer.add_asset("d", dir_meta, 0, std::io::empty(), Codec::Store).unwrap();
+        writer.add_asset("d/l2", link_meta, 8, &b"../l1/.."[..], Codec::Store).unwrap();
+        writer.add_asset("d/l3", link_meta, 10, &b"../d/l2/.."[..], Codec::Store).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let err = extract(&bytes, &dir.join("chain"), &ExtractLimits::default()).unwrap_err();
+        assert!(matches!(&err, ExtractError::UnsafePath { entry, .. } if entry == "d/l2"), "{}", err);
+        assert!(fs::symlink_metadata(dir.join("chain/d/l2")).is_err());
+        assert!(fs::symlink_metadata(dir.join("chain/d/l3")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
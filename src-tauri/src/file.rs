@@ -45,7 +45,7 @@ pub fn select_file(title: String) -> Result<String, String> {
     // Run zenity to open file selection dialog allowing only .reskin files
     let output = Command::new("zenity")
         .arg("--file-selection")
-        .arg(&format!("--title={}", title))
+        .arg(format!("--title={}", title))
         .arg("--file-filter=Reskin Files (*.reskin) | *.reskin")
         .output();
 
@@ -68,6 +68,34 @@ pub fn select_file(title: String) -> Result<String, String> {
     }
 }
 
+#[tauri::command]
+pub fn select_archive(title: String) -> Result<String, String> {
+    // Run zenity to open file selection dialog allowing only theme archives
+    let output = Command::new("zenity")
+        .arg("--file-selection")
+        .arg(format!("--title={}", title))
+        .arg("--file-filter=Theme Archives (*.tar.gz, *.tar.xz, *.zip) | *.tar.gz *.tgz *.tar.xz *.txz *.tar *.zip")
+        .output();
+
+    match output {
+        Ok(result) => {
+            if result.status.success() {
+                let path = String::from_utf8_lossy(&result.stdout).trim().to_string();
+                if !path.is_empty() {
+                    Ok(path)
+                } else {
+                    Err("No file selected".to_string()) // Throw error when no file is selected
+                }
+            } else {
+                Err("Failed to open file dialog".to_string()) // Throw error when opening the file dialog failed
+            }
+        }
+        Err(_) => {
+            Err("No file dialog available".to_string())
+        }
+    }
+}
+
 #[tauri::command]
 fn _ensure_reskin_folder() -> Result<(), String> { // Ensure /tmp/reskin exists
 	let mut path = temp_dir(); // Define path as /tmp
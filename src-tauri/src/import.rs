@@ -0,0 +1,629 @@
+// Import necessary crates
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::fs::{symlink, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use flate2::read::MultiGzDecoder;
+use serde::Serialize;
+use tar::EntryType;
+use tauri::AppHandle;
+use xz2::read::XzDecoder;
+use crate::bundle::{collect_directory_assets, write_unsigned_bundle};
+use crate::extract::{is_plain_dir, prepare_destination, restore_metadata, symlink_stays_inside, validate_entry_path, validate_theme_name, ExtractError, ExtractLimits};
+use crate::install::{install_bundle, InstallOptions};
+use crate::locations::{cache_dir, home_dir, private_dir};
+use crate::lint::{detected_components, Diagnostic};
+use crate::progress::{emit_to, Progress, ProgressReader};
+use crate::reskin_format::{EntryKind, MAX_PATH_LEN};
+use crate::types::{BundleRequest, InstallScope, ThemeComponent, ThemeManifest, MANIFEST_VERSION};
+
+const MAX_SEARCH_DEPTH: usize = 4; // How many folders deep theme roots are looked for inside an archive
+const ARCHIVE_EXTENSIONS: &[&str] = &[".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar", ".zip"]; // Stripped to get a theme name from the file name
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArchiveFormat { // Archive types themes are usually published as
+    Tar,
+    TarGz,
+    TarXz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    // Recognise an archive by its first bytes, since downloaded files are often misnamed
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::TarGz)
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0]) {
+            Some(Self::TarXz)
+        } else if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+            Some(Self::Zip)
+        } else if header.get(257..262) == Some(&b"ustar"[..]) {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ImportedTheme { // One theme found in an imported archive
+    pub manifest: ThemeManifest, // Manifest guessed from the theme files
+    pub bundle_path: Option<String>, // .reskin written for the theme, when not installing
+    pub message: String, // Result of installing or bundling it
+    pub diagnostics: Vec<Diagnostic>, // Problems the structure linter found while installing
+}
+
+#[tauri::command]
+pub fn import_theme_archive(app: AppHandle, archive_path: String, auto_apply: bool, require_signed: Option<bool>, scope: Option<InstallScope>) -> Result<Vec<ImportedTheme>, String> {
+    // Install every theme in the archive. Archives carry no signature, so a require_signed install refuses them.
+    let options = InstallOptions {
+        auto_apply,
+        require_signed: require_signed.unwrap_or(false),
+        scope,
+        ..Default::default()
+    };
+    import_archive(Path::new(&archive_path), None, &options, &mut emit_to(app, "import-progress"))
+}
+
+#[tauri::command]
+pub fn convert_theme_archive(app: AppHandle, archive_path: String, output_directory: Option<String>) -> Result<Vec<ImportedTheme>, String> {
+    // Write a .reskin for every theme in the archive, by default into ~/.cache/reskin/imports like exports
+    let output_directory = output_directory.map_or_else(|| home_dir().map(|home_dir| cache_dir(&home_dir).join("imports")), |dir| Ok(PathBuf::from(dir)))?;
+    import_archive(Path::new(&archive_path), Some(&output_directory), &InstallOptions::default(), &mut emit_to(app, "import-progress"))
+}
+
+// Unpack an archive, find the themes in it and bundle or install each of them. The bundles are never
+// signed, since the author's key must not vouch for someone else's files.
+pub fn import_archive(archive_path: &Path, output_directory: Option<&Path>, options: &InstallOptions, progress: &mut Progress) -> Result<Vec<ImportedTheme>, String> {
+    let work_dir = private_dir(&home_dir()?, "import")?;
+    let result = import_into(archive_path, &work_dir, output_directory, options, progress);
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn import_into(archive_path: &Path, work_dir: &Path, output_directory: Option<&Path>, options: &InstallOptions, progress: &mut Progress) -> Result<Vec<ImportedTheme>, String> {
+    let unpacked = work_dir.join("unpacked");
+    unpack_archive(archive_path, &unpacked, &ExtractLimits::default(), progress)?;
+
+    let roots = find_theme_roots(&unpacked);
+    if roots.is_empty() {
+        return Err("No GTK, icon, cursor, font or shell theme found in the archive".to_string());
+    }
+
+    if let Some(dir) = output_directory {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create output directory: {}", e))?;
+    }
+
+    let archive_name = archive_stem(archive_path);
+    let mut imported: Vec<ImportedTheme> = Vec::new();
+    for (index, root) in roots.iter().enumerate() {
+        // Themes at the top of the archive have no folder of their own to take a name from
+        let folder_name = if *root == unpacked { None } else { root.file_name().map(|n| n.to_string_lossy().into_owned()) };
+        let manifest = guess_manifest(root, folder_name.as_deref(), &archive_name);
+
+        let bundle_path = match output_directory {
+            Some(dir) => {
+                // GTK and icon themes often share a name, so keep their bundles apart
+                let taken = imported.iter().filter(|theme| theme.manifest.name == manifest.name).count();
+                let file_name = if taken == 0 { format!("{}.reskin", manifest.name) } else { format!("{}-{}.reskin", manifest.name, taken + 1) };
+                dir.join(file_name)
+            }
+            None => work_dir.join(format!("{}-{}.reskin", index, manifest.name)),
+        };
+
+        let request = BundleRequest {
+            manifest: manifest.clone(),
+            output_path: bundle_path.to_string_lossy().into_owned(),
+            assets: Vec::new(),
+            theme_directory: Some(root.to_string_lossy().into_owned()),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            normalize_metadata: false,
+        };
+        let message = write_unsigned_bundle(collect_directory_assets(request)?, progress)?;
+
+        if output_directory.is_some() {
+            imported.push(ImportedTheme { manifest, bundle_path: Some(bundle_path.to_string_lossy().into_owned()), message, diagnostics: Vec::new() });
+        } else {
+            // Only the first theme is applied, applying the rest would just replace it again
+            let report = install_bundle(&bundle_path.to_string_lossy(), &InstallOptions { auto_apply: options.auto_apply && index == 0, ..options.clone() }, progress)?;
+            imported.push(ImportedTheme { manifest, bundle_path: None, message: report.message, diagnostics: report.diagnostics });
+        }
+    }
+    Ok(imported)
+}
+
+// Unpack a tar.gz, tar.xz, tar or zip archive into `output_dir` with the same path checks and limits as bundles
+pub fn unpack_archive(archive_path: &Path, output_dir: &Path, limits: &ExtractLimits, progress: &mut Progress) -> Result<(), String> {
+    let mut file = File::open(archive_path)
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    let archive_len = file.metadata()
+        .map_err(|e| format!("Failed to open archive: {}", e))?
+        .len();
+    let mut header = [0u8; 512];
+    let header_len = read_up_to(&mut file, &mut header)
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+    let format = ArchiveFormat::detect(&header[..header_len])
+        .ok_or_else(|| format!("{} is not a tar.gz, tar.xz or zip archive", archive_path.display()))?;
+    drop(file);
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create import directory: {}", e))?;
+    let mut unpacker = Unpacker::new(output_dir, limits);
+
+    progress.start("unpacking", archive_len);
+    let file = File::open(archive_path)
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+    match format {
+        ArchiveFormat::Zip => unpack_zip(file, &mut unpacker, progress)?,
+        format => {
+            let reader = BufReader::new(ProgressReader::new(file, progress));
+            match format {
+                ArchiveFormat::TarGz => unpack_tar(MultiGzDecoder::new(reader), &mut unpacker)?,
+                ArchiveFormat::TarXz => unpack_tar(XzDecoder::new_multi_decoder(reader), &mut unpacker)?,
+                _ => unpack_tar(reader, &mut unpacker)?,
+            }
+        }
+    }
+    unpacker.finish().map_err(|e| e.to_string())?;
+    progress.finish();
+    Ok(())
+}
+
+fn read_up_to(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+fn unpack_tar<R: Read>(reader: R, unpacker: &mut Unpacker) -> Result<(), String> {
+    let read_error = |e: io::Error| format!("Failed to read archive: {}", e);
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(read_error)? {
+        let mut entry = entry.map_err(read_error)?;
+        let path = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let mode = entry.header().mode().ok();
+        let link_name = entry.link_name_bytes().map(|name| String::from_utf8_lossy(&name).into_owned());
+
+        let result = match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Continuous => {
+                let size = entry.size();
+                unpacker.file(&path, mode, size, &mut entry)
+            }
+            EntryType::Directory => unpacker.directory(&path, mode),
+            EntryType::Symlink => unpacker.symlink(&path, link_name.as_deref().unwrap_or("")),
+            EntryType::Link => unpacker.hard_link(&path, link_name.as_deref().unwrap_or("")),
+            _ => Ok(()), // Devices and FIFOs have no place in a theme
+        };
+        result.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn unpack_zip(file: File, unpacker: &mut Unpacker, progress: &mut Progress) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to read archive: {}", e))?;
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+        progress.advance(entry.compressed_size());
+        let path = entry.name().to_string(); // Checked by the unpacker rather than silently cleaned up
+        let mode = entry.unix_mode();
+
+        let result = if entry.is_dir() {
+            unpacker.directory(&path, mode)
+        } else if mode.is_some_and(|mode| mode & 0o170000 == 0o120000) {
+            // Zip stores a symlink as a file holding its target
+            let mut target = String::new();
+            (&mut entry).take(MAX_PATH_LEN as u64 + 1).read_to_string(&mut target)
+                .map_err(|e| format!("Failed to read archive: {}", e))?;
+            unpacker.symlink(&path, &target)
+        } else {
+            let size = entry.size();
+            unpacker.file(&path, mode, size, &mut entry)
+        };
+        result.map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+struct Unpacker<'a> { // Writes archive entries to disk with the checks extract_bundle_to makes for bundles
+    output_dir: &'a Path,
+    limits: &'a ExtractLimits,
+    file_count: usize,
+    total_size: u64,
+    directories: Vec<(PathBuf, String, Option<u32>)>, // Directory modes are restored last so they cannot block writing into them
+    links: Vec<(PathBuf, String, String)>, // Symlinks are made last, once their targets can be checked against the real tree
+}
+
+impl<'a> Unpacker<'a> {
+    fn new(output_dir: &'a Path, limits: &'a ExtractLimits) -> Self {
+        Self { output_dir, limits, file_count: 0, total_size: 0, directories: Vec::new(), links: Vec::new() }
+    }
+
+    // Check an entry against the limits and the path rules, returning where it goes.
+    // None for the entry of the archive root itself.
+    fn destination(&mut self, entry: &str, size: u64) -> Result<Option<PathBuf>, ExtractError> {
+        let relative_entry = entry.trim_start_matches("./").trim_end_matches('/');
+        if relative_entry.is_empty() || relative_entry == "." {
+            return Ok(None);
+        }
+
+        self.file_count += 1;
+        if self.file_count > self.limits.max_files {
+            return Err(ExtractError::TooManyFiles { entry: entry.to_string(), limit: self.limits.max_files });
+        }
+        self.total_size = self.total_size.saturating_add(size);
+        if self.total_size > self.limits.max_total_size {
+            return Err(ExtractError::TooLarge { entry: entry.to_string(), limit: self.limits.max_total_size });
+        }
+
+        let relative = validate_entry_path(relative_entry)?;
+        prepare_destination(self.output_dir, &relative, entry).map(Some)
+    }
+
+    fn file(&mut self, entry: &str, mode: Option<u32>, size: u64, data: &mut impl Read) -> Result<(), ExtractError> {
+        let io_error = |e: io::Error| ExtractError::Io { entry: entry.to_string(), message: e.to_string() };
+        let Some(path) = self.destination(entry, size)? else {
+            return Ok(());
+        };
+        if fs::symlink_metadata(&path).is_ok_and(|meta| meta.is_dir()) {
+            return Err(ExtractError::UnsafePath { entry: entry.to_string(), reason: "a directory with this name was already unpacked" });
+        }
+
+        // Never write more than the archive declared, whatever the decompressor produces
+        let mut file = BufWriter::new(File::create(&path).map_err(io_error)?);
+        io::copy(&mut data.take(size), &mut file).map_err(io_error)?;
+        file.flush().map_err(io_error)?;
+        restore_metadata(&path, EntryKind::File, mode, None).map_err(io_error)
+    }
+
+    fn directory(&mut self, entry: &str, mode: Option<u32>) -> Result<(), ExtractError> {
+        let Some(path) = self.destination(entry, 0)? else {
+            return Ok(());
+        };
+        if !path.is_dir() {
+            fs::create_dir(&path).map_err(|e| ExtractError::Io { entry: entry.to_string(), message: e.to_string() })?;
+        }
+        self.directories.push((path, entry.to_string(), mode));
+        Ok(())
+    }
+
+    fn symlink(&mut self, entry: &str, target: &str) -> Result<(), ExtractError> {
+        if target.len() > MAX_PATH_LEN {
+            return Err(ExtractError::UnsafePath { entry: entry.to_string(), reason: "symlink target is too long" });
+        }
+        let Some(path) = self.destination(entry, target.len() as u64)? else {
+            return Ok(());
+        };
+        if fs::symlink_metadata(&path).is_ok() {
+            return Err(ExtractError::UnsafePath { entry: entry.to_string(), reason: "the archive holds this path twice" });
+        }
+        self.links.push((path, entry.to_string(), target.to_string()));
+        Ok(())
+    }
+
+    // Tar hard links name an earlier entry of the archive; the file is copied since themes do not need to share inodes
+    fn hard_link(&mut self, entry: &str, target: &str) -> Result<(), ExtractError> {
+        let not_unpacked = || ExtractError::UnsafePath { entry: entry.to_string(), reason: "hard link does not point to a file unpacked earlier" };
+        let target_entry = target.trim_start_matches("./");
+        let relative = validate_entry_path(target_entry)?;
+
+        // Like prepare_destination, never go through a symlink on the way, and do not follow one at the end
+        let mut source = self.output_dir.to_path_buf();
+        for component in relative.components() {
+            source.push(component);
+            if fs::symlink_metadata(&source).is_ok_and(|meta| meta.file_type().is_symlink()) {
+                return Err(ExtractError::SymlinkInPath { entry: entry.to_string(), link: source });
+            }
+        }
+        let mut data = File::options()
+            .read(true)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(&source)
+            .map_err(|_| not_unpacked())?;
+        let source_meta = data.metadata()
+            .ok()
+            .filter(|meta| meta.is_file())
+            .ok_or_else(not_unpacked)?;
+        self.file(entry, Some(source_meta.permissions().mode()), source_meta.len(), &mut data)
+    }
+
+    fn finish(&mut self) -> Result<(), ExtractError> {
+        for (path, entry, target) in &self.links {
+            let relative_entry = entry.trim_start_matches("./");
+            if !symlink_stays_inside(relative_entry, target, |path| is_plain_dir(&self.output_dir.join(path))) {
+                return Err(ExtractError::UnsafePath { entry: entry.clone(), reason: "symlink points outside the theme" });
+            }
+            symlink(target, path).map_err(|e| ExtractError::Io { entry: entry.clone(), message: e.to_string() })?;
+        }
+        for (path, entry, mode) in self.directories.iter().rev() {
+            restore_metadata(path, EntryKind::Directory, *mode, None)
+                .map_err(|e| ExtractError::Io { entry: entry.clone(), message: e.to_string() })?;
+        }
+        Ok(())
+    }
+}
+
+// Folders inside `dir` that hold a theme. A theme folder is not searched any further, so its
+// gtk-3.0 or 48x48 subfolders are not mistaken for themes of their own.
+pub fn find_theme_roots(dir: &Path) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    collect_theme_roots(dir, 0, &mut roots);
+    roots
+}
+
+fn collect_theme_roots(dir: &Path, depth: usize, roots: &mut Vec<PathBuf>) {
+    if is_theme_root(dir) {
+        roots.push(dir.to_path_buf());
+        return;
+    }
+    if depth == MAX_SEARCH_DEPTH {
+        return;
+    }
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir())) // Symlinked folders are not followed
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            !name.starts_with('.') && name != "__MACOSX" // macOS resource forks
+        })
+        .map(|entry| entry.path())
+        .collect();
+    subdirs.sort();
+    for subdir in subdirs {
+        collect_theme_roots(&subdir, depth + 1, roots);
+    }
+}
+
+fn is_theme_root(dir: &Path) -> bool {
+    // Folders named "icons" or "48x48" only make an icon theme together with an index.theme;
+    // without one they are more likely a folder that holds icon themes
+    let components = detected_components(dir);
+    !components.is_empty() && (components != [ThemeComponent::Icons] || dir.join("index.theme").is_file())
+}
+
+// File name of the archive without its extension, e.g. "Nordic-darker" for Nordic-darker.tar.xz
+fn archive_stem(archive_path: &Path) -> String {
+    let file_name = archive_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    ARCHIVE_EXTENSIONS.iter()
+        .find_map(|extension| {
+            let cut = file_name.len().checked_sub(extension.len())?;
+            file_name.get(cut..)?.eq_ignore_ascii_case(extension).then(|| file_name[..cut].to_string())
+        })
+        .unwrap_or(file_name)
+}
+
+// Guess a manifest for a theme folder from its index.theme (or cursor.theme) and license file.
+// The folder name is kept as the theme name when there is one, since gsettings and the Inherits=
+// lines of other themes refer to themes by folder name; index.theme's Name is used otherwise.
+pub fn guess_manifest(theme_root: &Path, folder_name: Option<&str>, archive_name: &str) -> ThemeManifest {
+    let index = ["index.theme", "cursor.theme"].iter()
+        .find_map(|file| fs::read_to_string(theme_root.join(file)).ok())
+        .unwrap_or_default();
+    let display_name = ini_value(&index, "Name");
+
+    let name = [folder_name, display_name.as_deref(), Some(archive_name)].into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|name| validate_theme_name(name).is_ok())
+        .unwrap_or("Imported theme")
+        .to_string();
+    let description = ini_value(&index, "Comment")
+        .unwrap_or_else(|| format!("Imported from {}", archive_name));
+
+    let components = detected_components(theme_root);
+    ThemeManifest {
+        manifest_version: MANIFEST_VERSION,
+        name,
+        author: "Unknown".into(),
+        description,
+        version: "1.0.0".into(),
+        tags: components.iter().map(|component| component_tag(*component).to_string()).collect(),
+        license: guess_license(theme_root),
+        components,
+        ..Default::default()
+    }
+}
+
+// First unlocalized value of `key` in a desktop-entry style file
+fn ini_value(contents: &str, key: &str) -> Option<String> {
+    contents.lines()
+        .filter_map(|line| line.split_once('='))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn component_tag(component: ThemeComponent) -> &'static str {
+    match component {
+        ThemeComponent::Gtk => "gtk",
+        ThemeComponent::Icons => "icons",
+        ThemeComponent::Cursors => "cursors",
+        ThemeComponent::Fonts => "fonts",
+        ThemeComponent::Shell => "shell",
+    }
+}
+
+// SPDX identifier for the license file in a theme folder, going by its well-known opening lines
+fn guess_license(theme_root: &Path) -> String {
+    let Ok(entries) = fs::read_dir(theme_root) else {
+        return "LicenseRef-Unknown".into();
+    };
+    let text = entries.flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_ascii_uppercase();
+            name.starts_with("LICENSE") || name.starts_with("LICENCE") || name.starts_with("COPYING")
+        })
+        .find_map(|entry| fs::read_to_string(entry.path()).ok())
+        .unwrap_or_default();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    let license = if text.contains("GNU LESSER GENERAL PUBLIC LICENSE") {
+        if text.contains("Version 3") { "LGPL-3.0-or-later" } else { "LGPL-2.1-or-later" }
+    } else if text.contains("GNU GENERAL PUBLIC LICENSE") {
+        if text.contains("Version 3") { "GPL-3.0-or-later" } else { "GPL-2.0-or-later" }
+    } else if text.contains("Permission is hereby granted, free of charge") {
+        "MIT"
+    } else if text.contains("Apache License") && text.contains("Version 2.0") {
+        "Apache-2.0"
+    } else if text.contains("Attribution-ShareAlike 4.0") {
+        "CC-BY-SA-4.0"
+    } else if text.contains("SIL OPEN FONT LICENSE") {
+        "OFL-1.1"
+    } else {
+        "LicenseRef-Unknown"
+    };
+    license.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::test_support::scratch_dir;
+
+    fn tar_gz(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast()));
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn zip(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (path, contents) in files {
+            writer.start_file(*path, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn finds_themes_and_guesses_manifests() {
+        let dir = scratch_dir("import-roots");
+        let archive = dir.join("nordic-collection.tar.gz");
+        fs::write(&archive, tar_gz(&[
+            ("README.md", "Two themes"),
+            ("themes/Nordic/index.theme", "[Desktop Entry]\nName=Nordic\nName[de]=Nordisch\nComment=A dark theme\n"),
+            ("themes/Nordic/gtk-3.0/gtk.css", ""),
+            ("themes/Nordic/LICENSE", "GNU GENERAL PUBLIC LICENSE\n   Version 3, 29 June 2007"),
+            ("icons/Nordic-Folders/index.theme", "[Icon Theme]\nName=Nordic Folders\n"),
+            ("icons/Nordic-Folders/48x48/places/folder.svg", "<svg/>"),
+        ])).unwrap();
+
+        let unpacked = dir.join("unpacked");
+        unpack_archive(&archive, &unpacked, &ExtractLimits::default(), &mut Progress::none()).unwrap();
+        let roots = find_theme_roots(&unpacked);
+        assert_eq!(roots, [unpacked.join("icons/Nordic-Folders"), unpacked.join("themes/Nordic")]);
+
+        let manifest = guess_manifest(&roots[1], Some("Nordic"), &archive_stem(&archive));
+        assert_eq!((manifest.name.as_str(), manifest.description.as_str()), ("Nordic", "A dark theme"));
+        assert_eq!(manifest.license, "GPL-3.0-or-later");
+        assert_eq!(manifest.components, [ThemeComponent::Gtk, ThemeComponent::Icons]); // index.theme counts as icons
+        assert!(crate::lint::ensure_manifest_is_valid(&manifest).is_ok());
+
+        // A theme at the top of the archive takes its name from index.theme, then from the archive
+        let manifest = guess_manifest(&roots[0], None, "nordic-collection");
+        assert_eq!((manifest.name.as_str(), manifest.description.as_str()), ("Nordic Folders", "Imported from nordic-collection"));
+        assert_eq!(archive_stem(Path::new("/downloads/Bibata.TAR.XZ")), "Bibata");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn bundles_each_theme_from_a_zip() {
+        let dir = scratch_dir("import-zip");
+        let archive = dir.join("cursors.zip");
+        fs::write(&archive, zip(&[
+            ("Bibata/cursor.theme", "[Icon Theme]\nName=Bibata\nComment=Material cursors\n"),
+            ("Bibata/cursors/left_ptr", "Xcur"),
+            ("__MACOSX/Bibata/._cursor.theme", ""),
+        ])).unwrap();
+        let output = dir.join("out");
+        fs::create_dir_all(&output).unwrap();
+
+        let imported = import_archive(&archive, Some(&output), &InstallOptions::default(), &mut Progress::none()).unwrap();
+        assert_eq!(imported.len(), 1);
+        assert_eq!(imported[0].manifest.components, [ThemeComponent::Cursors]);
+        let bundle_path = imported[0].bundle_path.clone().unwrap();
+        assert_eq!(bundle_path, output.join("Bibata.reskin").to_string_lossy());
+        assert!(imported[0].message.starts_with("Bundle created"), "{}", imported[0].message); // Never signed with the author's key
+
+        let entries: Vec<String> = crate::extract::list_bundle_contents(bundle_path).unwrap().into_iter().map(|e| e.path).collect();
+        assert_eq!(entries, ["cursor.theme", "cursors", "cursors/left_ptr"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn refuses_unsafe_archive_entries() {
+        let dir = scratch_dir("import-unsafe");
+        let archive = dir.join("evil.zip");
+        fs::write(&archive, zip(&[("Theme/gtk-3.0/gtk.css", ""), ("../../evil.sh", "rm -rf ~")])).unwrap();
+        let err = unpack_archive(&archive, &dir.join("unpacked"), &ExtractLimits::default(), &mut Progress::none()).unwrap_err();
+        assert!(err.contains("path contains '..'"), "{}", err);
+        assert!(!dir.join("evil.sh").exists() && !std::env::temp_dir().join("evil.sh").exists());
+
+        let archive = dir.join("big.tar.gz");
+        fs::write(&archive, tar_gz(&[("a", "1234"), ("b", "5678")])).unwrap();
+        let limits = ExtractLimits { max_files: 10, max_total_size: 6 };
+        assert!(unpack_archive(&archive, &dir.join("big"), &limits, &mut Progress::none()).unwrap_err().contains("more than 6 bytes"));
+
+        fs::write(dir.join("notes.txt"), "not an archive").unwrap();
+        assert!(unpack_archive(&dir.join("notes.txt"), &dir.join("none"), &limits, &mut Progress::none()).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn copies_hard_links_without_following_symlinks() {
+        let dir = scratch_dir("import-hardlink");
+        let secret = dir.join("secret");
+        fs::create_dir_all(&secret).unwrap();
+        fs::write(secret.join("id_ed25519"), "private key").unwrap();
+
+        let tar_with_link = |link: &str, target: &str| {
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, "gtk.css", &b"* {}"[..]).unwrap();
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(EntryType::Link);
+            header.set_size(0);
+            builder.append_link(&mut header, link, target).unwrap();
+            builder.into_inner().unwrap()
+        };
+
+        let archive = dir.join("ok.tar");
+        fs::write(&archive, tar_with_link("gtk-dark.css", "gtk.css")).unwrap();
+        unpack_archive(&archive, &dir.join("ok"), &ExtractLimits::default(), &mut Progress::none()).unwrap();
+        assert_eq!(fs::read(dir.join("ok/gtk-dark.css")).unwrap(), b"* {}");
+
+        // Links already in the way are never followed, whether at the end or on the way
+        let out = dir.join("planted");
+        fs::create_dir_all(&out).unwrap();
+        symlink(secret.join("id_ed25519"), out.join("key")).unwrap();
+        symlink(&secret, out.join("ssh")).unwrap();
+        for (link, target) in [("stolen", "key"), ("stolen", "ssh/id_ed25519")] {
+            fs::write(&archive, tar_with_link(link, target)).unwrap();
+            assert!(unpack_archive(&archive, &out, &ExtractLimits::default(), &mut Progress::none()).is_err(), "{}", target);
+            assert!(!out.join("stolen").exists());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
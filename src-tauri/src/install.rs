@@ -1,64 +1,219 @@
 use std::fs;
-use std::path::Path;
-use crate::check::{has_gtk_or_wm_components, has_icons, has_cursors, has_fonts};
-use crate::extract::extract_theme;
-use crate::types::ThemeManifest;
-use crate::utils::{install_icons, install_cursors, install_fonts, copy_dir_recursive};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri::ipc::{InvokeBody, Request};
+use crate::extract::{extract_theme_with_progress, validate_entry_path, validate_theme_name};
+use crate::progress::{emit_to, Progress};
+use crate::types::{InstallScope, RicePack, ThemeComponent, ThemeManifest, ThemeVariant, variant_theme_name};
+use crate::utils::install_cursor_index;
+use crate::routing::{copy_routed, has_route, route_theme, Route};
 use crate::apply::apply_theme;
 use crate::recent::add_recent_theme;
+use crate::verify::ensure_bundle_is_valid;
+use crate::signing::{check_bundle_signature, SignatureStatus};
+use crate::lint::Diagnostic;
+use crate::structure::structure_diagnostics;
+use crate::pack::{config_target, install_config, save_installed_pack, forget_installed_pack};
+use crate::delta::apply_delta;
+use crate::registry::{bundle_hash, record_install, set_bundle_hash};
+use crate::transaction::InstallTransaction;
+use crate::locations::{cache_dir, home_dir, install_roots, private_dir, InstallRoots};
+use crate::policy::load_install_policy;
+use crate::system::Elevator;
 
-#[tauri::command]
-#[allow(non_snake_case)]
-pub fn install_theme_from_data(file_data: Vec<u8>, file_name: String, autoApply: bool) -> Result<String, String> {
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
+type InstalledComponents = Vec<(&'static str, PathBuf)>; // Each part installed and the folder it went to
 
-    let temp_dir = format!("/tmp/reskin_install_{}", timestamp);
-    let temp_file_path = format!("{}/{}", temp_dir, file_name);
+#[derive(Serialize, Clone, Debug)]
+pub struct InstallReport { // Result of installing a bundle
+    pub message: String, // What was installed and who signed it
+    pub diagnostics: Vec<Diagnostic>, // Problems the structure linter found in the theme
+    pub configs: Vec<String>, // Config files a pack wrote outside the theme folders
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InstallOptions { // How install_bundle treats one bundle
+    pub auto_apply: bool, // Apply the theme once it is installed
+    pub require_signed: bool, // Refuse bundles not signed by a trusted key, on top of the install policy
+    pub variants: Vec<String>, // Variants to install, all of them when empty
+    pub scope: Option<InstallScope>, // Who to install for, the saved scope when None
+    pub allow_configs: bool, // The user agreed to the config files a pack writes, so they need no trusted signature
+}
 
-    if let Err(e) = fs::create_dir_all(&temp_dir) {
-        return Err(format!("Failed to create temp directory: {}", e));
+// Files from the browser file picker have no path, so they are sent over in raw chunks and put back
+// together in a private folder instead of crossing IPC as one huge JSON array
+#[tauri::command]
+pub fn begin_upload() -> Result<String, String> {
+    let dir = private_dir(&home_dir()?, "upload")?;
+    Ok(dir.file_name().ok_or("Invalid upload directory")?.to_string_lossy().into_owned())
+}
+
+fn upload_path(home_dir: &str, upload_id: &str) -> Result<PathBuf, String> {
+    let valid = upload_id.starts_with("upload-") && upload_id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+    if !valid {
+        return Err(format!("Unknown upload '{}'", upload_id));
     }
+    Ok(cache_dir(home_dir).join(upload_id).join("bundle.reskin"))
+}
 
-    if let Err(e) = fs::write(&temp_file_path, &file_data) {
-        return Err(format!("Failed to write temp file: {}", e));
+#[tauri::command]
+pub fn append_upload(request: Request<'_>) -> Result<(), String> {
+    let InvokeBody::Raw(chunk) = request.body() else {
+        return Err("Upload chunks must be sent as raw bytes".to_string());
+    };
+    let upload_id = request.headers()
+        .get("upload-id")
+        .and_then(|id| id.to_str().ok())
+        .ok_or("Upload chunk has no upload id")?;
+    // Only appends inside a folder begin_upload made, since nothing else creates missing folders here
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(upload_path(&home_dir()?, upload_id)?)
+        .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    file.write_all(chunk).map_err(|e| format!("Failed to write temp file: {}", e))
+}
+
+#[tauri::command]
+#[allow(non_snake_case)]
+pub fn install_theme_from_upload(app: AppHandle, upload_id: String, autoApply: bool, requireSigned: Option<bool>, variants: Option<Vec<String>>, scope: Option<InstallScope>, allowConfigs: Option<bool>) -> Result<InstallReport, String> {
+    let upload_path = upload_path(&home_dir()?, &upload_id)?;
+    let options = InstallOptions {
+        auto_apply: autoApply,
+        require_signed: requireSigned.unwrap_or(false),
+        variants: variants.unwrap_or_default(),
+        scope,
+        allow_configs: allowConfigs.unwrap_or(false),
+    };
+    let result = install_bundle(&upload_path.to_string_lossy(), &options, &mut emit_to(app, "install-progress"));
+    if let Some(upload_dir) = upload_path.parent() {
+        let _ = fs::remove_dir_all(upload_dir);
     }
+    result
+}
 
-    let extracted_path = match extract_theme(temp_file_path.clone()) {
-        Ok(path) => path,
-        Err(e) => {
-            return Err(format!("Failed to extract theme: {}", e));
-        }
+#[tauri::command]
+#[allow(non_snake_case)]
+pub fn install_theme_from_file(app: AppHandle, file_path: String, autoApply: bool, requireSigned: Option<bool>, variants: Option<Vec<String>>, scope: Option<InstallScope>, allowConfigs: Option<bool>) -> Result<InstallReport, String> {
+    // Read the bundle from disk instead of sending it over IPC, reporting progress as it is unpacked
+    let options = InstallOptions {
+        auto_apply: autoApply,
+        require_signed: requireSigned.unwrap_or(false),
+        variants: variants.unwrap_or_default(),
+        scope,
+        allow_configs: allowConfigs.unwrap_or(false),
     };
+    install_bundle(&file_path, &options, &mut emit_to(app, "install-progress"))
+}
 
-    let manifest_path = format!("{}/reskin.json", extracted_path);
-    let manifest_bytes = match fs::read(&manifest_path) {
-        Ok(bytes) => bytes,
-        Err(e) => {
-            return Err(format!("Failed to read manifest: {}", e));
+// Verify, check the signature of, extract and install a bundle file. The install policy from Settings
+// always applies: `options` can demand a signature on top of it, and its scope only replaces the saved
+// one when given.
+pub fn install_bundle(bundle_path: &str, options: &InstallOptions, progress: &mut Progress) -> Result<InstallReport, String> {
+    let home_dir = home_dir()?;
+    let policy = load_install_policy(&home_dir)?;
+    let require_signed = options.require_signed || policy.require_signed;
+    let scope = options.scope.unwrap_or(policy.scope);
+
+    // Every step reads this one handle, so the bytes unpacked are the bytes that were verified and signed
+    let bundle = fs::File::open(bundle_path)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+
+    // Never unpack a truncated or corrupted bundle
+    progress.start("verifying", 0);
+    ensure_bundle_is_valid(&bundle)?;
+
+    // Check who signed the bundle before anything is unpacked
+    let signature = check_bundle_signature(&bundle)?;
+    match &signature {
+        SignatureStatus::Invalid => return Err("Refusing to install: the theme has a bad signature".to_string()),
+        // Anyone can make a key and sign with it, so only keys in the trust store count
+        SignatureStatus::Unsigned if require_signed => return Err("Refusing to install: the theme is not signed".to_string()),
+        SignatureStatus::UnknownKey { public_key } if require_signed => {
+            return Err(format!("Refusing to install: the theme is signed by {}, which is not a trusted key", public_key));
         }
+        _ => {}
+    }
+
+    let extracted_path = extract_theme_with_progress(&bundle, progress)
+        .map_err(|e| format!("Failed to extract theme: {}", e))?;
+
+    // The unpacked copy is only needed until its files are installed, whether or not that works
+    let result = install_extracted(&bundle, &extracted_path, options, scope, &signature, &home_dir, progress);
+    if let Some(extract_dir) = Path::new(&extracted_path).parent() {
+        let _ = fs::remove_dir_all(extract_dir);
+    }
+    let mut report = result?;
+    report.message = format!("{}\nSignature: {}", report.message, signature.describe());
+    Ok(report)
+}
+
+// Install a bundle that extract_theme_with_progress has unpacked to `extracted_path`
+fn install_extracted(bundle: &fs::File, extracted_path: &str, options: &InstallOptions, scope: InstallScope, signature: &SignatureStatus, home_dir: &str, progress: &mut Progress) -> Result<InstallReport, String> {
+    let manifest_path = format!("{}/reskin.json", extracted_path);
+    let manifest_bytes = fs::read(&manifest_path)
+        .map_err(|e| format!("Failed to read manifest: {}", e))?;
+    let manifest = serde_json::from_slice::<ThemeManifest>(&manifest_bytes)
+        .map_err(|e| format!("Failed to parse manifest: {}", e))?;
+    if let Some(required) = manifest.requires_newer_reskin() {
+        return Err(format!("This theme needs Reskin {} or newer (this is {})", required, env!("CARGO_PKG_VERSION")));
+    }
+
+    // Broken themes still install, but the user gets to see what is wrong with them. A delta only holds
+    // what changed, so there is no whole theme to check.
+    let diagnostics = if manifest.delta.is_some() {
+        Vec::new()
+    } else {
+        structure_diagnostics(Path::new(extracted_path))?
+    };
+    // Config files sit outside the theme folders and are read by other programs, so they are only written
+    // when the user agreed to them or someone they trust signed the pack. An update only rewrites the ones it carries.
+    let configs = match &manifest.pack {
+        Some(pack) => pack.configs.iter()
+            .filter(|config| manifest.delta.is_none() || validate_entry_path(&config.source).is_ok_and(|source| Path::new(extracted_path).join(source).is_file()))
+            .map(|config| config_target(home_dir, &config.target).map(|dest| dest.to_string_lossy().into_owned()))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Vec::new(),
     };
+    if !configs.is_empty() && !options.allow_configs && !matches!(signature, SignatureStatus::Trusted { .. }) {
+        return Err(format!("Refusing to install: the pack writes {} and is not signed by a trusted key, so the config files must be confirmed first", configs.join(", ")));
+    }
 
-    if let Err(e) = serde_json::from_slice::<ThemeManifest>(&manifest_bytes) {
-        return Err(format!("Failed to parse manifest: {}", e));
+    // Packs bring per-user config files, and updates patch the user's own copies
+    if scope == InstallScope::System && (manifest.delta.is_some() || manifest.pack.is_some() || !manifest.variants.is_empty()) {
+        return Err("Only single themes can be installed system-wide, not rice packs, variants or updates".to_string());
     }
 
-    let result = match install_theme(extracted_path.clone(), autoApply) {
-        Ok(r) => r,
-        Err(e) => {
-            return Err(e);
-        }
+    progress.start("installing", 0);
+    let result = if let Some(delta) = &manifest.delta {
+        apply_delta(Path::new(extracted_path), &manifest, delta, home_dir, &install_roots(home_dir)?)?
+    } else if let Some(pack) = &manifest.pack {
+        install_pack(extracted_path, &manifest, pack, options.auto_apply)?
+    } else if !manifest.variants.is_empty() {
+        forget_installed_pack(home_dir, &manifest.name);
+        install_variants(extracted_path, &manifest, &options.variants, options.auto_apply)?
+    } else if let Some(variant) = options.variants.first() {
+        return Err(format!("Theme has no variant '{}'", variant));
+    } else {
+        forget_installed_pack(home_dir, &manifest.name);
+        install_theme(extracted_path.to_string(), options.auto_apply, Some(scope))?
     };
 
-    let _ = fs::remove_dir_all(&temp_dir);
-    Ok(result)
+    // Remember exactly which version is installed, so updates can be shipped as deltas against it
+    let bundle_sha256 = match &manifest.delta {
+        Some(delta) => Some(delta.target_sha256.clone()),
+        None => bundle_hash(bundle)?,
+    };
+    set_bundle_hash(home_dir, &manifest, bundle_sha256)?;
+    progress.finish();
+
+    Ok(InstallReport { message: result, diagnostics, configs })
 }
 
 #[tauri::command]
 #[allow(non_snake_case)]
-pub fn install_theme(theme_path: String, autoApply: bool) -> Result<String, String> {
+pub fn install_theme(theme_path: String, autoApply: bool, scope: Option<InstallScope>) -> Result<String, String> {
     if !Path::new(&theme_path).exists() {
         return Err(format!("Theme not found at '{}'", theme_path));
     }
@@ -69,79 +224,369 @@ pub fn install_theme(theme_path: String, autoApply: bool) -> Result<String, Stri
         .to_string_lossy()
         .to_string();
 
-    let home_dir = std::env::var("HOME").unwrap_or("/home/user".into());
+    let home_dir = home_dir()?;
+    let scope = match scope {
+        Some(scope) => scope,
+        None => load_install_policy(&home_dir)?.scope,
+    };
+    let mut transaction = InstallTransaction::default();
+    let (installed_components, files) = match scope {
+        InstallScope::User => {
+            let installed_components = install_components(Path::new(&theme_path), &theme_name, &[], &install_roots(&home_dir)?, &mut transaction)?;
+            (installed_components, transaction.files.clone())
+        }
+        InstallScope::System => install_system(Path::new(&theme_path), &theme_name, &home_dir, &Elevator::pkexec())?,
+    };
 
-    let mut installed_components = Vec::new();
-    let staging_path = Path::new(&theme_path);
+    let mut result_message = format!(
+        "Theme '{}' installed successfully!\nComponents: {}",
+        theme_name, describe_components(&installed_components)
+    );
+
+    let manifest_path = format!("{}/reskin.json", theme_path);
+
+    let manifest = fs::read(&manifest_path).ok()
+        .and_then(|bytes| serde_json::from_slice::<ThemeManifest>(&bytes).ok());
+    let (author, description) = match &manifest {
+        Some(manifest) => (manifest.author.clone(), manifest.description.clone()),
+        None => ("Unknown".into(), "".into()),
+    };
+
+    // Folders without a manifest are recorded under their own name, so they can be uninstalled too
+    let manifest = manifest.unwrap_or_else(|| ThemeManifest { name: theme_name.clone(), ..Default::default() });
+    record_install(&home_dir, &theme_name, &manifest, scope, files, Vec::new())?;
+    transaction.commit();
+
+    let _ = add_recent_theme(theme_name.clone(), author, description);
+
+    if autoApply {
+        append_apply_result(&mut result_message, apply_theme(theme_name.clone(), None));
+    }
+
+    Ok(result_message)
+}
+
+// Install the selected variants (all of them when `selected` is empty), each as a theme of its own
+fn install_variants(extracted_path: &str, manifest: &ThemeManifest, selected: &[String], auto_apply: bool) -> Result<String, String> {
+    let variants: Vec<&ThemeVariant> = if selected.is_empty() {
+        manifest.variants.iter().collect()
+    } else {
+        selected.iter()
+            .map(|name| manifest.variant(name).ok_or_else(|| format!("Theme has no variant '{}'", name)))
+            .collect::<Result<_, _>>()?
+    };
+
+    let home_dir = home_dir()?;
+    let roots = install_roots(&home_dir)?;
+    let mut lines = Vec::new();
+    let mut transaction = InstallTransaction::default(); // Variants go in together or not at all
+    for variant in &variants {
+        let subtree = validate_entry_path(&variant.path).map_err(|e| e.to_string())?;
+        let source = Path::new(extracted_path).join(subtree);
+        if !source.is_dir() {
+            return Err(format!("Variant '{}' points to {}, which is not in the theme", variant.name, variant.path));
+        }
+        let installed_name = variant_theme_name(&manifest.name, &variant.name);
+        validate_theme_name(&installed_name).map_err(|e| e.to_string())?;
+
+        let installed_components = install_components(&source, &installed_name, &variant.components, &roots, &mut transaction)?;
+        lines.push(format!("{}: {}", installed_name, describe_components(&installed_components)));
+    }
+    record_install(&home_dir, &manifest.name, manifest, InstallScope::User, transaction.files.clone(), Vec::new())?;
+    transaction.commit();
 
-    if has_gtk_or_wm_components(&staging_path) {
-        let themes_dir = format!("{}/.themes", home_dir);
-        let dest_dir = format!("{}/{}", themes_dir, theme_name);
+    let _ = add_recent_theme(manifest.name.clone(), manifest.author.clone(), manifest.description.clone());
+    let mut result_message = format!("Theme '{}' installed successfully!\nVariants:\n{}", manifest.name, lines.join("\n"));
+
+    if auto_apply {
+        // Apply the default variant if it was installed, otherwise the first one
+        let variant = manifest.default_variant.as_ref()
+            .filter(|default| variants.iter().any(|v| &v.name == *default))
+            .unwrap_or(&variants[0].name);
+        append_apply_result(&mut result_message, apply_theme(manifest.name.clone(), Some(variant.clone())));
+    }
+
+    Ok(result_message)
+}
+
+// Install every theme and config file of a rice pack. Applying the pack by its name switches all of its themes.
+fn install_pack(extracted_path: &str, manifest: &ThemeManifest, pack: &RicePack, auto_apply: bool) -> Result<String, String> {
+    let home_dir = home_dir()?;
+    let mut transaction = InstallTransaction::default();
+    let lines = install_pack_contents(Path::new(extracted_path), &manifest.name, pack, &home_dir, &install_roots(&home_dir)?, &mut transaction)?;
+    record_install(&home_dir, &manifest.name, manifest, InstallScope::User, transaction.files.clone(), transaction.configs.clone())?;
+    transaction.commit();
+
+    let _ = add_recent_theme(manifest.name.clone(), manifest.author.clone(), manifest.description.clone());
+    let mut result_message = format!("Pack '{}' installed successfully!\n{}", manifest.name, lines.join("\n"));
+
+    if auto_apply {
+        append_apply_result(&mut result_message, apply_theme(manifest.name.clone(), None));
+    }
+
+    Ok(result_message)
+}
 
-        let _ = fs::create_dir_all(&themes_dir);
-        if Path::new(&dest_dir).exists() {
-            let _ = fs::remove_dir_all(&dest_dir);
+// Install the themes of a pack unpacked at `source_root` into `roots` and its configs into `home_dir`, as
+// part of `transaction`
+pub fn install_pack_contents(source_root: &Path, pack_name: &str, pack: &RicePack, home_dir: &str, roots: &InstallRoots, transaction: &mut InstallTransaction) -> Result<Vec<String>, String> {
+    if pack.themes.is_empty() && pack.configs.is_empty() {
+        return Err(format!("Pack '{}' is empty", pack_name));
+    }
+    // Check every config target before anything is copied, so a bad one does not leave half a pack behind
+    for config in &pack.configs {
+        config_target(home_dir, &config.target)?;
+    }
+
+    let mut lines = Vec::new();
+    for theme in &pack.themes {
+        let subtree = validate_entry_path(&theme.path).map_err(|e| e.to_string())?;
+        let source = source_root.join(subtree);
+        if !source.is_dir() {
+            return Err(format!("Pack theme '{}' points to {}, which is not in the theme", theme.name, theme.path));
         }
+        validate_theme_name(&theme.name).map_err(|e| e.to_string())?;
 
-        copy_dir_recursive(&theme_path, &dest_dir)
-            .map_err(|e| format!("Failed to copy theme: {}", e))?;
+        let installed_components = install_components(&source, &theme.name, &[theme.component], roots, transaction)?;
+        lines.push(format!("{}: {}", theme.name, describe_components(&installed_components)));
+    }
 
-        installed_components.push("GTK/Window Manager theme");
+    for config in &pack.configs {
+        let subtree = validate_entry_path(&config.source).map_err(|e| e.to_string())?;
+        let dest = install_config(&source_root.join(subtree), &config.target, home_dir, transaction)?;
+        lines.push(format!("Config: {}", dest.display()));
     }
 
-    if has_icons(&staging_path) {
-        install_icons(&theme_path, &theme_name, &home_dir)?;
-        installed_components.push("Icons");
+    save_installed_pack(home_dir, pack_name, pack)?;
+    Ok(lines)
+}
+
+// Copy the parts of a theme folder into the install roots, under `theme_name`. Each subtree goes only to
+// the copy it belongs in (see routing::route_path), so nothing is stored twice. Each copy is built next
+// to its target and swapped in by `transaction`, so a failure never leaves a half-copied theme. With no
+// `components` listed they are detected from the files. Returns each part with the folder it went to.
+fn install_components(source: &Path, theme_name: &str, components: &[ThemeComponent], roots: &InstallRoots, transaction: &mut InstallTransaction) -> Result<InstalledComponents, String> {
+    let entries = route_theme(source);
+    let wanted = |component: ThemeComponent, route: Route| {
+        if components.is_empty() { has_route(&entries, route) } else { components.contains(&component) }
+    };
+    // Shell themes live next to the GTK theme in the themes folder
+    let gtk = wanted(ThemeComponent::Gtk, Route::Theme) || components.contains(&ThemeComponent::Shell);
+    let icons = wanted(ThemeComponent::Icons, Route::Icons);
+    // Icons and cursors share one folder, so an icon theme brings along the cursors in it
+    let cursors = wanted(ThemeComponent::Cursors, Route::Cursors) || (icons && has_route(&entries, Route::Cursors));
+    let fonts = wanted(ThemeComponent::Fonts, Route::Fonts);
+    let copy = |routes: &[Route], staging: &Path| {
+        copy_routed(&entries, routes, staging).map_err(|e| format!("Failed to copy theme: {}", e))
+    };
+    let mut installed_components = Vec::new();
+
+    // Files that belong to no component, like assets/, go with the GTK theme, or else the icons
+    if gtk {
+        let dest = roots.themes.join(theme_name);
+        transaction.replace_dir(&dest, |staging| copy(&[Route::Theme, Route::Other], staging))?;
+        installed_components.push(("GTK/Window Manager theme", dest));
     }
 
-    if has_cursors(&staging_path) {
-        install_cursors(&theme_path, &theme_name, &home_dir)?;
-        installed_components.push("Cursors");
+    // Icons and cursors share one folder in the icons folder
+    if icons || cursors {
+        let dest = roots.icons.join(theme_name);
+        let mut routes = Vec::new();
+        routes.extend(icons.then_some(Route::Icons));
+        routes.extend(cursors.then_some(Route::Cursors));
+        routes.extend((!gtk).then_some(Route::Other));
+        transaction.replace_dir(&dest, |staging| {
+            copy(&routes, staging)?;
+            if cursors { install_cursor_index(theme_name, staging) } else { Ok(()) }
+        })?;
+        if icons {
+            installed_components.push(("Icons", dest.clone()));
+        }
+        if cursors {
+            installed_components.push(("Cursors", dest));
+        }
     }
 
-    if has_fonts(&staging_path) {
-        install_fonts(&theme_path, &theme_name, &home_dir)?;
-        installed_components.push("Fonts");
+    if fonts {
+        let dest = roots.fonts.join(theme_name);
+        transaction.replace_dir(&dest, |staging| copy(&[Route::Fonts], staging))?;
+        installed_components.push(("Fonts", dest));
     }
 
-    let components_str = if installed_components.is_empty() {
-        "No compatible components found".into()
-    } else {
-        installed_components.join(", ")
+    Ok(installed_components)
+}
+
+// Build the theme into a staging folder of the user's, then have the helper `elevator` runs copy it into
+// its roots with root rights. Returns the installed parts and every path the helper wrote.
+fn install_system(source: &Path, theme_name: &str, home_dir: &str, elevator: &Elevator) -> Result<(InstalledComponents, Vec<String>), String> {
+    // Under the home directory rather than /tmp, so other accounts cannot tamper with it
+    let staging = cache_dir(home_dir).join(format!("system-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&staging);
+    let staged_roots = InstallRoots {
+        themes: staging.join("themes"),
+        icons: staging.join("icons"),
+        fonts: staging.join("fonts"),
     };
 
-    let mut result_message = format!(
-        "Theme '{}' installed successfully!\nComponents: {}",
-        theme_name, components_str
-    );
+    let result = (|| {
+        let mut transaction = InstallTransaction::default();
+        let installed_components = install_components(source, theme_name, &[], &staged_roots, &mut transaction)?;
+        transaction.commit(); // The staging folder is thrown away either way
+        let files = elevator.install(&staging)?;
+        let installed_components = installed_components.into_iter()
+            .map(|(component, dest)| (component, staged_roots.relocate(&dest, &elevator.roots)))
+            .collect();
+        Ok((installed_components, files))
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
 
-    let manifest_path = format!("{}/reskin.json", theme_path);
+fn describe_components(installed_components: &[(&str, PathBuf)]) -> String {
+    if installed_components.is_empty() {
+        "No compatible components found".into()
+    } else {
+        installed_components.iter()
+            .map(|(component, dest)| format!("{} in {}", component, dest.display()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
 
-    let (_name, author, description) = if let Ok(bytes) = fs::read(&manifest_path) {
-        if let Ok(manifest) = serde_json::from_slice::<ThemeManifest>(&bytes) {
-            (manifest.name.clone(), manifest.author.clone(), manifest.description.clone())
-        } else {
-            ("Unknown".into(), "Unknown".into(), "".into())
+fn append_apply_result(message: &mut String, result: Result<String, String>) {
+    match result {
+        Ok(msg) => {
+            message.push_str("\n\n");
+            message.push_str(&msg);
         }
-    
-    } else {
-        ("Unknown".into(), "Unknown".into(), "".into())
-    };
+        Err(e) => {
+            message.push_str("\n\n⚠️ Failed to auto-apply: ");
+            message.push_str(&e);
+        }
+    }
+}
 
-    let _ = add_recent_theme(theme_name.clone(), author, description);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{scratch_dir, test_manifest};
 
-    if autoApply {
-        match apply_theme(theme_name.clone()) {
-            Ok(msg) => {
-                result_message.push_str("\n\n");
-                result_message.push_str(&msg);
-            }
-            Err(e) => {
-                result_message.push_str("\n\n⚠️ Failed to auto-apply: ");
-                result_message.push_str(&e);
-            }
+    #[test]
+    fn uploads_stay_in_their_own_folder() {
+        let path = upload_path("/home/nord", "upload-42-17").unwrap();
+        assert_eq!(path, Path::new("/home/nord/.cache/reskin/upload-42-17/bundle.reskin"));
+        for upload_id in ["", "upload-../../.ssh", "upload-1/../../x", "system-42"] {
+            assert!(upload_path("/home/nord", upload_id).is_err(), "{}", upload_id);
         }
     }
 
-    Ok(result_message)
+    #[test]
+    fn installs_only_the_listed_components() {
+        let root = scratch_dir("install-components");
+        let variant = root.join("Nordic-dark");
+        for dir in ["gtk-3.0", "scalable"] {
+            fs::create_dir_all(variant.join(dir)).unwrap();
+        }
+        fs::write(variant.join("index.theme"), "[Icon Theme]\nName=Nordic\n").unwrap();
+        let home = root.join("home");
+        let roots = InstallRoots::resolve(&home.to_string_lossy(), &Default::default(), None).unwrap();
+
+        // Listing only the GTK theme leaves the icons alone even though the folder has them
+        let mut transaction = InstallTransaction::default();
+        let installed = install_components(&variant, "Nordic-dark", &[ThemeComponent::Gtk], &roots, &mut transaction).unwrap();
+        assert_eq!(installed, [("GTK/Window Manager theme", home.join(".local/share/themes/Nordic-dark"))]);
+        assert!(home.join(".local/share/themes/Nordic-dark/gtk-3.0").is_dir());
+        assert!(!home.join(".local/share/icons/Nordic-dark").exists());
+        assert!(transaction.files.contains(&home.join(".local/share/themes/Nordic-dark/gtk-3.0").to_string_lossy().into_owned()));
+
+        // Without a list both are detected
+        let installed = install_components(&variant, "Nordic-dark", &[], &roots, &mut transaction).unwrap();
+        transaction.commit();
+        assert_eq!(installed.iter().map(|(component, _)| *component).collect::<Vec<_>>(), ["GTK/Window Manager theme", "Icons"]);
+        assert!(home.join(".local/share/icons/Nordic-dark/index.theme").is_file());
+
+        // Each part is stored once, in its own copy
+        assert!(!home.join(".local/share/icons/Nordic-dark/gtk-3.0").exists());
+        assert!(!home.join(".local/share/themes/Nordic-dark/scalable").exists());
+
+        // A cursor theme gets an index.theme of its own
+        let cursors = root.join("Bibata");
+        fs::create_dir_all(cursors.join("cursors")).unwrap();
+        fs::write(cursors.join("reskin.json"), "{}").unwrap();
+        let mut transaction = InstallTransaction::default();
+        let installed = install_components(&cursors, "Bibata", &[], &roots, &mut transaction).unwrap();
+        transaction.commit();
+        assert_eq!(installed, [("Cursors", home.join(".local/share/icons/Bibata"))]);
+        assert!(fs::read_to_string(home.join(".local/share/icons/Bibata/index.theme")).unwrap().starts_with("[Icon Theme]\nName=Bibata\n"));
+        assert!(!home.join(".local/share/icons/Bibata/reskin.json").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn installs_pack_themes_and_configs() {
+        let root = scratch_dir("install-pack");
+        let bundle = root.join("bundle");
+        fs::create_dir_all(bundle.join("gtk/gtk-3.0")).unwrap();
+        fs::create_dir_all(bundle.join("icons/scalable")).unwrap();
+        fs::create_dir_all(bundle.join("dotfiles")).unwrap();
+        fs::write(bundle.join("icons/index.theme"), "[Icon Theme]\nName=Nord Icons\n").unwrap();
+        fs::write(bundle.join("dotfiles/kitty.conf"), "background #2e3440\n").unwrap();
+        let home = root.join("home");
+        let home_dir = home.to_string_lossy();
+        // The legacy ~/.themes folder, picked in Settings
+        let locations = crate::locations::InstallLocations { themes: Some("~/.themes".into()), ..Default::default() };
+        let roots = InstallRoots::resolve(&home_dir, &locations, None).unwrap();
+        fs::create_dir_all(home.join(".config/kitty")).unwrap();
+        fs::write(home.join(".config/kitty/kitty.conf"), "background #000000\n").unwrap();
+
+        let pack: RicePack = serde_json::from_str(r#"{
+            "themes": [
+                {"name": "Nord", "path": "gtk", "component": "gtk"},
+                {"name": "Nord-Icons", "path": "icons", "component": "icons"}
+            ],
+            "configs": [{"source": "dotfiles/kitty.conf", "target": "~/.config/kitty/kitty.conf"}]
+        }"#).unwrap();
+        let mut transaction = InstallTransaction::default();
+        install_pack_contents(&bundle, "Nord Rice", &pack, &home_dir, &roots, &mut transaction).unwrap();
+        transaction.commit();
+
+        assert!(home.join(".themes/Nord/gtk-3.0").is_dir());
+        assert!(home.join(".local/share/icons/Nord-Icons/index.theme").is_file());
+        assert_eq!(fs::read_to_string(home.join(".config/kitty/kitty.conf")).unwrap(), "background #2e3440\n");
+        assert_eq!(fs::read_to_string(home.join(".config/kitty/kitty.conf.bak")).unwrap(), "background #000000\n");
+        assert_eq!(crate::pack::load_installed_pack(&home_dir, "Nord Rice").unwrap(), Some(pack.clone()));
+
+        // Configs aimed outside the home directory are refused
+        let mut escaping = pack;
+        escaping.configs[0].target = "~/../../etc/profile".into();
+        assert!(install_pack_contents(&bundle, "Nord Rice", &escaping, &home_dir, &roots, &mut InstallTransaction::default()).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn installs_system_wide_through_the_helper() {
+        let root = scratch_dir("install-system");
+        let theme = root.join("Nordic");
+        fs::create_dir_all(theme.join("gtk-3.0")).unwrap();
+        fs::create_dir_all(theme.join("scalable")).unwrap();
+        fs::write(theme.join("index.theme"), "[Icon Theme]\nName=Nordic\n").unwrap();
+        let home = root.join("home");
+        let home_dir = home.to_string_lossy();
+
+        // A stand-in for pkexec that runs the helper in this process, against roots under the test folder
+        let system = InstallRoots::resolve(&root.join("usr").to_string_lossy(), &Default::default(), None).unwrap();
+        let elevator = Elevator::new(system.clone(), |args| crate::system::run_helper(args, &system));
+        let (installed, files) = install_system(&theme, "Nordic", &home_dir, &elevator).unwrap();
+        assert_eq!(installed, [("GTK/Window Manager theme", system.themes.join("Nordic")), ("Icons", system.icons.join("Nordic"))]);
+        assert!(system.themes.join("Nordic/gtk-3.0").is_dir() && system.icons.join("Nordic/index.theme").is_file());
+        assert!(!home.join(".local/share/themes/Nordic").exists());
+        assert!(!home.join(".cache/reskin").read_dir().unwrap().any(|_| true)); // Staging is cleaned up
+
+        // The record says to uninstall through the helper too
+        let manifest = test_manifest("Nordic");
+        record_install(&home_dir, "Nordic", &manifest, InstallScope::System, files, Vec::new()).unwrap();
+        crate::uninstall::uninstall(&home_dir, "Nordic", &elevator).unwrap();
+        assert!(!system.themes.join("Nordic").exists() && !system.icons.join("Nordic").exists());
+        let _ = fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,644 @@
+// Import necessary crates
+use std::path::Path;
+use serde::Serialize;
+use crate::types::{RicePack, ThemeComponent, ThemeManifest, variant_theme_name, version_numbers};
+use crate::check::{has_cursors, has_fonts, has_gtk_or_wm_components, has_icons, has_shell_theme};
+use crate::extract::{validate_entry_path, validate_theme_name};
+use crate::structure::structure_diagnostics;
+use crate::preview::{DEFAULT_PREVIEWS, PREVIEW_EXTENSIONS};
+use crate::pack::{config_target, read_pack_file};
+
+const MAX_NAME_LEN: usize = 255; // Longest file name most Linux file systems accept, in bytes
+
+// SPDX license identifiers themes commonly use. Identifiers that are well-formed but not listed here
+// only cause a warning, since the full SPDX list is much longer.
+const KNOWN_LICENSES: &[&str] = &[
+    "0BSD", "AFL-3.0", "AGPL-3.0-only", "AGPL-3.0-or-later", "Apache-1.1", "Apache-2.0", "Artistic-2.0",
+    "BSD-1-Clause", "BSD-2-Clause", "BSD-3-Clause", "BSL-1.0", "CC0-1.0", "CC-BY-3.0", "CC-BY-4.0",
+    "CC-BY-NC-4.0", "CC-BY-NC-ND-4.0", "CC-BY-NC-SA-4.0", "CC-BY-ND-4.0", "CC-BY-SA-3.0", "CC-BY-SA-4.0",
+    "EPL-2.0", "EUPL-1.2", "GFDL-1.3-only", "GFDL-1.3-or-later", "GPL-2.0-only", "GPL-2.0-or-later",
+    "GPL-3.0-only", "GPL-3.0-or-later", "ISC", "LGPL-2.1-only", "LGPL-2.1-or-later", "LGPL-3.0-only",
+    "LGPL-3.0-or-later", "MIT", "MIT-0", "MPL-2.0", "OFL-1.1", "OFL-1.1-no-RFN", "OFL-1.1-RFN",
+    "Ubuntu-font-1.0", "Unlicense", "WTFPL", "X11", "Zlib",
+];
+
+// Deprecated SPDX identifiers and what replaced them
+const DEPRECATED_LICENSES: &[(&str, &str)] = &[
+    ("AGPL-3.0", "AGPL-3.0-only or AGPL-3.0-or-later"),
+    ("GPL-2.0", "GPL-2.0-only or GPL-2.0-or-later"),
+    ("GPL-3.0", "GPL-3.0-only or GPL-3.0-or-later"),
+    ("LGPL-2.1", "LGPL-2.1-only or LGPL-2.1-or-later"),
+    ("LGPL-3.0", "LGPL-3.0-only or LGPL-3.0-or-later"),
+];
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error, // Bundling is refused
+    Warning, // Worth fixing, but the theme still works
+    Hint, // Nothing is wrong, but the theme could make more of itself
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Diagnostic { // One problem found in a manifest or theme folder
+    pub severity: Severity,
+    pub subject: String, // Manifest field or theme path the problem is about
+    pub message: String, // What is wrong and how to fix it
+}
+
+impl Diagnostic {
+    pub fn error(subject: &str, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, subject: subject.to_string(), message: message.into() }
+    }
+
+    pub fn warning(subject: &str, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, subject: subject.to_string(), message: message.into() }
+    }
+
+    pub fn hint(subject: &str, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Hint, subject: subject.to_string(), message: message.into() }
+    }
+}
+
+#[tauri::command]
+pub fn validate_manifest(manifest: ThemeManifest) -> Vec<Diagnostic> {
+    manifest_diagnostics(&manifest)
+}
+
+#[tauri::command]
+pub fn lint_theme(theme_directory: String, mut manifest: ThemeManifest) -> Result<Vec<Diagnostic>, String> {
+    let theme_root = Path::new(&theme_directory);
+    if !theme_root.is_dir() {
+        return Err(format!("Theme directory '{}' does not exist", theme_directory));
+    }
+    // The bundler takes the pack from reskin-pack.json, so lint what it would write
+    if manifest.pack.is_none() {
+        manifest.pack = read_pack_file(theme_root)?;
+    }
+
+    let mut diagnostics = manifest_diagnostics(&manifest);
+    diagnostics.extend(component_diagnostics(theme_root, &manifest));
+    diagnostics.extend(variant_diagnostics(theme_root, &manifest));
+    if let Some(pack) = &manifest.pack {
+        diagnostics.extend(pack_diagnostics(theme_root, pack));
+    }
+    diagnostics.extend(screenshot_diagnostics(theme_root, &manifest.screenshots));
+    match &manifest.preview_image {
+        Some(preview) if !theme_root.join(preview).is_file() => {
+            diagnostics.push(Diagnostic::warning("preview_image", format!("Preview image {} is not in the theme folder", preview)));
+        }
+        Some(_) => {}
+        // Only the preview the manifest names is shown, so point out an image that looks like one
+        None => match DEFAULT_PREVIEWS.iter().find(|name| theme_root.join(name).is_file()) {
+            Some(name) => diagnostics.push(Diagnostic::hint("preview_image", format!("Set the preview image to {} to show it on theme cards", name))),
+            None => diagnostics.push(Diagnostic::hint("preview_image", "No preview image, so theme cards show no picture")),
+        },
+    }
+    diagnostics.extend(structure_diagnostics(theme_root)?);
+    diagnostics.sort_by_key(|d| d.severity); // Errors first
+    Ok(diagnostics)
+}
+
+// Refuse to write a manifest with errors in it
+pub fn ensure_manifest_is_valid(manifest: &ThemeManifest) -> Result<(), String> {
+    let errors: Vec<String> = manifest_diagnostics(manifest)
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| format!(" - {}: {}", d.subject, d.message))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Manifest is not valid:\n{}", errors.join("\n")))
+    }
+}
+
+// Check the manifest fields on their own, without looking at any theme files
+pub fn manifest_diagnostics(manifest: &ThemeManifest) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    check_name(&manifest.name, &mut diagnostics);
+
+    if manifest.author.trim().is_empty() {
+        diagnostics.push(Diagnostic::error("author", "Author is empty"));
+    }
+    if manifest.description.trim().is_empty() {
+        diagnostics.push(Diagnostic::warning("description", "Description is empty"));
+    }
+
+    if manifest.version.trim().is_empty() {
+        diagnostics.push(Diagnostic::error("version", "Version is empty"));
+    } else if !is_semver(&manifest.version) {
+        diagnostics.push(Diagnostic::error("version", format!("'{}' is not a semantic version such as 1.0.0", manifest.version)));
+    }
+
+    check_license(&manifest.license, &mut diagnostics);
+
+    if manifest.tags.is_empty() {
+        diagnostics.push(Diagnostic::warning("tags", "No tags, so the theme is harder to find on the marketplace"));
+    }
+
+    for (field, url) in [("homepage", &manifest.homepage), ("repository", &manifest.repository)] {
+        if let Some(url) = url {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                diagnostics.push(Diagnostic::warning(field, format!("'{}' is not an http(s) URL", url)));
+            }
+        }
+    }
+
+    if let Some(preview) = &manifest.preview_image {
+        let extension = Path::new(preview).extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        if preview.contains("://") {
+            diagnostics.push(Diagnostic::warning("preview_image", "Preview must be an image inside the theme folder, not a URL"));
+        } else if !PREVIEW_EXTENSIONS.contains(&extension.as_str()) {
+            diagnostics.push(Diagnostic::warning("preview_image", format!("{} is not a PNG, JPEG or WebP image", preview)));
+        }
+    }
+
+    check_variants(manifest, &mut diagnostics);
+    if let Some(pack) = &manifest.pack {
+        check_pack(pack, !manifest.variants.is_empty(), &mut diagnostics);
+    }
+
+    for desktop in &manifest.desktops {
+        if desktop.name.trim().is_empty() {
+            diagnostics.push(Diagnostic::error("desktops", "Desktop name is empty"));
+        }
+    }
+
+    if let Some(required) = &manifest.min_reskin_version {
+        if !required.split('.').all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit())) {
+            diagnostics.push(Diagnostic::error("min_reskin_version", format!("'{}' is not a version such as 1.8.0", required)));
+        } else if version_numbers(required) > version_numbers(env!("CARGO_PKG_VERSION")) {
+            diagnostics.push(Diagnostic::warning("min_reskin_version", format!("Reskin {} has not been released yet", required)));
+        }
+    }
+
+    diagnostics
+}
+
+fn check_name(name: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if name.trim().is_empty() {
+        diagnostics.push(Diagnostic::error("name", "Name is empty"));
+        return;
+    }
+    // The name becomes the directory the theme is installed into
+    if validate_theme_name(name).is_err() || name.chars().any(char::is_control) {
+        diagnostics.push(Diagnostic::error("name", format!("'{}' cannot be used as a directory name", name)));
+    } else if name.len() > MAX_NAME_LEN {
+        diagnostics.push(Diagnostic::error("name", format!("Name is longer than {} bytes", MAX_NAME_LEN)));
+    }
+    if name.starts_with('.') {
+        diagnostics.push(Diagnostic::warning("name", "Names starting with '.' are installed as hidden directories"));
+    }
+    if name.trim() != name {
+        diagnostics.push(Diagnostic::warning("name", "Name starts or ends with whitespace"));
+    }
+}
+
+fn check_variants(manifest: &ThemeManifest, diagnostics: &mut Vec<Diagnostic>) {
+    for (index, variant) in manifest.variants.iter().enumerate() {
+        // Variants are installed as <theme>-<variant>, so the name has to be usable in a directory name
+        if variant.name.trim().is_empty() {
+            diagnostics.push(Diagnostic::error("variants", "Variant name is empty"));
+        } else if variant.name.contains('/') || validate_theme_name(&variant_theme_name(&manifest.name, &variant.name)).is_err() {
+            diagnostics.push(Diagnostic::error("variants", format!("Variant name '{}' cannot be used in a directory name", variant.name)));
+        } else if manifest.variants[..index].iter().any(|other| other.name == variant.name) {
+            diagnostics.push(Diagnostic::error("variants", format!("Variant {} is declared twice", variant.name)));
+        }
+        if validate_entry_path(&variant.path).is_err() {
+            diagnostics.push(Diagnostic::error("variants", format!("Variant {} must point to a relative folder inside the theme, not '{}'", variant.name, variant.path)));
+        }
+    }
+
+    if let Some(default) = &manifest.default_variant {
+        if manifest.variant(default).is_none() {
+            diagnostics.push(Diagnostic::error("default_variant", format!("There is no variant named {}", default)));
+        }
+    }
+}
+
+fn check_pack(pack: &RicePack, has_variants: bool, diagnostics: &mut Vec<Diagnostic>) {
+    if has_variants {
+        diagnostics.push(Diagnostic::error("pack", "A pack cannot have variants; list each flavour as a theme of the pack"));
+    }
+    if pack.themes.is_empty() && pack.configs.is_empty() {
+        diagnostics.push(Diagnostic::error("pack", "Pack has no themes or config files"));
+    }
+
+    for (index, theme) in pack.themes.iter().enumerate() {
+        if theme.name.contains('/') || validate_theme_name(&theme.name).is_err() {
+            diagnostics.push(Diagnostic::error("pack", format!("Pack theme name '{}' cannot be used in a directory name", theme.name)));
+        } else if pack.themes[..index].iter().any(|other| other.name == theme.name && other.component == theme.component) {
+            diagnostics.push(Diagnostic::error("pack", format!("Pack theme {} is listed twice", theme.name)));
+        }
+        if validate_entry_path(&theme.path).is_err() {
+            diagnostics.push(Diagnostic::error("pack", format!("Pack theme {} must point to a relative folder inside the theme, not '{}'", theme.name, theme.path)));
+        }
+    }
+
+    for (index, config) in pack.configs.iter().enumerate() {
+        if validate_entry_path(&config.source).is_err() {
+            diagnostics.push(Diagnostic::error("pack", format!("Config source must be a relative path inside the theme, not '{}'", config.source)));
+        }
+        if let Err(reason) = config_target("~", &config.target) {
+            diagnostics.push(Diagnostic::error("pack", reason));
+        } else if pack.configs[..index].iter().any(|other| other.target == config.target) {
+            diagnostics.push(Diagnostic::error("pack", format!("Two config files go to {}", config.target)));
+        }
+    }
+}
+
+fn check_license(license: &str, diagnostics: &mut Vec<Diagnostic>) {
+    if license.trim().is_empty() {
+        diagnostics.push(Diagnostic::error("license", "License is empty"));
+        return;
+    }
+    let identifiers = match parse_spdx_expression(license) {
+        Ok(identifiers) => identifiers,
+        Err(reason) => {
+            diagnostics.push(Diagnostic::error("license", format!("'{}' is not a valid SPDX license expression: {}", license, reason)));
+            return;
+        }
+    };
+
+    for id in identifiers {
+        let base = id.trim_end_matches('+');
+        if let Some((_, replacement)) = DEPRECATED_LICENSES.iter().find(|(old, _)| old.eq_ignore_ascii_case(base)) {
+            diagnostics.push(Diagnostic::warning("license", format!("{} is deprecated, use {}", id, replacement)));
+        } else if !base.starts_with("LicenseRef-") && !KNOWN_LICENSES.iter().any(|known| known.eq_ignore_ascii_case(base)) {
+            diagnostics.push(Diagnostic::warning("license", format!("{} is not a known SPDX license identifier", id)));
+        }
+    }
+}
+
+// Components the check.rs detectors find in a folder
+pub fn detected_components(dir: &Path) -> Vec<ThemeComponent> {
+    [
+        (ThemeComponent::Gtk, has_gtk_or_wm_components(dir)),
+        (ThemeComponent::Icons, has_icons(dir)),
+        (ThemeComponent::Cursors, has_cursors(dir)),
+        (ThemeComponent::Fonts, has_fonts(dir)),
+        (ThemeComponent::Shell, has_shell_theme(dir)),
+    ].into_iter().filter(|(_, found)| *found).map(|(component, _)| component).collect()
+}
+
+// Check that the components the manifest declares are really in the folder or its variants, and mention
+// the ones it forgot
+fn component_diagnostics(theme_root: &Path, manifest: &ThemeManifest) -> Vec<Diagnostic> {
+    let declared = &manifest.components;
+    let mut detected = detected_components(theme_root);
+    let subtrees = manifest.variants.iter().map(|variant| &variant.path)
+        .chain(manifest.pack.iter().flat_map(|pack| pack.themes.iter().map(|theme| &theme.path)));
+    for subtree in subtrees {
+        for component in detected_components(&theme_root.join(subtree)) {
+            if !detected.contains(&component) {
+                detected.push(component);
+            }
+        }
+    }
+
+    let mut diagnostics = Vec::new();
+    if declared.is_empty() {
+        if !detected.is_empty() {
+            let names: Vec<&str> = detected.iter().map(|c| component_name(*c)).collect();
+            diagnostics.push(Diagnostic::warning("components", format!("No components declared; the folder contains: {}", names.join(", "))));
+        }
+        return diagnostics;
+    }
+
+    for component in declared {
+        if !detected.contains(component) {
+            diagnostics.push(Diagnostic::error("components", format!("Declares {} but the folder has none", component_name(*component))));
+        }
+    }
+    for component in &detected {
+        if !declared.contains(component) {
+            diagnostics.push(Diagnostic::warning("components", format!("Contains {} but does not declare it", component_name(*component))));
+        }
+    }
+    diagnostics
+}
+
+// Check that every variant directory exists and holds the components the variant lists
+fn variant_diagnostics(theme_root: &Path, manifest: &ThemeManifest) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for variant in &manifest.variants {
+        let dir = theme_root.join(&variant.path);
+        if validate_entry_path(&variant.path).is_err() {
+            continue; // Already reported by manifest_diagnostics
+        }
+        if !dir.is_dir() {
+            diagnostics.push(Diagnostic::error("variants", format!("Variant {} points to {}, which is not a folder", variant.name, variant.path)));
+            continue;
+        }
+        let detected = detected_components(&dir);
+        for component in &variant.components {
+            if !detected.contains(component) {
+                diagnostics.push(Diagnostic::error("variants", format!("Variant {} declares {} but {} has none", variant.name, component_name(*component), variant.path)));
+            }
+        }
+    }
+    diagnostics
+}
+
+// Check that the pack's theme folders hold the kind of theme they are listed as, and that its config files exist
+fn pack_diagnostics(theme_root: &Path, pack: &RicePack) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for theme in pack.themes.iter().filter(|theme| validate_entry_path(&theme.path).is_ok()) {
+        let dir = theme_root.join(&theme.path);
+        if !dir.is_dir() {
+            diagnostics.push(Diagnostic::error("pack", format!("Pack theme {} points to {}, which is not a folder", theme.name, theme.path)));
+        } else if !detected_components(&dir).contains(&theme.component) {
+            diagnostics.push(Diagnostic::error("pack", format!("Pack theme {} is listed as {} but {} has none", theme.name, component_name(theme.component), theme.path)));
+        }
+    }
+    for config in pack.configs.iter().filter(|config| validate_entry_path(&config.source).is_ok()) {
+        if !theme_root.join(&config.source).is_file() {
+            diagnostics.push(Diagnostic::error("pack", format!("Config file {} is not in the theme folder", config.source)));
+        }
+    }
+    diagnostics
+}
+
+fn screenshot_diagnostics(theme_root: &Path, screenshots: &[String]) -> Vec<Diagnostic> {
+    screenshots.iter()
+        .filter(|s| !s.starts_with("https://") && !s.starts_with("http://"))
+        .filter(|s| !theme_root.join(s).is_file())
+        .map(|s| Diagnostic::warning("screenshots", format!("Screenshot {} is not in the theme folder", s)))
+        .collect()
+}
+
+fn component_name(component: ThemeComponent) -> &'static str {
+    match component {
+        ThemeComponent::Gtk => "a GTK theme",
+        ThemeComponent::Icons => "icons",
+        ThemeComponent::Cursors => "cursors",
+        ThemeComponent::Fonts => "fonts",
+        ThemeComponent::Shell => "a shell theme",
+    }
+}
+
+// Semantic Versioning 2.0.0: MAJOR.MINOR.PATCH with optional -prerelease and +build parts
+pub fn is_semver(version: &str) -> bool {
+    let (version, build) = match version.split_once('+') {
+        Some((version, build)) => (version, Some(build)),
+        None => (version, None),
+    };
+    let (core, prerelease) = match version.split_once('-') {
+        Some((core, prerelease)) => (core, Some(prerelease)),
+        None => (version, None),
+    };
+
+    let is_identifier = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) && (s == "0" || !s.starts_with('0'));
+
+    let core: Vec<&str> = core.split('.').collect();
+    core.len() == 3
+        && core.iter().all(|part| is_number(part))
+        && prerelease.is_none_or(|pre| pre.split('.').all(|id| {
+            is_identifier(id) && (!id.bytes().all(|b| b.is_ascii_digit()) || is_number(id))
+        }))
+        && build.is_none_or(|build| build.split('.').all(is_identifier))
+}
+
+// Parse an SPDX license expression such as "MIT OR (GPL-3.0-or-later WITH Classpath-exception-2.0)",
+// returning the license identifiers it uses
+fn parse_spdx_expression(expression: &str) -> Result<Vec<String>, String> {
+    let tokens: Vec<String> = expression
+        .replace('(', " ( ")
+        .replace(')', " ) ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    let mut parser = SpdxParser { tokens: &tokens, position: 0, licenses: Vec::new() };
+    parser.or_expression()?;
+    match tokens.get(parser.position) {
+        None => Ok(parser.licenses),
+        Some(token) => Err(format!("unexpected '{}'", token)),
+    }
+}
+
+struct SpdxParser<'t> { // Recursive descent parser over expression tokens
+    tokens: &'t [String],
+    position: usize,
+    licenses: Vec<String>, // License identifiers seen so far
+}
+
+impl SpdxParser<'_> {
+    fn next(&mut self) -> Option<&str> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
+
+    fn next_is(&self, token: &str) -> bool {
+        self.tokens.get(self.position).is_some_and(|t| t == token)
+    }
+
+    fn or_expression(&mut self) -> Result<(), String> {
+        self.and_expression()?;
+        while self.next_is("OR") {
+            self.position += 1;
+            self.and_expression()?;
+        }
+        Ok(())
+    }
+
+    fn and_expression(&mut self) -> Result<(), String> {
+        self.term()?;
+        while self.next_is("AND") {
+            self.position += 1;
+            self.term()?;
+        }
+        Ok(())
+    }
+
+    fn term(&mut self) -> Result<(), String> {
+        match self.next() {
+            None => Err("expression ends too early".to_string()),
+            Some("(") => {
+                self.or_expression()?;
+                match self.next() {
+                    Some(")") => Ok(()),
+                    _ => Err("missing ')'".to_string()),
+                }
+            }
+            Some(token @ (")" | "AND" | "OR" | "WITH")) => Err(format!("unexpected '{}'", token)),
+            Some(license) => {
+                let license = license.to_string();
+                if !is_spdx_id(license.strip_suffix('+').unwrap_or(&license)) {
+                    return Err(format!("'{}' is not a license identifier", license));
+                }
+                self.licenses.push(license);
+                if self.next_is("WITH") {
+                    self.position += 1;
+                    match self.next() {
+                        Some(exception) if is_spdx_id(exception) => {}
+                        _ => return Err("WITH must be followed by an exception identifier".to_string()),
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn is_spdx_id(id: &str) -> bool { // Letters, digits, "." and "-", optionally behind a DocumentRef-...: prefix
+    let id = match id.split_once(':') {
+        Some((document, id)) if document.starts_with("DocumentRef-") => id,
+        _ => id,
+    };
+    !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'.' || b == b'-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::test_support::{scratch_dir, test_manifest};
+
+
+    fn subjects(diagnostics: &[Diagnostic], severity: Severity) -> Vec<&str> {
+        diagnostics.iter().filter(|d| d.severity == severity).map(|d| d.subject.as_str()).collect()
+    }
+
+    #[test]
+    fn accepts_a_clean_manifest() {
+        assert_eq!(manifest_diagnostics(&test_manifest("Nordic")), []);
+        assert!(ensure_manifest_is_valid(&test_manifest("Nordic")).is_ok());
+    }
+
+    #[test]
+    fn reports_bad_fields() {
+        let bad = ThemeManifest {
+            name: "Nordic/Dark".into(),
+            version: "1.0".into(),
+            license: "MIT AND".into(),
+            homepage: Some("example.org".into()),
+            ..test_manifest("Nordic")
+        };
+        let diagnostics = manifest_diagnostics(&bad);
+        assert_eq!(subjects(&diagnostics, Severity::Error), ["name", "version", "license"]);
+        assert_eq!(subjects(&diagnostics, Severity::Warning), ["homepage"]);
+        assert!(ensure_manifest_is_valid(&bad).unwrap_err().contains("not a semantic version"));
+    }
+
+    #[test]
+    fn checks_semver_and_spdx() {
+        for good in ["0.1.0", "1.0.0-beta.2", "2.10.3+build.5", "1.0.0-0.3.7"] {
+            assert!(is_semver(good), "{}", good);
+        }
+        for bad in ["1.0", "01.0.0", "1.0.0-", "1.0.0-01", "v1.0.0", "1.0.0+"] {
+            assert!(!is_semver(bad), "{}", bad);
+        }
+
+        assert_eq!(parse_spdx_expression("MIT OR (Apache-2.0 AND CC-BY-SA-4.0)").unwrap(), ["MIT", "Apache-2.0", "CC-BY-SA-4.0"]);
+        assert_eq!(parse_spdx_expression("GPL-2.0-or-later WITH Font-exception-2.0").unwrap(), ["GPL-2.0-or-later"]);
+        for bad in ["MIT OR", "(MIT", "MIT Apache-2.0", "My License!", "MIT WITH"] {
+            assert!(parse_spdx_expression(bad).is_err(), "{}", bad);
+        }
+
+        // Well-formed but deprecated or unknown identifiers are only warnings
+        let diagnostics = manifest_diagnostics(&ThemeManifest { license: "GPL-3.0 OR Made-Up-1.0".into(), ..test_manifest("Nordic") });
+        assert_eq!(subjects(&diagnostics, Severity::Warning), ["license", "license"]);
+        assert!(subjects(&diagnostics, Severity::Error).is_empty());
+    }
+
+    #[test]
+    fn checks_declared_components_against_the_folder() {
+        let root = scratch_dir("lint-components");
+        fs::create_dir_all(root.join("gtk-3.0")).unwrap();
+        fs::create_dir_all(root.join("cursors")).unwrap();
+        fs::write(root.join("gtk-3.0/gtk.css"), "").unwrap();
+        fs::write(root.join("cursors/left_ptr"), b"Xcur").unwrap();
+
+        let declared = ThemeManifest {
+            components: vec![ThemeComponent::Gtk, ThemeComponent::Icons],
+            screenshots: vec!["preview.png".into()],
+            ..test_manifest("Nordic")
+        };
+        let diagnostics = lint_theme(root.to_string_lossy().into_owned(), declared).unwrap();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Declares icons but the folder has none",
+            "Contains cursors but does not declare it",
+            "Screenshot preview.png is not in the theme folder",
+            "No preview image, so theme cards show no picture",
+        ]);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[3].severity, Severity::Hint);
+
+        // An image that looks like a preview is suggested, but never used unless the manifest names it
+        fs::write(root.join("preview.png"), b"").unwrap();
+        let diagnostics = lint_theme(root.to_string_lossy().into_owned(), test_manifest("Nordic")).unwrap();
+        let hint = diagnostics.iter().find(|d| d.subject == "preview_image").unwrap();
+        assert_eq!(hint.message, "Set the preview image to preview.png to show it on theme cards");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn checks_variants() {
+        let root = scratch_dir("lint-variants");
+        fs::create_dir_all(root.join("Nordic/gtk-3.0")).unwrap();
+        fs::create_dir_all(root.join("Nordic-dark")).unwrap();
+        fs::write(root.join("Nordic/gtk-3.0/gtk.css"), "").unwrap();
+
+        let variant = |name: &str, path: &str| crate::types::ThemeVariant {
+            name: name.into(),
+            path: path.into(),
+            components: vec![ThemeComponent::Gtk],
+        };
+        let with_variants = ThemeManifest {
+            components: vec![ThemeComponent::Gtk],
+            variants: vec![
+                variant("light", "Nordic"),
+                variant("dark", "Nordic-dark"),
+                variant("dark", "../outside"),
+                variant("compact", "Nordic-compact"),
+            ],
+            default_variant: Some("blue".into()),
+            ..test_manifest("Nordic")
+        };
+        let diagnostics = lint_theme(root.to_string_lossy().into_owned(), with_variants).unwrap();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Variant dark is declared twice",
+            "Variant dark must point to a relative folder inside the theme, not '../outside'",
+            "There is no variant named blue",
+            "Variant dark declares a GTK theme but Nordic-dark has none",
+            "Variant compact points to Nordic-compact, which is not a folder",
+            "No preview image, so theme cards show no picture",
+        ]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn checks_packs() {
+        let root = scratch_dir("lint-pack");
+        fs::create_dir_all(root.join("gtk/gtk-3.0")).unwrap();
+        fs::create_dir_all(root.join("nord-icons")).unwrap();
+        fs::write(root.join("gtk/gtk-3.0/gtk.css"), "").unwrap();
+        fs::write(root.join(crate::pack::PACK_FILE), r#"{
+            "themes": [
+                {"name": "Nord", "path": "gtk", "component": "gtk"},
+                {"name": "Nord-Icons", "path": "nord-icons", "component": "icons"}
+            ],
+            "configs": [
+                {"source": "dotfiles/kitty.conf", "target": "~/.config/kitty/kitty.conf"},
+                {"source": "dotfiles/profile", "target": "/etc/profile"}
+            ]
+        }"#).unwrap();
+
+        // The pack comes from reskin-pack.json when the manifest has none
+        let diagnostics = lint_theme(root.to_string_lossy().into_owned(), ThemeManifest { components: vec![ThemeComponent::Gtk], ..test_manifest("Nordic") }).unwrap();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, [
+            "Config target '/etc/profile' must be inside ~/.config/<app>/",
+            "Pack theme Nord-Icons is listed as icons but nord-icons has none",
+            "Config file dotfiles/kitty.conf is not in the theme folder",
+            "Config file dotfiles/profile is not in the theme folder",
+            "No preview image, so theme cards show no picture",
+        ]);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,194 @@
+// Import necessary crates
+use std::fs;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct InstallLocations { // Folders picked in Settings; unset ones follow the XDG base directories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub themes: Option<String>, // GTK, window manager and shell themes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icons: Option<String>, // Icon and cursor themes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fonts: Option<String>, // Fonts
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct InstallRoots { // Folders installs actually go to
+    pub themes: PathBuf,
+    pub icons: PathBuf,
+    pub fonts: PathBuf,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct InstallLocationSettings { // What Settings shows: the saved choices and where they lead
+    pub locations: InstallLocations,
+    pub roots: InstallRoots,
+}
+
+pub fn home_dir() -> Result<String, String> { // User's home directory
+    std::env::var("HOME")
+        .ok()
+        .filter(|home| !home.is_empty())
+        .ok_or_else(|| "Failed to get HOME directory".to_string())
+}
+
+// Reskin's cache folder, which unlike /tmp only this account can write to
+pub fn cache_dir(home_dir: &str) -> PathBuf {
+    Path::new(home_dir).join(".cache/reskin")
+}
+
+// Reskin's config folder, where settings, keys and install records are kept
+pub fn config_dir(home_dir: &str) -> PathBuf {
+    Path::new(home_dir).join(".config/reskin")
+}
+
+// Create a new folder in the cache that only the owner can open
+pub fn private_dir(home_dir: &str, prefix: &str) -> Result<PathBuf, String> {
+    let cache_dir = cache_dir(home_dir);
+    fs::create_dir_all(&cache_dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+    let dir = cache_dir.join(format!("{}-{}-{}", prefix, std::process::id(), nanos));
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&dir) // Fails rather than reusing a folder that is already there
+        .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    Ok(dir)
+}
+
+fn settings_path(home_dir: &str) -> PathBuf {
+    config_dir(home_dir).join("install.json")
+}
+
+pub fn load_install_locations(home_dir: &str) -> Result<InstallLocations, String> {
+    let settings_path = settings_path(home_dir);
+    if !settings_path.is_file() {
+        return Ok(InstallLocations::default());
+    }
+    let bytes = fs::read(&settings_path).map_err(|e| format!("Failed to read install settings: {}", e))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse install settings: {}", e))
+}
+
+// $XDG_DATA_HOME, or ~/.local/share when it is unset or relative, which the spec says to ignore
+fn data_home(home_dir: &str, xdg_data_home: Option<&str>) -> PathBuf {
+    xdg_data_home
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map_or_else(|| Path::new(home_dir).join(".local/share"), PathBuf::from)
+}
+
+// Turn a folder typed in Settings into an absolute path, expanding a leading ~
+fn expand_location(home_dir: &str, location: &str) -> Result<PathBuf, String> {
+    let path = match location.strip_prefix('~') {
+        Some("") => PathBuf::from(home_dir),
+        Some(rest) if rest.starts_with('/') => Path::new(home_dir).join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(location),
+    };
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Err(format!("Install location '{}' must be an absolute path or start with ~/", location))
+    }
+}
+
+impl InstallRoots {
+    // Apply the saved choices on top of the XDG defaults, e.g. $XDG_DATA_HOME/themes rather than the
+    // legacy ~/.themes unless that is what the user picked
+    pub fn resolve(home_dir: &str, locations: &InstallLocations, xdg_data_home: Option<&str>) -> Result<Self, String> {
+        let data_home = data_home(home_dir, xdg_data_home);
+        let pick = |location: &Option<String>, default: &str| match location {
+            Some(location) => expand_location(home_dir, location),
+            None => Ok(data_home.join(default)),
+        };
+        Ok(InstallRoots {
+            themes: pick(&locations.themes, "themes")?,
+            icons: pick(&locations.icons, "icons")?,
+            fonts: pick(&locations.fonts, "fonts")?,
+        })
+    }
+
+    // Where `path`, which is inside one of these roots, ends up when the same root in `to` is used instead
+    pub fn relocate(&self, path: &Path, to: &InstallRoots) -> PathBuf {
+        [(&self.themes, &to.themes), (&self.icons, &to.icons), (&self.fonts, &to.fonts)].into_iter()
+            .find_map(|(from, to)| path.strip_prefix(from).ok().map(|rest| to.join(rest)))
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+}
+
+// Where installs for `home_dir` go right now
+pub fn install_roots(home_dir: &str) -> Result<InstallRoots, String> {
+    let xdg_data_home = std::env::var("XDG_DATA_HOME").ok();
+    InstallRoots::resolve(home_dir, &load_install_locations(home_dir)?, xdg_data_home.as_deref())
+}
+
+#[tauri::command]
+pub fn get_install_locations() -> Result<InstallLocationSettings, String> {
+    let home_dir = home_dir()?;
+    let locations = load_install_locations(&home_dir)?;
+    Ok(InstallLocationSettings { roots: install_roots(&home_dir)?, locations })
+}
+
+#[tauri::command]
+pub fn set_install_locations(locations: InstallLocations) -> Result<InstallLocationSettings, String> {
+    let home_dir = home_dir()?;
+    // Blank fields go back to the default
+    let clean = |location: Option<String>| location.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    let locations = InstallLocations {
+        themes: clean(locations.themes),
+        icons: clean(locations.icons),
+        fonts: clean(locations.fonts),
+    };
+    InstallRoots::resolve(&home_dir, &locations, None)?; // Refuse paths that cannot be used before saving them
+
+    let settings_path = settings_path(&home_dir);
+    if let Some(parent) = settings_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&locations)
+        .map_err(|e| format!("Failed to serialize install settings: {}", e))?;
+    fs::write(&settings_path, json).map_err(|e| format!("Failed to save install settings: {}", e))?;
+    Ok(InstallLocationSettings { roots: install_roots(&home_dir)?, locations })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_install_roots() {
+        let home = "/home/nord";
+
+        // Nothing picked: the XDG data directory, falling back to ~/.local/share
+        let defaults = InstallRoots::resolve(home, &InstallLocations::default(), None).unwrap();
+        assert_eq!(defaults.themes, Path::new("/home/nord/.local/share/themes"));
+        assert_eq!(defaults.fonts, Path::new("/home/nord/.local/share/fonts"));
+        let xdg = InstallRoots::resolve(home, &InstallLocations::default(), Some("/data")).unwrap();
+        assert_eq!(xdg.icons, Path::new("/data/icons"));
+        assert_eq!(InstallRoots::resolve(home, &InstallLocations::default(), Some("relative")).unwrap(), defaults);
+
+        // Picked folders win, with ~ expanded, and relative ones are refused
+        let legacy = InstallLocations { themes: Some("~/.themes".into()), icons: Some("/opt/icons".into()), fonts: None };
+        let roots = InstallRoots::resolve(home, &legacy, Some("/data")).unwrap();
+        assert_eq!(roots.themes, Path::new("/home/nord/.themes"));
+        assert_eq!(roots.icons, Path::new("/opt/icons"));
+        assert_eq!(roots.fonts, Path::new("/data/fonts"));
+        let relative = InstallLocations { themes: Some("themes".into()), ..Default::default() };
+        assert!(InstallRoots::resolve(home, &relative, None).is_err());
+    }
+
+    #[test]
+    fn private_dirs_are_new_and_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let home = std::env::temp_dir().join(format!("reskin-locations-{}", std::process::id()));
+        let home = home.to_string_lossy();
+        let first = private_dir(&home, "install").unwrap();
+        let second = private_dir(&home, "install").unwrap();
+        assert_ne!(first, second);
+        assert!(first.starts_with(cache_dir(&home)));
+        assert_eq!(fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o700);
+        let _ = fs::remove_dir_all(home.as_ref());
+    }
+}
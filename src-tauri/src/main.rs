@@ -1,17 +1,29 @@
-mod info; mod file; mod extract; mod check; mod bundle; mod apply; mod recent; mod types; mod utils; mod install; mod marketplace; mod window; // Import required modules
+mod info; mod file; mod reskin_format; mod extract; mod check; mod bundle; mod apply; mod recent; mod types; mod utils; mod install; mod marketplace; mod window; mod verify; mod signing; mod progress; mod asset_filter; mod lint; mod structure; mod preview; mod pack; mod import; mod export; mod registry; mod delta; mod uninstall; mod transaction; mod locations; mod system; mod routing; mod policy; #[cfg(test)] mod test_support; // Import required modules
 use tauri::{Manager}; // Import other necessary crates
 
 fn main() {
+	// Started by pkexec to copy or delete system-wide themes, so no window is opened
+	if let Some(code) = system::helper_main() {
+		std::process::exit(code);
+	}
 	tauri::Builder::default()
 		.plugin(tauri_plugin_opener::init())
 		.invoke_handler(tauri::generate_handler![ // Generate the handler with all available backend commands
 			info::get_app_version, info::init,
-			file::select_folder, file::select_file,
+			file::select_folder, file::select_file, file::select_archive,
 			extract::extract_theme_info, extract::extract_theme_info_from_file, extract::extract_theme,
-			bundle::bundle_theme, bundle::bundle_theme_from_directory,
+			extract::list_bundle_contents, extract::read_bundle_asset, preview::get_bundle_preview,
+			verify::verify_bundle,
+			lint::validate_manifest, lint::lint_theme,
+			signing::get_author_public_key, signing::generate_author_key, signing::list_trusted_keys, signing::trust_author_key, signing::remove_trusted_key,
+			bundle::bundle_theme, bundle::bundle_theme_from_directory, bundle::preview_bundle_assets,
+			import::import_theme_archive, import::convert_theme_archive, export::export_bundle, export::export_installed_theme,
+			delta::create_delta,
 			apply::apply_theme,
 			recent::get_recent_themes, recent::add_recent_theme,
-			install::install_theme_from_data, install::install_theme,
+			install::begin_upload, install::append_upload, install::install_theme_from_upload, install::install_theme_from_file, install::install_theme, uninstall::uninstall_theme,
+			registry::get_installed_theme, locations::get_install_locations, locations::set_install_locations,
+			policy::get_install_policy, policy::set_install_policy,
 			marketplace::fetch_marketplace_themes, marketplace::get_theme_info, marketplace::download_theme,
 			window::minimize, window::toggle_maximize, window::close,
 			utils::apply_config_file, utils::backup_config_file
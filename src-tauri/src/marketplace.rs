@@ -4,7 +4,12 @@ use std::path::Path;
 use reqwest::Client;
 use dotenv::dotenv;
 use std::env;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use crate::install::{install_bundle, InstallOptions};
+use crate::progress::Progress;
+use crate::registry::load_installed_theme;
+use crate::types::InstallScope;
 
 #[tauri::command]
 #[allow(non_snake_case)]
@@ -28,11 +33,11 @@ pub async fn get_theme_info(databaseId: &str, collectionId: &str, documentId: &s
     if response.status().is_success() {
         let theme_data = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?; // Read theme data from response, throw error on failure
         let parsed: serde_json::Value = serde_json::from_str(&theme_data).unwrap_or(json!({}));
-        return Ok(parsed); // Return success if response status is success
+        Ok(parsed) // Return success if response status is success
     } else {
         let status_code = response.status().as_u16(); // Return status code as u16
         let error_text = response.text().await.unwrap_or_default(); // Response error message
-        return Err(format!("Failed to get theme info: status {} - {}", status_code, error_text)); // Throw error with status code and error message
+        Err(format!("Failed to get theme info: status {} - {}", status_code, error_text)) // Throw error with status code and error message
     }
 }
 
@@ -58,17 +63,55 @@ pub async fn fetch_marketplace_themes(databaseId: &str, collectionId: &str) -> R
     if response.status().is_success() {
         let theme_data = response.text().await.map_err(|e| format!("Failed to read response: {}", e))?; // Read theme data from response, throw error on failure
         let parsed: serde_json::Value = serde_json::from_str(&theme_data).unwrap_or(json!({}));
-        return Ok(parsed); // Return success if response status is success
+        Ok(parsed) // Return success if response status is success
     } else {
         let status_code = response.status().as_u16(); // Return status code as u16
         let error_text = response.text().await.unwrap_or_default(); // Response error message
-        return Err(format!("Failed to fetch themes: status {} - {}", status_code, error_text)); // Throw error with status code and error message
+        Err(format!("Failed to fetch themes: status {} - {}", status_code, error_text)) // Throw error with status code and error message
     }
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct DeltaOffer { // Delta the marketplace lists for a theme
+    pub base_sha256: String, // Hex bundle hash of the version the delta upgrades
+    pub file: String, // Storage file ID of the delta bundle
+}
+
 #[tauri::command]
 #[allow(non_snake_case)]
-pub async fn download_theme(themeFileId: String, themeName: String) -> Result<(), String> { // Download a theme file from the marketplace
+pub async fn download_theme(themeFileId: String, themeName: String, deltas: Option<Vec<DeltaOffer>>) -> Result<(), String> { // Download a theme file from the marketplace
+    let home_dir = env::home_dir().ok_or("Failed to get home directory".to_string())?; // Get user's home directory
+
+    // When the installed version has a delta to the new one, fetch and apply just that
+    let installed_hash = load_installed_theme(&home_dir.to_string_lossy(), &themeName)
+        .ok()
+        .flatten()
+        .and_then(|installed| installed.bundle_sha256);
+    let delta = deltas.unwrap_or_default()
+        .into_iter()
+        .find(|delta| Some(&delta.base_sha256) == installed_hash.as_ref());
+    if let Some(delta) = delta {
+        let bytes = download_file(&delta.file).await?;
+        let delta_path = env::temp_dir().join(format!("reskin_delta_{}.reskin", std::process::id()));
+        fs::write(&delta_path, &bytes).map_err(|e| format!("Failed to save theme file: {}", e))?;
+        let result = install_bundle(&delta_path.to_string_lossy(), &InstallOptions { scope: Some(InstallScope::User), ..Default::default() }, &mut Progress::none());
+        let _ = fs::remove_file(&delta_path);
+        return result.map(|_| ());
+    }
+
+    let bytes = download_file(&themeFileId).await?;
+    let reskin_dir = Path::new(&home_dir).join(".reskin-themes"); // Reskin themes directory
+    let theme_path = reskin_dir.join(format!("{}.reskin", themeName)); // Theme file destination
+
+    fs::create_dir_all(&reskin_dir).map_err(|e| format!("Failed to create directory: {}", e))?; // Create themes directory and all parent directories
+
+    fs::write(&theme_path, &bytes).map_err(|e| format!("Failed to save theme file: {}", e))?; // Write theme file to the destination path
+
+    Ok(()) // Return success
+}
+
+#[allow(non_snake_case)]
+async fn download_file(fileId: &str) -> Result<Vec<u8>, String> { // Download a file from the themes storage bucket
     dotenv().ok();
     // Import Appwrite credientials from .env
     let endpoint = env::var("VITE_APPWRITE_ENDPOINT").map_err(|_| "APPWRITE_ENDPOINT not set".to_string())?; // Get Appwrite endpoint 
@@ -76,7 +119,7 @@ pub async fn download_theme(themeFileId: String, themeName: String) -> Result<()
     let apiKey = env::var("VITE_APPWRITE_API_KEY").map_err(|_| "APPWRITE_API_KEY not set".to_string())?; // Get Appwrite API key
 
     let client = Client::new(); // Create new Appwrite client object
-    let url = format!("{}/storage/buckets/{}/files/{}/download", endpoint, "themes", themeFileId); // Theme files storage bucket
+    let url = format!("{}/storage/buckets/{}/files/{}/download", endpoint, "themes", fileId); // Theme files storage bucket
     let response = client // Response using the client object
         .get(&url) // Get the specified URL using the client object
         .header("X-Appwrite-Project", projectId) // Add Appwrite project ID header to response
@@ -87,19 +130,10 @@ pub async fn download_theme(themeFileId: String, themeName: String) -> Result<()
         .map_err(|e| format!("Failed to download theme: {}", e))?; // Throw error on failure
     if response.status().is_success() {
         let bytes = response.bytes().await.map_err(|e| format!("Failed to read theme bytes: {}", e))?; // Read theme file bytes from the response
-        
-        let home_dir = env::home_dir().ok_or("Failed to get home directory".to_string())?; // Get user's home directory
-        let reskin_dir = Path::new(&home_dir).join(".reskin-themes"); // Reskin themes directory
-        let theme_path = reskin_dir.join(format!("{}.reskin", themeName)); // Theme file destination
-
-        fs::create_dir_all(&reskin_dir).map_err(|e| format!("Failed to create directory: {}", e))?; // Create themes directory and all parent directories
-
-        fs::write(&theme_path, &bytes).map_err(|e| format!("Failed to save theme file: {}", e))?; // Write theme file to the destination path
-        
-        Ok(()) // Return success
+        Ok(bytes.to_vec()) // Return success
     } else {
         let status_code = response.status().as_u16(); // Return status code as u16
         let error_text = response.text().await.unwrap_or_default(); // Response error message
         Err(format!("Failed to download theme: status {} - {}", status_code, error_text)) // Throw error with status code and error message
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,142 @@
+// Import necessary crates
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::extract::{validate_entry_path, validate_theme_name};
+use crate::locations::config_dir;
+use crate::transaction::InstallTransaction;
+use crate::types::RicePack;
+
+pub const PACK_FILE: &str = "reskin-pack.json"; // Pack definition the bundler reads from the theme root
+
+// Read the rice pack definition from a theme folder, if it has one
+pub fn read_pack_file(theme_root: &Path) -> Result<Option<RicePack>, String> {
+    let pack_path = theme_root.join(PACK_FILE);
+    if !pack_path.is_file() {
+        return Ok(None);
+    }
+    let bytes = fs::read(&pack_path)
+        .map_err(|e| format!("Failed to read {}: {}", PACK_FILE, e))?;
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse {}: {}", PACK_FILE, e))
+}
+
+// Folders packs may never write to, anywhere in a target: they start programs at login or hold keys and
+// Reskin's own trust settings
+const PROTECTED_CONFIGS: &[&str] = &["autostart", "systemd", "reskin", ".ssh", ".gnupg"];
+
+// Resolve a config target such as "~/.config/kitty/kitty.conf". Packs may only write files inside an
+// application's folder under ~/.config, never dotfiles in the home directory itself.
+pub fn config_target(home_dir: &str, target: &str) -> Result<PathBuf, String> {
+    let relative = target.strip_prefix("~/.config/")
+        .ok_or_else(|| format!("Config target '{}' must be inside ~/.config/<app>/", target))?;
+    let relative = validate_entry_path(relative)
+        .map_err(|_| format!("Config target '{}' must stay inside ~/.config", target))?;
+    if relative.components().count() < 2 {
+        return Err(format!("Config target '{}' must be inside ~/.config/<app>/", target));
+    }
+    if relative.components().any(|component| PROTECTED_CONFIGS.iter().any(|name| component.as_os_str() == *name)) {
+        return Err(format!("Config target '{}' is in a folder packs may not change", target));
+    }
+    Ok(Path::new(home_dir).join(".config").join(relative))
+}
+
+// Copy a config file to its target as part of `transaction`, keeping what it replaces as <target>.bak. A
+// symlink at the target is backed up as the link itself, never followed.
+pub fn install_config(source: &Path, target: &str, home_dir: &str, transaction: &mut InstallTransaction) -> Result<PathBuf, String> {
+    let dest = config_target(home_dir, target)?;
+    if !source.is_file() {
+        return Err(format!("Config file {} is not in the theme", source.display()));
+    }
+    match fs::symlink_metadata(&dest) {
+        Ok(meta) if meta.is_dir() => return Err(format!("Config target {} is a folder", dest.display())),
+        Ok(_) => {
+            let mut backup = dest.clone().into_os_string();
+            backup.push(".bak");
+            transaction.replace_file(Path::new(&backup), &dest)
+                .map_err(|e| format!("Failed to back up {}: {}", dest.display(), e))?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("Failed to read {}: {}", dest.display(), e)),
+    }
+    transaction.replace_file(&dest, source)?;
+    transaction.configs.push(dest.to_string_lossy().into_owned());
+    Ok(dest)
+}
+
+fn pack_record_path(home_dir: &str, pack_name: &str) -> Result<PathBuf, String> {
+    validate_theme_name(pack_name).map_err(|e| e.to_string())?;
+    Ok(config_dir(home_dir).join("packs").join(format!("{}.json", pack_name)))
+}
+
+// Remember which themes an installed pack is made of, so applying it later can switch all of them
+pub fn save_installed_pack(home_dir: &str, pack_name: &str, pack: &RicePack) -> Result<(), String> {
+    let record_path = pack_record_path(home_dir, pack_name)?;
+    if let Some(parent) = record_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create packs directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(pack)
+        .map_err(|e| format!("Failed to serialize pack: {}", e))?;
+    fs::write(&record_path, json).map_err(|e| format!("Failed to save pack: {}", e))
+}
+
+// Look up an installed pack by name; None when `pack_name` is an ordinary theme
+pub fn load_installed_pack(home_dir: &str, pack_name: &str) -> Result<Option<RicePack>, String> {
+    let Ok(record_path) = pack_record_path(home_dir, pack_name) else {
+        return Ok(None);
+    };
+    if !record_path.is_file() {
+        return Ok(None);
+    }
+    let bytes = fs::read(&record_path).map_err(|e| format!("Failed to read pack: {}", e))?;
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse pack: {}", e))
+}
+
+// Drop the record of a pack, when a plain theme is installed under the same name
+pub fn forget_installed_pack(home_dir: &str, pack_name: &str) {
+    if let Ok(record_path) = pack_record_path(home_dir, pack_name) {
+        let _ = fs::remove_file(record_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_targets_stay_in_app_config_folders() {
+        assert_eq!(config_target("/home/ann", "~/.config/kitty/kitty.conf").unwrap(), Path::new("/home/ann/.config/kitty/kitty.conf"));
+        assert_eq!(config_target("/home/ann", "~/.config/nvim/lua/theme.lua").unwrap(), Path::new("/home/ann/.config/nvim/lua/theme.lua"));
+        for target in [
+            "/etc/profile", "~/../bob/.bashrc", "~/", ".config/foo", "~/.config/../../etc", "~/.config/kitty/../../.bashrc",
+            "~/.bashrc", "~/.ssh/authorized_keys", "~/.gnupg/gpg.conf", "~/.config/mimeapps.list", "~/.config/kitty",
+            "~/.config/autostart/evil.desktop", "~/.config/systemd/user/evil.service", "~/.config/reskin/policy.json",
+            "~/.config/app/.ssh/config",
+        ] {
+            assert!(config_target("/home/ann", target).is_err(), "{} was accepted", target);
+        }
+    }
+
+    #[test]
+    fn backs_up_a_symlinked_config_as_a_link() {
+        let root = std::env::temp_dir().join(format!("reskin-pack-link-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let home = root.join("home");
+        fs::create_dir_all(home.join(".config/kitty")).unwrap();
+        fs::write(root.join("dotfiles-kitty.conf"), "background #000000\n").unwrap();
+        std::os::unix::fs::symlink(root.join("dotfiles-kitty.conf"), home.join(".config/kitty/kitty.conf")).unwrap();
+        fs::write(root.join("new.conf"), "background #2e3440\n").unwrap();
+
+        let mut transaction = InstallTransaction::default();
+        install_config(&root.join("new.conf"), "~/.config/kitty/kitty.conf", &home.to_string_lossy(), &mut transaction).unwrap();
+        transaction.commit();
+
+        // The file the link pointed at is untouched, and the backup is the link itself
+        assert_eq!(fs::read_to_string(root.join("dotfiles-kitty.conf")).unwrap(), "background #000000\n");
+        assert_eq!(fs::read_link(home.join(".config/kitty/kitty.conf.bak")).unwrap(), root.join("dotfiles-kitty.conf"));
+        assert_eq!(fs::read_to_string(home.join(".config/kitty/kitty.conf")).unwrap(), "background #2e3440\n");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
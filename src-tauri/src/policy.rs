@@ -0,0 +1,65 @@
+// Import necessary crates
+use std::fs;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use crate::locations::{config_dir, home_dir};
+use crate::types::InstallScope;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct InstallPolicy { // Rules from Settings that every install follows, whichever way the bundle arrives
+    #[serde(default)]
+    pub require_signed: bool, // Refuse bundles that are not signed by a trusted key
+    #[serde(default)]
+    pub scope: InstallScope, // Who themes are installed for unless a caller asks for something else
+}
+
+fn policy_path(home_dir: &str) -> PathBuf {
+    config_dir(home_dir).join("policy.json")
+}
+
+pub fn load_install_policy(home_dir: &str) -> Result<InstallPolicy, String> {
+    let policy_path = policy_path(home_dir);
+    if !policy_path.is_file() {
+        return Ok(InstallPolicy::default());
+    }
+    let bytes = fs::read(&policy_path).map_err(|e| format!("Failed to read install policy: {}", e))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse install policy: {}", e))
+}
+
+fn save_install_policy(home_dir: &str, policy: &InstallPolicy) -> Result<(), String> {
+    let policy_path = policy_path(home_dir);
+    if let Some(parent) = policy_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(policy)
+        .map_err(|e| format!("Failed to serialize install policy: {}", e))?;
+    fs::write(&policy_path, json).map_err(|e| format!("Failed to save install policy: {}", e))
+}
+
+#[tauri::command]
+pub fn get_install_policy() -> Result<InstallPolicy, String> {
+    load_install_policy(&home_dir()?)
+}
+
+#[tauri::command]
+pub fn set_install_policy(policy: InstallPolicy) -> Result<InstallPolicy, String> {
+    save_install_policy(&home_dir()?, &policy)?;
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_defaults_until_saved() {
+        let home = std::env::temp_dir().join(format!("reskin-policy-{}", std::process::id()));
+        let home = home.to_string_lossy();
+        assert_eq!(load_install_policy(&home).unwrap(), InstallPolicy { require_signed: false, scope: InstallScope::User });
+
+        let strict = InstallPolicy { require_signed: true, scope: InstallScope::System };
+        save_install_policy(&home, &strict).unwrap();
+        assert_eq!(load_install_policy(&home).unwrap(), strict);
+        let _ = fs::remove_dir_all(home.as_ref());
+    }
+}
@@ -0,0 +1,97 @@
+// Import necessary crates
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageFormat;
+use crate::reskin_format::{BundleReader, EntryKind};
+
+pub const PREVIEW_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"]; // Image formats previews can use
+pub const DEFAULT_PREVIEWS: &[&str] = &["preview.png", "preview.jpg", "preview.jpeg", "preview.webp"]; // Suggested by the linter when the manifest names no preview
+
+const THUMBNAIL_SIZE: u32 = 480; // Default longest side of a thumbnail, in pixels
+const MAX_PREVIEW_SIZE: u64 = 16 * 1024 * 1024; // Larger preview images are not decoded
+
+#[tauri::command]
+pub fn get_bundle_preview(bundle_path: String, max_size: Option<u32>) -> Result<Option<String>, String> {
+    // Decode the preview image straight from the bundle and scale it down to a PNG data URL.
+    // Returns None for bundles without a preview.
+    let file = File::open(&bundle_path)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut reader = BundleReader::new(BufReader::new(file))?;
+    let Some(preview_path) = reader.manifest().preview_path().map(str::to_string) else {
+        return Ok(None);
+    };
+
+    let entry = reader.entries()?
+        .into_iter()
+        .find(|e| e.path == preview_path && e.kind == EntryKind::File)
+        .ok_or_else(|| format!("Preview image '{}' is not in the bundle", preview_path))?;
+    if entry.size > MAX_PREVIEW_SIZE {
+        return Err(format!("Preview image '{}' is too large ({} bytes)", preview_path, entry.size));
+    }
+    let data = reader.read_entry(&entry)?;
+
+    let thumbnail = thumbnail_png(&data, max_size.unwrap_or(THUMBNAIL_SIZE))?;
+    Ok(Some(format!("data:image/png;base64,{}", STANDARD.encode(thumbnail))))
+}
+
+// Scale an image down to fit in a `max_size` square, keeping its aspect ratio, and encode it as PNG
+pub fn thumbnail_png(data: &[u8], max_size: u32) -> Result<Vec<u8>, String> {
+    let image = image::load_from_memory(data)
+        .map_err(|e| format!("Failed to decode preview image: {}", e))?;
+    let max_size = max_size.max(1);
+    let image = if image.width() > max_size || image.height() > max_size {
+        image.thumbnail(max_size, max_size)
+    } else {
+        image // Small images are never scaled up
+    };
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode thumbnail: {}", e))?;
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reskin_format::Codec;
+    use crate::test_support::{bundle_bytes, scratch_dir, test_manifest};
+    use crate::types::ThemeManifest;
+    use image::{ImageBuffer, Rgb};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgb([46u8, 52, 64]));
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), ImageFormat::Png).unwrap();
+        data
+    }
+
+    fn bundle_with_preview(preview: Option<&str>, image: &[u8]) -> String {
+        let manifest = ThemeManifest { preview_image: preview.map(str::to_string), ..test_manifest("Preview") };
+        let path = scratch_dir(&format!("preview-{}", preview.is_some())).join("Preview.reskin");
+        std::fs::write(&path, bundle_bytes(&manifest, &[("shots/desktop.png", image, Codec::Store)])).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn returns_a_scaled_thumbnail() {
+        let path = bundle_with_preview(Some("shots/desktop.png"), &png(1000, 500));
+        let data_url = get_bundle_preview(path.clone(), Some(100)).unwrap().unwrap();
+        let encoded = data_url.strip_prefix("data:image/png;base64,").unwrap();
+        let thumbnail = image::load_from_memory(&STANDARD.decode(encoded).unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (100, 50));
+        let _ = std::fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap());
+    }
+
+    #[test]
+    fn bundles_without_a_preview_have_none() {
+        let path = bundle_with_preview(None, &png(10, 10));
+        assert_eq!(get_bundle_preview(path.clone(), None).unwrap(), None);
+        let _ = std::fs::remove_dir_all(std::path::Path::new(&path).parent().unwrap());
+
+        // Small images are not scaled up
+        let thumbnail = image::load_from_memory(&thumbnail_png(&png(40, 30), 480).unwrap()).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (40, 30));
+    }
+}
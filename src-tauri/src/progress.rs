@@ -0,0 +1,81 @@
+// Import necessary crates
+use std::io::{self, Read};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+const REPORT_EVERY: u64 = 1024 * 1024; // Emit at most one event per MiB so the frontend is not flooded
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ProgressEvent { // Payload of the bundle-progress and install-progress events
+    pub stage: &'static str, // What is being done, e.g. "bundling" or "extracting"
+    pub bytes_done: u64, // Bytes processed so far in this stage
+    pub bytes_total: u64, // Bytes expected in this stage, 0 when unknown
+}
+
+pub struct Progress<'a> { // Byte counter that reports to a callback as work advances
+    stage: &'static str,
+    bytes_done: u64,
+    bytes_total: u64,
+    last_reported: u64, // bytes_done at the last report
+    report: Box<dyn FnMut(ProgressEvent) + 'a>,
+}
+
+impl<'a> Progress<'a> {
+    pub fn new(report: impl FnMut(ProgressEvent) + 'a) -> Self {
+        Self { stage: "", bytes_done: 0, bytes_total: 0, last_reported: 0, report: Box::new(report) }
+    }
+
+    pub fn none() -> Self { // Progress that goes nowhere, for callers without a window
+        Self::new(|_| {})
+    }
+
+    // Start a new stage and report it straight away
+    pub fn start(&mut self, stage: &'static str, bytes_total: u64) {
+        self.stage = stage;
+        self.bytes_done = 0;
+        self.bytes_total = bytes_total;
+        self.report_now();
+    }
+
+    pub fn advance(&mut self, bytes: u64) { // Count processed bytes, reporting once enough have piled up
+        self.bytes_done += bytes;
+        if self.bytes_done - self.last_reported >= REPORT_EVERY {
+            self.report_now();
+        }
+    }
+
+    pub fn finish(&mut self) { // Report the final count of the current stage
+        self.report_now();
+    }
+
+    fn report_now(&mut self) {
+        self.last_reported = self.bytes_done;
+        (self.report)(ProgressEvent { stage: self.stage, bytes_done: self.bytes_done, bytes_total: self.bytes_total });
+    }
+}
+
+// Progress that is sent to the frontend as a Tauri event
+pub fn emit_to(app: AppHandle, event: &'static str) -> Progress<'static> {
+    Progress::new(move |payload| {
+        let _ = app.emit(event, payload); // A closed window must not abort the work
+    })
+}
+
+pub struct ProgressReader<'p, 'a, R> { // Reader that counts the bytes passing through it
+    inner: R,
+    progress: &'p mut Progress<'a>,
+}
+
+impl<'p, 'a, R> ProgressReader<'p, 'a, R> {
+    pub fn new(inner: R, progress: &'p mut Progress<'a>) -> Self {
+        Self { inner, progress }
+    }
+}
+
+impl<R: Read> Read for ProgressReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.advance(n as u64);
+        Ok(n)
+    }
+}
@@ -1,12 +1,12 @@
 // Import necessary crates
 use std::fs;
 use std::time::SystemTime;
+use crate::locations::home_dir;
 use crate::types::RecentTheme;
 
 #[tauri::command]
 pub fn get_recent_themes() -> Result<Vec<RecentTheme>, String> { // Get recently installed themes
-    let home_dir = std::env::var("HOME") // Get user's home directory
-        .map_err(|_| "Failed to get HOME directory".to_string())?; // Throw error on failure
+    let home_dir = home_dir()?; // Get user's home directory
     
     let recent_file = format!("{}/.config/reskin/recent.json", home_dir); // File that stores recently installed themes
     
@@ -25,8 +25,7 @@ pub fn get_recent_themes() -> Result<Vec<RecentTheme>, String> { // Get recently
 
 #[tauri::command]
 pub fn add_recent_theme(theme_name: String, author: String, description: String) -> Result<(), String> { // Add a theme to recent.json
-    let home_dir = std::env::var("HOME") // Get user's home directory
-        .map_err(|_| "Failed to get HOME directory".to_string())?; // Throw error on failure
+    let home_dir = home_dir()?; // Get user's home directory
     
     let config_dir = format!("{}/.config/reskin", home_dir); // Reskin config directory
     fs::create_dir_all(&config_dir) // Create config directory and all necessary parent folders
@@ -69,3 +68,22 @@ pub fn add_recent_theme(theme_name: String, author: String, description: String)
     
     Ok(()) // Return success
 }
+
+// Drop a theme from recent.json, e.g. after it was uninstalled
+pub fn remove_recent_theme(theme_name: &str) -> Result<(), String> {
+    let home_dir = home_dir()?; // Get user's home directory
+
+    let recent_file = format!("{}/.config/reskin/recent.json", home_dir); // File that stores recently installed themes
+    if !std::path::Path::new(&recent_file).exists() {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&recent_file).unwrap_or_default();
+    let mut themes: Vec<RecentTheme> = serde_json::from_str(&content).unwrap_or_else(|_| Vec::new());
+    themes.retain(|t| t.name != theme_name);
+
+    let json = serde_json::to_string_pretty(&themes)
+        .map_err(|e| format!("Failed to serialize themes: {}", e))?; // Throw error on failure
+    fs::write(&recent_file, json) // Write the remaining themes back to recent.json
+        .map_err(|e| format!("Failed to write recent themes: {}", e))
+}
@@ -0,0 +1,92 @@
+// Import necessary crates
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::extract::validate_theme_name;
+use crate::locations::config_dir;
+use crate::reskin_format::to_hex;
+use crate::verify::open_bundle;
+use crate::types::{InstallScope, InstalledTheme, ThemeManifest};
+
+fn record_path(home_dir: &str, theme_name: &str) -> Result<PathBuf, String> {
+    validate_theme_name(theme_name).map_err(|e| e.to_string())?;
+    Ok(config_dir(home_dir).join("installed").join(format!("{}.json", theme_name)))
+}
+
+// Hex hash recorded in a bundle's trailer, which identifies the exact version that was installed
+pub fn bundle_hash(bundle: &File) -> Result<Option<String>, String> {
+    Ok(open_bundle(bundle)?.bundle_sha256()?.map(|hash| to_hex(&hash)))
+}
+
+// A folder and everything below it, without following symlinks
+pub fn tree_paths(root: &Path) -> Vec<String> {
+    let mut paths = vec![root.to_string_lossy().into_owned()];
+    if fs::symlink_metadata(root).is_ok_and(|meta| meta.is_dir()) {
+        for entry in fs::read_dir(root).into_iter().flatten().flatten() {
+            paths.extend(tree_paths(&entry.path()));
+        }
+    }
+    paths
+}
+
+pub fn save_installed_theme(home_dir: &str, theme_name: &str, record: &InstalledTheme) -> Result<(), String> {
+    let record_path = record_path(home_dir, theme_name)?;
+    if let Some(parent) = record_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create registry directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(record)
+        .map_err(|e| format!("Failed to serialize install record: {}", e))?;
+    fs::write(&record_path, json).map_err(|e| format!("Failed to save install record: {}", e))
+}
+
+// Record what an install wrote. The bundle hash is filled in by set_bundle_hash once the whole bundle is in.
+pub fn record_install(home_dir: &str, theme_name: &str, manifest: &ThemeManifest, scope: InstallScope, mut files: Vec<String>, configs: Vec<String>) -> Result<(), String> {
+    files.sort();
+    files.dedup();
+    let installed_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let record = InstalledTheme {
+        manifest: ThemeManifest { delta: None, ..manifest.clone() },
+        bundle_sha256: None,
+        installed_at,
+        files,
+        configs,
+        scope,
+    };
+    save_installed_theme(home_dir, theme_name, &record)
+}
+
+// Note which bundle, and so which version, an installed theme now matches
+pub fn set_bundle_hash(home_dir: &str, manifest: &ThemeManifest, bundle_sha256: Option<String>) -> Result<(), String> {
+    let mut record = load_installed_theme(home_dir, &manifest.name)?
+        .ok_or_else(|| format!("Theme '{}' has no install record", manifest.name))?;
+    record.manifest = ThemeManifest { delta: None, ..manifest.clone() };
+    record.bundle_sha256 = bundle_sha256;
+    save_installed_theme(home_dir, &manifest.name, &record)
+}
+
+#[tauri::command]
+pub fn get_installed_theme(name: String) -> Result<Option<ThemeManifest>, String> {
+    // Manifest of the installed version, wherever the theme was installed to
+    let home_dir = crate::locations::home_dir()?;
+    Ok(load_installed_theme(&home_dir, &name)?.map(|record| record.manifest))
+}
+
+// Look up how a theme was installed; None for themes Reskin has no record of
+pub fn load_installed_theme(home_dir: &str, theme_name: &str) -> Result<Option<InstalledTheme>, String> {
+    let record_path = record_path(home_dir, theme_name)?;
+    if !record_path.is_file() {
+        return Ok(None);
+    }
+    let bytes = fs::read(&record_path).map_err(|e| format!("Failed to read install record: {}", e))?;
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse install record: {}", e))
+}
+
+pub fn forget_installed_theme(home_dir: &str, theme_name: &str) {
+    if let Ok(record_path) = record_path(home_dir, theme_name) {
+        let _ = fs::remove_file(record_path);
+    }
+}
@@ -0,0 +1,1331 @@
+// Import necessary crates
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use serde::{Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use crate::types::{ThemeManifest, MANIFEST_VERSION};
+
+// Layout of a .reskin container (all integers little-endian):
+//
+//   "RSKN"                 magic
+//   version: u8            container format version
+//   manifest_len: u64      length of the manifest JSON
+//   manifest: [u8]         manifest JSON
+//   entries:
+//     path_len: u32        length of the asset path
+//     path: [u8]           UTF-8 path relative to the theme root
+//     kind: u8             0 = file, 1 = directory, 2 = symlink
+//     mode: u32            Unix permission bits
+//     mtime: i64           modification time in seconds since the Unix epoch
+//     codec: u8            compression codec of the stored data
+//     size: u64            uncompressed length of the asset
+//     chunks:
+//       chunk_len: u32     length of the next piece of stored data, zero ends the asset
+//       chunk: [u8]        stored asset data
+//     sha256: [u8; 32]     SHA-256 of the uncompressed asset, after the data
+//     Directories have no data, and a symlink's data is its target path.
+//   end marker: u32        zero path length, ends the entry list
+//   table of contents:
+//     entry_count: u32
+//     per entry: path_len u32, path, kind u8, mode u32, mtime i64, codec u8, size u64,
+//                data_offset u64, data_len u64, sha256 [u8; 32]
+//     data_offset and data_len describe the chunks, terminator included.
+//   trailer:
+//     toc_offset: u64      absolute offset of the table of contents
+//     bundle_sha256: [u8; 32]  SHA-256 of every byte before it, toc_offset included
+//     signature_kind: u8   0 = unsigned, 1 = Ed25519
+//     public_key: [u8; 32] signer's Ed25519 public key, zero if unsigned
+//     signature: [u8; 64]  Ed25519 signature over SIGNATURE_CONTEXT + bundle_sha256
+//     "RSKT"               trailer magic
+//
+// Bundles written before the version byte existed (version 0) have no version byte and
+// store each entry as path_len u32, path, data_len u64 and the raw data, running until
+// EOF with no table of contents, so they are indexed by scanning. Chunked entries let
+// writers stream assets of any size without knowing the stored length up front.
+
+pub const MAGIC: &[u8; 4] = b"RSKN"; // Magic number at the start of every .reskin file
+pub const TOC_MAGIC: &[u8; 4] = b"RSKT"; // Magic number at the very end of bundles with a table of contents
+pub const FORMAT_VERSION: u8 = 1; // Container version written by this build
+pub const LEGACY_VERSION: u8 = 0; // Version reported for bundles without a version byte
+const TRAILER_LEN: u64 = 141; // toc_offset, bundle hash, signature block and trailer magic
+const SIGNATURE_CONTEXT: &[u8] = b"reskin-bundle-signature-v1:"; // Prefix for signed messages, so signatures cannot be reused elsewhere
+
+pub const MAX_MANIFEST_LEN: u64 = 16 * 1024 * 1024; // Upper bound for the manifest JSON
+pub const MAX_PATH_LEN: usize = 4096; // Upper bound for an asset path
+pub const MAX_ENTRIES: u32 = 1_000_000; // Upper bound for the number of table of contents entries
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec { // How an asset is stored in the bundle
+    Store, // Raw bytes
+    Zstd, // Zstandard-compressed bytes
+}
+
+impl Codec {
+    fn to_byte(self) -> u8 {
+        match self {
+            Codec::Store => 0,
+            Codec::Zstd => 1,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, String> {
+        match byte {
+            0 => Ok(Codec::Store),
+            1 => Ok(Codec::Zstd),
+            other => Err(format!("Invalid .reskin file: unknown codec {}", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind { // What an entry recreates on disk
+    File, // Regular file with the entry data as contents
+    Directory, // Directory, possibly empty
+    Symlink, // Symbolic link with the entry data as target
+}
+
+impl EntryKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            EntryKind::File => 0,
+            EntryKind::Directory => 1,
+            EntryKind::Symlink => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, String> {
+        match byte {
+            0 => Ok(EntryKind::File),
+            1 => Ok(EntryKind::Directory),
+            2 => Ok(EntryKind::Symlink),
+            other => Err(format!("Invalid .reskin file: unknown entry kind {}", other)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryMetadata { // File system metadata recorded for an entry
+    pub kind: EntryKind,
+    pub mode: u32, // Unix permission bits
+    pub mtime: i64, // Modification time in seconds since the Unix epoch
+}
+
+impl Default for EntryMetadata {
+    fn default() -> Self {
+        Self { kind: EntryKind::File, mode: 0o644, mtime: 0 }
+    }
+}
+
+const ZSTD_LEVEL: i32 = 19; // Bundles are written once and downloaded many times, so favour size
+const CHUNK_SIZE: usize = 64 * 1024; // Largest chunk written, which bounds the writer's memory use
+const MAX_CHUNK_LEN: u32 = 16 * 1024 * 1024; // Largest chunk accepted when reading
+const SAMPLE_SIZE: usize = 128 * 1024; // Leading bytes compressed on trial to decide whether compression helps
+
+pub type Sha256Hash = [u8; 32];
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct BundleEntry { // Table of contents entry for one asset
+    pub path: String, // Path relative to the theme root
+    pub kind: EntryKind, // File, directory or symlink; always a file in legacy bundles
+    pub mode: Option<u32>, // Unix permission bits, absent in legacy bundles
+    pub mtime: Option<i64>, // Modification time, absent in legacy bundles
+    pub codec: Codec, // How the asset data is stored
+    pub size: u64, // Uncompressed length of the asset
+    pub offset: u64, // Absolute offset of the stored data in the bundle
+    pub stored_size: u64, // Length of the stored data
+    #[serde(serialize_with = "serialize_hash")]
+    pub sha256: Option<Sha256Hash>, // Checksum of the uncompressed asset, absent in legacy bundles
+}
+
+pub struct EntryHeader { // Entry header as read from the entry list
+    pub path: String, // Path relative to the theme root
+    pub kind: EntryKind, // File, directory or symlink; always a file in legacy bundles
+    pub mode: Option<u32>, // Unix permission bits, absent in legacy bundles
+    pub mtime: Option<i64>, // Modification time, absent in legacy bundles
+    pub codec: Codec, // How the asset data is stored
+    pub size: u64, // Uncompressed length of the asset
+    pub stored_size: Option<u64>, // Length of the stored data, unknown until the chunks are read
+    pub sha256: Option<Sha256Hash>, // Checksum of the uncompressed asset, known here only from the table of contents
+}
+
+#[derive(Clone, Debug)]
+pub struct BundleSignature { // Signature block read from a bundle trailer
+    pub public_key: [u8; 32], // Signer's Ed25519 public key
+    pub signature: [u8; 64], // Ed25519 signature over the bundle hash
+    pub bundle_sha256: Sha256Hash, // Bundle hash the signature covers
+}
+
+impl BundleSignature {
+    // Check the signature against the recorded bundle hash; the hash itself is checked by verify()
+    pub fn is_valid(&self) -> bool {
+        let Ok(key) = VerifyingKey::from_bytes(&self.public_key) else {
+            return false;
+        };
+        key.verify(&signed_message(&self.bundle_sha256), &Signature::from_bytes(&self.signature)).is_ok()
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct VerifyProblem { // One thing wrong with a bundle
+    pub entry: Option<String>, // Damaged asset, or None for the container itself
+    pub message: String, // What is wrong
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct VerifyReport { // Result of checking a bundle against its checksums
+    pub valid: bool, // True when no problems were found
+    pub has_checksums: bool, // False for legacy bundles, which can only be checked structurally
+    pub bundle_sha256: Option<String>, // Hex SHA-256 of the bundle as recorded in the trailer
+    pub entries_checked: usize, // Number of assets that were read back
+    pub problems: Vec<VerifyProblem>, // Everything that failed, in bundle order
+}
+
+pub struct BundleWriter<W: Write> { // Writes a .reskin container to any output
+    inner: W,
+    position: u64, // Bytes written so far, used for table of contents offsets
+    entries: Vec<BundleEntry>, // Entries written so far
+    hasher: Sha256, // Running hash of everything written so far
+    signing_key: Option<SigningKey>, // Key to sign the bundle hash with, if any
+}
+
+impl<W: Write> BundleWriter<W> {
+    // Write the header and manifest, returning a writer ready to accept assets
+    pub fn new(inner: W, manifest: &ThemeManifest) -> Result<Self, String> {
+        // Manifests are always written in the current schema, whatever the frontend sent
+        let manifest = ThemeManifest { manifest_version: MANIFEST_VERSION, ..manifest.clone() };
+        let manifest_json = serde_json::to_vec(&manifest)
+            .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+        let mut writer = Self { inner, position: 0, entries: Vec::new(), hasher: Sha256::new(), signing_key: None };
+        writer.write(MAGIC).map_err(|e| format!("Failed to write magic: {}", e))?;
+        writer.write(&[FORMAT_VERSION]).map_err(|e| format!("Failed to write format version: {}", e))?;
+        writer.write(&(manifest_json.len() as u64).to_le_bytes())
+            .map_err(|e| format!("Failed to write manifest length: {}", e))?;
+        writer.write(&manifest_json).map_err(|e| format!("Failed to write manifest data: {}", e))?;
+
+        Ok(writer)
+    }
+
+    // Append one entry of `size` bytes, streaming it from `data` and compressing it with the requested
+    // codec. Only a bounded amount of the asset is held in memory at any time. Directories take no data
+    // and symlinks take their target path.
+    pub fn add_asset<R: Read>(&mut self, path: &str, metadata: EntryMetadata, size: u64, mut data: R, codec: Codec) -> Result<(), String> {
+        let mut asset = self.start_asset(path, metadata, size, codec)?;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        loop {
+            let n = match data.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(format!("Failed to read asset {}: {}", path, e)),
+            };
+            asset.write_all(&buf[..n]).map_err(|e| format!("Failed to write asset {}: {}", path, e))?;
+        }
+        asset.finish()
+    }
+
+    // Begin an entry of `size` bytes whose data is then written to the returned AssetWriter, for sources
+    // that push data out rather than being read from, such as another bundle
+    pub fn start_asset(&mut self, path: &str, metadata: EntryMetadata, size: u64, codec: Codec) -> Result<AssetWriter<'_, W>, String> {
+        let path_bytes = path.as_bytes();
+        if path_bytes.is_empty() || path_bytes.len() > MAX_PATH_LEN {
+            return Err(format!("Invalid asset path length for '{}'", path));
+        }
+        if metadata.kind == EntryKind::Directory && size != 0 {
+            return Err(format!("Directory entry {} cannot have data", path));
+        }
+        if metadata.kind == EntryKind::Symlink && (size == 0 || size > MAX_PATH_LEN as u64) {
+            return Err(format!("Invalid symlink target length for '{}'", path));
+        }
+
+        Ok(AssetWriter {
+            path: path.to_string(),
+            metadata,
+            size,
+            codec,
+            sample: Vec::new(),
+            hasher: Sha256::new(),
+            count: 0,
+            data: AssetData::Sampling(self),
+        })
+    }
+
+    // Sign the bundle with the author's key when it is finished
+    pub fn sign_with(&mut self, key: SigningKey) {
+        self.signing_key = Some(key);
+    }
+
+    // Write the end marker, table of contents and trailer, then hand back the underlying output
+    pub fn finish(mut self) -> Result<W, String> {
+        self.write(&0u32.to_le_bytes()).map_err(|e| format!("Failed to write end marker: {}", e))?;
+
+        let toc_offset = self.position;
+        let mut toc = Vec::new();
+        toc.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for entry in &self.entries {
+            toc.extend_from_slice(&(entry.path.len() as u32).to_le_bytes());
+            toc.extend_from_slice(entry.path.as_bytes());
+            toc.push(entry.kind.to_byte());
+            toc.extend_from_slice(&entry.mode.unwrap_or_default().to_le_bytes());
+            toc.extend_from_slice(&entry.mtime.unwrap_or_default().to_le_bytes());
+            toc.push(entry.codec.to_byte());
+            toc.extend_from_slice(&entry.size.to_le_bytes());
+            toc.extend_from_slice(&entry.offset.to_le_bytes());
+            toc.extend_from_slice(&entry.stored_size.to_le_bytes());
+            toc.extend_from_slice(&entry.sha256.unwrap_or_default());
+        }
+        self.write(&toc).map_err(|e| format!("Failed to write table of contents: {}", e))?;
+        self.write(&toc_offset.to_le_bytes()).map_err(|e| format!("Failed to write trailer: {}", e))?;
+
+        // The bundle hash covers everything written up to this point
+        let bundle_sha256 = std::mem::take(&mut self.hasher).finalize();
+        self.write(&bundle_sha256).map_err(|e| format!("Failed to write trailer: {}", e))?;
+
+        let mut signature_block = [0u8; 97];
+        if let Some(key) = &self.signing_key {
+            let bundle_sha256: Sha256Hash = bundle_sha256.into();
+            signature_block[0] = 1;
+            signature_block[1..33].copy_from_slice(key.verifying_key().as_bytes());
+            signature_block[33..].copy_from_slice(&key.sign(&signed_message(&bundle_sha256)).to_bytes());
+        }
+        self.write(&signature_block).map_err(|e| format!("Failed to write signature: {}", e))?;
+        self.write(TOC_MAGIC).map_err(|e| format!("Failed to write trailer: {}", e))?;
+
+        self.inner.flush().map_err(|e| format!("Failed to flush bundle: {}", e))?;
+        Ok(self.inner)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> { // Write and keep track of the position and hash
+        self.inner.write_all(bytes)?;
+        self.hasher.update(bytes);
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+pub struct BundleReader<R: Read> { // Reads a .reskin container from any input
+    inner: R,
+    version: u8,
+    manifest_json: Vec<u8>,
+    manifest: ThemeManifest,
+    entries_start: u64, // Offset of the first entry
+}
+
+impl<R: Read> BundleReader<R> {
+    // Read and validate the header and manifest
+    pub fn new(mut inner: R) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        inner.read_exact(&mut magic).map_err(|_| "Invalid .reskin file: too small".to_string())?;
+        if &magic != MAGIC {
+            return Err("Invalid .reskin file: missing RSKN header".to_string());
+        }
+
+        // Either [version, len x8] or, for legacy bundles, [len x8, '{']
+        let mut head = [0u8; 9];
+        inner.read_exact(&mut head).map_err(|_| "Invalid .reskin file: too small".to_string())?;
+
+        let (version, manifest_len, mut manifest_json) = if head[8] == b'{' {
+            // The top byte of a sane manifest length is always zero, so this can only be a legacy bundle
+            let len = u64::from_le_bytes(head[..8].try_into().unwrap());
+            (LEGACY_VERSION, len, vec![b'{'])
+        } else {
+            let len = u64::from_le_bytes(head[1..].try_into().unwrap());
+            (head[0], len, Vec::new())
+        };
+
+        if version > FORMAT_VERSION || (version == LEGACY_VERSION && manifest_json.is_empty()) {
+            return Err(format!("Unsupported .reskin format version {} (this build supports up to {})", version, FORMAT_VERSION));
+        }
+        if manifest_len == 0 || manifest_len > MAX_MANIFEST_LEN {
+            return Err("Invalid .reskin file: manifest size mismatch".to_string());
+        }
+
+        let already_read = manifest_json.len();
+        manifest_json.resize(manifest_len as usize, 0);
+        inner.read_exact(&mut manifest_json[already_read..])
+            .map_err(|_| "Invalid .reskin file: manifest size mismatch".to_string())?;
+
+        let manifest: ThemeManifest = serde_json::from_slice(&manifest_json)
+            .map_err(|e| format!("Failed to parse manifest: {}", e))?;
+
+        let header_len = if version == LEGACY_VERSION { 12 } else { 13 };
+        let entries_start = header_len + manifest_len;
+
+        Ok(Self { inner, version, manifest_json, manifest, entries_start })
+    }
+
+    #[cfg(test)]
+    pub fn version(&self) -> u8 { // Container format version of this bundle
+        self.version
+    }
+
+    pub fn manifest(&self) -> &ThemeManifest { // Parsed manifest
+        &self.manifest
+    }
+
+    pub fn manifest_json(&self) -> &[u8] { // Manifest exactly as stored in the bundle
+        &self.manifest_json
+    }
+
+    // Stream the data belonging to a header just returned by next_entry_header into `out`
+    pub fn copy_entry_data<W: Write>(&mut self, header: &EntryHeader, out: &mut W) -> Result<(), String> {
+        copy_stored(&mut self.inner, self.version, header, out)
+    }
+
+    // Read the header of the next entry, returning None once the entry list ends. The reader is left at
+    // the entry's data, so callers must follow up with copy_entry_data before asking for the next header.
+    pub fn next_entry_header(&mut self) -> Result<Option<EntryHeader>, String> {
+        let mut path_len_bytes = [0u8; 4];
+        if !read_exact_or_eof(&mut self.inner, &mut path_len_bytes)
+            .map_err(|e| format!("Failed to read filename length: {}", e))? {
+            if self.version != LEGACY_VERSION {
+                return Err("Invalid .reskin file: missing end marker".to_string());
+            }
+            return Ok(None); // Legacy bundles simply end after the last entry
+        }
+
+        let path_len = u32::from_le_bytes(path_len_bytes) as usize;
+        if path_len == 0 && self.version != LEGACY_VERSION {
+            return Ok(None); // End marker
+        }
+        if path_len == 0 || path_len > MAX_PATH_LEN {
+            return Err(format!("Invalid .reskin file: bad filename length {}", path_len));
+        }
+
+        let mut path_bytes = vec![0u8; path_len];
+        self.inner.read_exact(&mut path_bytes).map_err(|e| format!("Failed to read filename: {}", e))?;
+        let path = String::from_utf8(path_bytes)
+            .map_err(|_| "Invalid .reskin file: filename is not valid UTF-8".to_string())?;
+
+        if self.version == LEGACY_VERSION {
+            // Legacy entries are raw data whose stored length is the only length
+            let size = read_u64(&mut self.inner)
+                .map_err(|_| format!("Failed to read asset length for {}", path))?;
+            return Ok(Some(EntryHeader {
+                path,
+                kind: EntryKind::File,
+                mode: None,
+                mtime: None,
+                codec: Codec::Store,
+                size,
+                stored_size: Some(size),
+                sha256: None,
+            }));
+        }
+
+        let (kind, mode, mtime) = read_metadata(&mut self.inner)
+            .map_err(|_| format!("Failed to read entry metadata for {}", path))?;
+        let byte = read_u8(&mut self.inner).map_err(|_| format!("Failed to read asset codec for {}", path))?;
+        let codec = Codec::from_byte(byte)?;
+        let size = read_u64(&mut self.inner)
+            .map_err(|_| format!("Failed to read asset length for {}", path))?;
+
+        // The stored length is only known once the chunks are read, and the checksum follows them
+        Ok(Some(EntryHeader { path, kind, mode: Some(mode), mtime: Some(mtime), codec, size, stored_size: None, sha256: None }))
+    }
+}
+
+impl<R: Read + Seek> BundleReader<R> {
+    // List every asset with its offset and size, without reading any asset data
+    pub fn entries(&mut self) -> Result<Vec<BundleEntry>, String> {
+        let entries = if self.version == LEGACY_VERSION {
+            self.scan_entries()?
+        } else {
+            self.read_toc()?
+        };
+
+        // Rewind so sequential reads still start at the first entry
+        self.inner.seek(SeekFrom::Start(self.entries_start))
+            .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+        Ok(entries)
+    }
+
+    // Stream a single asset into `out` by seeking straight to it
+    pub fn copy_entry<W: Write>(&mut self, entry: &BundleEntry, out: &mut W) -> Result<(), String> {
+        self.inner.seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| format!("Failed to seek to {}: {}", entry.path, e))?;
+
+        copy_stored(&mut self.inner, self.version, &EntryHeader::from(entry), out)
+    }
+
+    // Read a single asset into memory by seeking straight to it
+    pub fn read_entry(&mut self, entry: &BundleEntry) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        self.copy_entry(entry, &mut data)?;
+        Ok(data)
+    }
+
+    // Read a single asset by its path
+    pub fn read_asset(&mut self, path: &str) -> Result<Vec<u8>, String> {
+        let entry = self.entries()?
+            .into_iter()
+            .find(|e| e.path == path)
+            .ok_or_else(|| format!("Asset '{}' not found in bundle", path))?;
+        self.read_entry(&entry)
+    }
+
+    // Check every asset and the whole-bundle hash, collecting problems instead of stopping at the first one
+    pub fn verify(&mut self) -> Result<VerifyReport, String> {
+        let mut report = VerifyReport {
+            valid: true,
+            has_checksums: self.version != LEGACY_VERSION,
+            bundle_sha256: None,
+            entries_checked: 0,
+            problems: Vec::new(),
+        };
+
+        if self.version != LEGACY_VERSION {
+            match self.read_trailer() {
+                Ok(trailer) => {
+                    report.bundle_sha256 = Some(to_hex(&trailer.bundle_sha256));
+
+                    // Hash everything before the bundle hash without holding it in memory
+                    let mut hasher = Sha256::new();
+                    self.inner.seek(SeekFrom::Start(0))
+                        .and_then(|_| io::copy(&mut (&mut self.inner).take(trailer.hashed_len), &mut hasher))
+                        .map_err(|e| format!("Failed to read bundle: {}", e))?;
+                    if hasher.finalize()[..] != trailer.bundle_sha256 {
+                        report.problems.push(VerifyProblem {
+                            entry: None,
+                            message: "Bundle checksum mismatch: the file is corrupted or was modified".to_string(),
+                        });
+                    }
+                }
+                Err(e) => report.problems.push(VerifyProblem { entry: None, message: e }),
+            }
+        }
+
+        match self.entries() {
+            Ok(entries) => {
+                for entry in &entries {
+                    report.entries_checked += 1;
+                    if let Err(e) = self.copy_entry(entry, &mut io::sink()) {
+                        report.problems.push(VerifyProblem { entry: Some(entry.path.clone()), message: e });
+                    }
+                }
+            }
+            Err(e) => report.problems.push(VerifyProblem { entry: None, message: e }),
+        }
+
+        self.inner.seek(SeekFrom::Start(self.entries_start))
+            .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+        report.valid = report.problems.is_empty();
+        Ok(report)
+    }
+
+    // Read the signature block, returning None for unsigned and legacy bundles
+    pub fn signature(&mut self) -> Result<Option<BundleSignature>, String> {
+        if self.version == LEGACY_VERSION {
+            return Ok(None);
+        }
+        let trailer = self.read_trailer()?;
+        Ok(trailer.signature.map(|(public_key, signature)| BundleSignature {
+            public_key,
+            signature,
+            bundle_sha256: trailer.bundle_sha256,
+        }))
+    }
+
+    // Whole-bundle hash as recorded in the trailer, None for legacy bundles. Not checked against the contents; verify() does that.
+    pub fn bundle_sha256(&mut self) -> Result<Option<Sha256Hash>, String> {
+        if self.version == LEGACY_VERSION {
+            return Ok(None);
+        }
+        let trailer = self.read_trailer()?;
+        self.inner.seek(SeekFrom::Start(self.entries_start))
+            .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+        Ok(Some(trailer.bundle_sha256))
+    }
+
+    fn read_trailer(&mut self) -> Result<Trailer, String> { // Read the fixed-size trailer at the end of the bundle
+        let file_len = self.inner.seek(SeekFrom::End(0))
+            .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+        if file_len < self.entries_start + 4 + TRAILER_LEN {
+            return Err("Invalid .reskin file: missing table of contents".to_string());
+        }
+
+        let mut trailer = [0u8; TRAILER_LEN as usize];
+        self.inner.seek(SeekFrom::Start(file_len - TRAILER_LEN))
+            .and_then(|_| self.inner.read_exact(&mut trailer))
+            .map_err(|e| format!("Failed to read trailer: {}", e))?;
+        if &trailer[trailer.len() - 4..] != TOC_MAGIC {
+            return Err("Invalid .reskin file: missing table of contents".to_string());
+        }
+
+        let toc_offset = u64::from_le_bytes(trailer[..8].try_into().unwrap());
+        let toc_end = file_len - TRAILER_LEN;
+        if toc_offset < self.entries_start + 4 || toc_offset > toc_end {
+            return Err("Invalid .reskin file: table of contents offset out of range".to_string());
+        }
+
+        let bundle_sha256 = trailer[8..40].try_into().unwrap();
+        let signature = match trailer[40] {
+            0 => None,
+            1 => Some((trailer[41..73].try_into().unwrap(), trailer[73..137].try_into().unwrap())),
+            other => return Err(format!("Invalid .reskin file: unknown signature kind {}", other)),
+        };
+        Ok(Trailer { toc_offset, toc_end, hashed_len: toc_end + 8, bundle_sha256, signature })
+    }
+
+    fn read_toc(&mut self) -> Result<Vec<BundleEntry>, String> { // Parse the table of contents via the trailer
+        let Trailer { toc_offset, toc_end, .. } = self.read_trailer()?;
+
+        let mut toc = Vec::new();
+        self.inner.seek(SeekFrom::Start(toc_offset))
+            .map_err(|e| format!("Failed to seek to table of contents: {}", e))?;
+        (&mut self.inner).take(toc_end - toc_offset).read_to_end(&mut toc)
+            .map_err(|e| format!("Failed to read table of contents: {}", e))?;
+
+        let mut cursor = &toc[..];
+        let count = read_u32(&mut cursor)?;
+        if count > MAX_ENTRIES {
+            return Err(format!("Invalid .reskin file: too many entries ({})", count));
+        }
+
+        let mut entries = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let path_len = read_u32(&mut cursor)? as usize;
+            if path_len == 0 || path_len > MAX_PATH_LEN || path_len > cursor.len() {
+                return Err("Invalid .reskin file: corrupt table of contents".to_string());
+            }
+            let path = String::from_utf8(cursor[..path_len].to_vec())
+                .map_err(|_| "Invalid .reskin file: filename is not valid UTF-8".to_string())?;
+            cursor = &cursor[path_len..];
+
+            let (kind, mode, mtime) = read_metadata(&mut cursor)?;
+            let codec = Codec::from_byte(read_u8(&mut cursor)?)?;
+            let size = read_u64(&mut cursor)?;
+            let offset = read_u64(&mut cursor)?;
+            let stored_size = read_u64(&mut cursor)?;
+            let mut sha256 = [0u8; 32];
+            cursor.read_exact(&mut sha256).map_err(|_| "Invalid .reskin file: corrupt table of contents".to_string())?;
+
+            // Asset chunks and their trailing checksum must lie between the header and the table of contents
+            if offset < self.entries_start || offset.checked_add(stored_size + 32).is_none_or(|end| end > toc_offset) {
+                return Err(format!("Invalid .reskin file: entry {} points outside the bundle", path));
+            }
+            entries.push(BundleEntry {
+                path,
+                kind,
+                mode: Some(mode),
+                mtime: Some(mtime),
+                codec,
+                size,
+                offset,
+                stored_size,
+                sha256: Some(sha256),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn scan_entries(&mut self) -> Result<Vec<BundleEntry>, String> { // Build an index for legacy bundles, which have no table of contents
+        let file_len = self.inner.seek(SeekFrom::End(0))
+            .and_then(|len| self.inner.seek(SeekFrom::Start(self.entries_start)).map(|_| len))
+            .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+
+        let mut entries = Vec::new();
+        while let Some(header) = self.next_entry_header()? {
+            let offset = self.inner.stream_position()
+                .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+            let stored_size = header.stored_size.unwrap_or(header.size); // Always known in legacy bundles
+            if offset.checked_add(stored_size).is_none_or(|end| end > file_len) {
+                return Err(format!("Invalid .reskin file: asset {} is truncated", header.path));
+            }
+            self.inner.seek(SeekFrom::Start(offset + stored_size))
+                .map_err(|e| format!("Failed to skip asset {}: {}", header.path, e))?;
+            entries.push(BundleEntry {
+                path: header.path,
+                kind: header.kind,
+                mode: header.mode,
+                mtime: header.mtime,
+                codec: header.codec,
+                size: header.size,
+                offset,
+                stored_size,
+                sha256: header.sha256,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, String> { // Read a single byte
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes).map_err(|_| "Invalid .reskin file: unexpected end of data".to_string())?;
+    Ok(bytes[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, String> { // Read a little-endian u32
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes).map_err(|_| "Invalid .reskin file: unexpected end of data".to_string())?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, String> { // Read a little-endian u64
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes).map_err(|_| "Invalid .reskin file: unexpected end of data".to_string())?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// Read the kind, mode and mtime that follow an entry path
+fn read_metadata<R: Read>(reader: &mut R) -> Result<(EntryKind, u32, i64), String> {
+    let kind = EntryKind::from_byte(read_u8(reader)?)?;
+    let mode = read_u32(reader)?;
+    let mtime = read_u64(reader)? as i64;
+    Ok((kind, mode, mtime))
+}
+
+struct Trailer { // Parsed trailer of a bundle with a table of contents
+    toc_offset: u64, // Start of the table of contents
+    toc_end: u64, // End of the table of contents, where the trailer starts
+    hashed_len: u64, // Number of leading bytes covered by the bundle hash
+    bundle_sha256: Sha256Hash, // Recorded bundle hash
+    signature: Option<([u8; 32], [u8; 64])>, // Ed25519 public key and signature, if signed
+}
+
+fn signed_message(bundle_sha256: &Sha256Hash) -> Vec<u8> { // Message covered by a bundle signature
+    [SIGNATURE_CONTEXT, &bundle_sha256[..]].concat()
+}
+
+// Stream one asset's stored data into `out`, undoing its codec and refusing data that decodes to anything
+// but `size` bytes or does not match its checksum. Entries end with their own checksum, which must agree
+// with the one from the table of contents when that is known. Legacy entries are raw data with no checksum.
+fn copy_stored<R: Read, W: Write>(reader: &mut R, version: u8, header: &EntryHeader, out: &mut W) -> Result<(), String> {
+    let path = &header.path;
+    let truncated = || format!("Invalid .reskin file: asset {} is truncated", path);
+
+    let actual = if version != LEGACY_VERSION {
+        let mut chunks = ChunkReader { inner: &mut *reader, remaining: 0, done: false, consumed: 0, limit: header.stored_size };
+        let actual = decode_into(&mut chunks, header, out)?;
+        io::copy(&mut chunks, &mut io::sink()).map_err(|e| read_error(path, e))?; // Skip to the terminator
+
+        let mut footer = [0u8; 32];
+        reader.read_exact(&mut footer).map_err(|_| truncated())?;
+        if footer != actual {
+            return Err(format!("Checksum mismatch for asset {}", path));
+        }
+        actual
+    } else {
+        let mut stored = (&mut *reader).take(header.stored_size.unwrap_or(header.size));
+        let actual = decode_into(&mut stored, header, out)?;
+        io::copy(&mut stored, &mut io::sink()).map_err(|e| read_error(path, e))?;
+        if stored.limit() != 0 {
+            return Err(truncated());
+        }
+        actual
+    };
+
+    if header.sha256.is_some_and(|expected| expected != actual) {
+        return Err(format!("Checksum mismatch for asset {}", path));
+    }
+    Ok(())
+}
+
+// Undo the codec of `stored` and copy the result into `out`, returning its checksum
+fn decode_into<S: Read, W: Write>(stored: S, header: &EntryHeader, out: &mut W) -> Result<Sha256Hash, String> {
+    match header.codec {
+        Codec::Store => pump(stored, header, out),
+        Codec::Zstd => {
+            let decoder = zstd::stream::read::Decoder::new(stored)
+                .map_err(|e| format!("Failed to decompress asset {}: {}", header.path, e))?;
+            pump(decoder, header, out)
+        }
+    }
+}
+
+// Copy decoded data into `out` while hashing it, stopping one byte past the declared size
+fn pump<D: Read, W: Write>(decoded: D, header: &EntryHeader, out: &mut W) -> Result<Sha256Hash, String> {
+    let mut decoded = decoded.take(header.size + 1);
+    let mut hasher = Sha256::new();
+    let mut count = 0u64;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let n = match decoded.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(read_error(&header.path, e)),
+        };
+        hasher.update(&buf[..n]);
+        count += n as u64;
+        out.write_all(&buf[..n]).map_err(|e| format!("Failed to write asset {}: {}", header.path, e))?;
+    }
+
+    if count != header.size {
+        return Err(format!("Invalid .reskin file: asset {} has the wrong size", header.path));
+    }
+    Ok(hasher.finalize().into())
+}
+
+fn read_error(path: &str, e: io::Error) -> String { // Describe a failure while reading asset data
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => format!("Invalid .reskin file: asset {} is truncated", path),
+        io::ErrorKind::InvalidData => format!("Invalid .reskin file: asset {}: {}", path, e),
+        _ => format!("Failed to read asset {}: {}", path, e),
+    }
+}
+
+impl From<&BundleEntry> for EntryHeader {
+    fn from(entry: &BundleEntry) -> Self {
+        Self {
+            path: entry.path.clone(),
+            kind: entry.kind,
+            mode: entry.mode,
+            mtime: entry.mtime,
+            codec: entry.codec,
+            size: entry.size,
+            stored_size: Some(entry.stored_size),
+            sha256: entry.sha256,
+        }
+    }
+}
+
+struct ChunkWriter<'a, W: Write> { // Splits written data into length-prefixed chunks
+    bundle: &'a mut BundleWriter<W>,
+    buffer: Vec<u8>, // Data waiting for the current chunk to fill up
+}
+
+impl<'a, W: Write> ChunkWriter<'a, W> {
+    fn flush_chunk(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let chunk = std::mem::take(&mut self.buffer);
+            self.bundle.write(&(chunk.len() as u32).to_le_bytes())?;
+            self.bundle.write(&chunk)?;
+            self.buffer = chunk;
+            self.buffer.clear();
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<&'a mut BundleWriter<W>> { // Write the last chunk and the terminator
+        self.flush_chunk()?;
+        self.bundle.write(&0u32.to_le_bytes())?;
+        Ok(self.bundle)
+    }
+}
+
+impl<W: Write> Write for ChunkWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(CHUNK_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == CHUNK_SIZE {
+            self.flush_chunk()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(()) // Chunks are only cut when full, so a flush must not end one early
+    }
+}
+
+struct ChunkReader<'a, R: Read> { // Reads the data of a chunked entry up to its terminator
+    inner: &'a mut R,
+    remaining: u32, // Bytes left in the current chunk
+    done: bool, // Terminator has been read
+    consumed: u64, // Bytes read so far, chunk lengths included
+    limit: Option<u64>, // Length of the chunks from the table of contents, if known
+}
+
+impl<R: Read> Read for ChunkReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        while self.remaining == 0 {
+            if self.done || buf.is_empty() {
+                return Ok(0);
+            }
+            let mut len = [0u8; 4];
+            self.inner.read_exact(&mut len)?;
+            self.consumed += 4;
+
+            let len = u32::from_le_bytes(len);
+            if len == 0 {
+                self.done = true;
+                if self.limit.is_some_and(|limit| limit != self.consumed) {
+                    return Err(invalid("data does not match the table of contents"));
+                }
+                return Ok(0);
+            }
+            if len > MAX_CHUNK_LEN {
+                return Err(invalid("chunk is too large"));
+            }
+            if self.limit.is_some_and(|limit| self.consumed + len as u64 + 4 > limit) {
+                return Err(invalid("data runs past the end recorded in the table of contents"));
+            }
+            self.remaining = len;
+        }
+
+        let want = buf.len().min(self.remaining as usize);
+        let n = self.inner.read(&mut buf[..want])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= n as u32;
+        self.consumed += n as u64;
+        Ok(n)
+    }
+}
+
+// Data of an asset begun with BundleWriter::start_asset. The first bytes are held back until there are
+// enough to choose the codec, after which data is compressed and stored as it is written.
+pub struct AssetWriter<'a, W: Write> {
+    path: String,
+    metadata: EntryMetadata,
+    size: u64, // Length declared in the entry header
+    codec: Codec, // Requested codec until the sample is full, then the one in use
+    sample: Vec<u8>, // Leading bytes held back to choose the codec with
+    hasher: Sha256, // Running hash of the uncompressed data
+    count: u64, // Uncompressed bytes written so far
+    data: AssetData<'a, W>,
+}
+
+enum AssetData<'a, W: Write> {
+    Sampling(&'a mut BundleWriter<W>),
+    Store(ChunkWriter<'a, W>, u64), // Chunks and the offset they start at
+    Zstd(zstd::stream::write::Encoder<'static, ChunkWriter<'a, W>>, u64),
+    Switching, // Moving from sampling to storing, or left behind when that failed
+}
+
+impl<'a, W: Write> AssetWriter<'a, W> {
+    // Pick the codec from the sample, write the entry header and start storing data
+    fn start_data(&mut self) -> io::Result<()> {
+        if !matches!(self.data, AssetData::Sampling(_)) {
+            return Ok(());
+        }
+        let AssetData::Sampling(bundle) = std::mem::replace(&mut self.data, AssetData::Switching) else {
+            unreachable!("checked above");
+        };
+
+        // Fall back to storing raw bytes when compressing the start of the asset does not help
+        self.codec = match self.codec {
+            Codec::Zstd if self.sample.is_empty() => Codec::Store,
+            Codec::Zstd if zstd::bulk::compress(&self.sample, ZSTD_LEVEL)?.len() < self.sample.len() => Codec::Zstd,
+            _ => Codec::Store,
+        };
+
+        let path_bytes = self.path.as_bytes();
+        bundle.write(&(path_bytes.len() as u32).to_le_bytes())?;
+        bundle.write(path_bytes)?;
+        bundle.write(&[self.metadata.kind.to_byte()])?;
+        bundle.write(&self.metadata.mode.to_le_bytes())?;
+        bundle.write(&self.metadata.mtime.to_le_bytes())?;
+        bundle.write(&[self.codec.to_byte()])?;
+        bundle.write(&self.size.to_le_bytes())?;
+
+        let offset = bundle.position;
+        let mut chunks = ChunkWriter { bundle, buffer: Vec::with_capacity(CHUNK_SIZE) };
+        self.data = match self.codec {
+            Codec::Store => {
+                chunks.write_all(&self.sample)?;
+                AssetData::Store(chunks, offset)
+            }
+            Codec::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(chunks, ZSTD_LEVEL)?;
+                encoder.write_all(&self.sample)?;
+                AssetData::Zstd(encoder, offset)
+            }
+        };
+        self.sample = Vec::new();
+        Ok(())
+    }
+
+    // Store what is left of the data and record the entry
+    pub fn finish(mut self) -> Result<(), String> {
+        let path = self.path.clone();
+        let write_error = |e: io::Error| format!("Failed to write asset {}: {}", path, e);
+        self.start_data().map_err(write_error)?;
+        let (bundle, offset) = match std::mem::replace(&mut self.data, AssetData::Switching) {
+            AssetData::Store(chunks, offset) => (chunks.finish().map_err(write_error)?, offset),
+            AssetData::Zstd(encoder, offset) => (encoder.finish().and_then(ChunkWriter::finish).map_err(write_error)?, offset),
+            AssetData::Sampling(_) | AssetData::Switching => return Err(format!("Failed to write asset {}: an earlier write failed", path)),
+        };
+        let stored_size = bundle.position - offset;
+
+        if self.count != self.size {
+            return Err(format!("Asset {} changed size while it was being bundled", path));
+        }
+        let sha256: Sha256Hash = self.hasher.finalize().into();
+        bundle.write(&sha256).map_err(|e| format!("Failed to write asset checksum: {}", e))?;
+
+        bundle.entries.push(BundleEntry {
+            path,
+            kind: self.metadata.kind,
+            mode: Some(self.metadata.mode),
+            mtime: Some(self.metadata.mtime),
+            codec: self.codec,
+            size: self.size,
+            offset,
+            stored_size,
+            sha256: Some(sha256),
+        });
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for AssetWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = match &mut self.data {
+            AssetData::Sampling(_) => {
+                let n = buf.len().min(SAMPLE_SIZE - self.sample.len());
+                self.sample.extend_from_slice(&buf[..n]);
+                n
+            }
+            AssetData::Store(chunks, _) => chunks.write(buf)?,
+            AssetData::Zstd(encoder, _) => encoder.write(buf)?,
+            AssetData::Switching => return Err(io::Error::other("an earlier write failed")),
+        };
+        self.hasher.update(&buf[..n]);
+        self.count += n as u64;
+        if self.sample.len() == SAMPLE_SIZE {
+            self.start_data()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(()) // Data is stored in whole chunks, which are cut by ChunkWriter
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String { // Lowercase hex encoding for checksums
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn serialize_hash<S: Serializer>(hash: &Option<Sha256Hash>, serializer: S) -> Result<S::Ok, S::Error> {
+    match hash {
+        Some(hash) => serializer.serialize_some(&to_hex(hash)),
+        None => serializer.serialize_none(),
+    }
+}
+
+// Fill `buf` completely, or return false if the input ended before the first byte
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use crate::test_support::{bundle_bytes, scratch_dir, test_manifest};
+
+    fn write_bundle(assets: &[(&str, &[u8])]) -> Vec<u8> {
+        let assets: Vec<_> = assets.iter().map(|(path, data)| (*path, *data, Codec::Zstd)).collect();
+        bundle_bytes(&test_manifest("Test"), &assets)
+    }
+
+    fn read_all(bytes: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+        let mut reader = BundleReader::new(bytes)?;
+        let mut assets = Vec::new();
+        while let Some(header) = reader.next_entry_header()? {
+            let mut data = Vec::new();
+            reader.copy_entry_data(&header, &mut data)?;
+            assets.push((header.path, data));
+        }
+        Ok(assets)
+    }
+
+    #[test]
+    fn round_trip() {
+        let big = vec![7u8; 70_000]; // Larger than u16 to catch narrow length fields
+        let bytes = write_bundle(&[("gtk-3.0/gtk.css", b"* {}"), ("empty", b""), ("icons/big.bin", &big)]);
+
+        let reader = BundleReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.version(), FORMAT_VERSION);
+        assert_eq!(reader.manifest().name, "Test");
+
+        let assets = read_all(&bytes).unwrap();
+        assert_eq!(assets.len(), 3);
+        assert_eq!(assets[0], ("gtk-3.0/gtk.css".to_string(), b"* {}".to_vec()));
+        assert_eq!(assets[1], ("empty".to_string(), Vec::new()));
+        assert_eq!(assets[2].1, big);
+    }
+
+    #[test]
+    fn reads_legacy_bundles() {
+        // Layout produced by bundle_theme before the version byte was added
+        let manifest_json = serde_json::to_vec(&test_manifest("Test")).unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(manifest_json.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&manifest_json);
+        bytes.extend_from_slice(&5u32.to_le_bytes());
+        bytes.extend_from_slice(b"a.css");
+        bytes.extend_from_slice(&3u64.to_le_bytes());
+        bytes.extend_from_slice(b"abc");
+
+        let mut reader = BundleReader::new(Cursor::new(&bytes)).unwrap();
+        assert_eq!(reader.version(), LEGACY_VERSION);
+        assert_eq!(reader.manifest_json(), &manifest_json[..]);
+        assert_eq!(reader.read_asset("a.css").unwrap(), b"abc"); // Indexed by scanning
+        let entry = reader.entries().unwrap().remove(0);
+        assert_eq!((entry.kind, entry.mode, entry.mtime), (EntryKind::File, None, None)); // Legacy bundles carry no metadata
+        assert_eq!(read_all(&bytes).unwrap(), vec![("a.css".to_string(), b"abc".to_vec())]);
+    }
+
+    #[test]
+    fn rejects_bad_magic_and_future_versions() {
+        let mut bytes = write_bundle(&[]);
+        bytes[0] = b'X';
+        assert!(BundleReader::new(&bytes[..]).is_err());
+
+        let mut bytes = write_bundle(&[]);
+        bytes[4] = FORMAT_VERSION + 1;
+        assert!(BundleReader::new(&bytes[..]).is_err());
+        bytes[4] = LEGACY_VERSION; // Legacy bundles have no version byte at all
+        assert!(BundleReader::new(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn bundle_and_extract_round_trip() {
+        // Build a small theme tree with the real bundler and unpack it with the real extractor
+        let dir = scratch_dir("format-round-trip");
+        let src = dir.join("src");
+        let out = dir.join("Test.reskin");
+        std::fs::create_dir_all(src.join("gtk-3.0")).unwrap();
+        std::fs::write(src.join("gtk-3.0/gtk.css"), b"window { color: red; }").unwrap();
+        std::fs::write(src.join("index.theme"), vec![1u8; 100_000]).unwrap();
+        std::os::unix::fs::symlink("gtk.css", src.join("gtk-3.0/gtk-dark.css")).unwrap();
+        let outside = dir.join("outside");
+        std::fs::write(&outside, b"shared").unwrap();
+        std::os::unix::fs::symlink(&outside, src.join("outside")).unwrap(); // Stored as what it points at
+
+        let request = crate::bundle::collect_directory_assets(crate::types::BundleRequest {
+            manifest: test_manifest("Test"),
+            output_path: out.to_string_lossy().into_owned(),
+            assets: Vec::new(),
+            theme_directory: Some(src.to_string_lossy().into_owned()),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            normalize_metadata: false,
+        }).unwrap();
+        crate::bundle::write_bundle(request, &mut crate::progress::Progress::none()).unwrap();
+
+        let extracted = std::path::PathBuf::from(crate::extract::extract_theme(out.to_string_lossy().into_owned()).unwrap());
+        assert_eq!(std::fs::read(extracted.join("gtk-3.0/gtk.css")).unwrap(), b"window { color: red; }");
+        assert_eq!(std::fs::read(extracted.join("index.theme")).unwrap(), vec![1u8; 100_000]);
+        assert!(extracted.join("reskin.json").exists());
+        assert!(extracted.starts_with(crate::locations::cache_dir(&crate::locations::home_dir().unwrap()))); // Never a shared folder such as /tmp
+        assert_eq!(std::fs::read_link(extracted.join("gtk-3.0/gtk-dark.css")).unwrap(), std::path::PathBuf::from("gtk.css"));
+        assert!(!std::fs::symlink_metadata(extracted.join("outside")).unwrap().file_type().is_symlink());
+
+        let _ = std::fs::remove_dir_all(&dir);
+        let _ = std::fs::remove_dir_all(extracted.parent().unwrap());
+        let _ = std::fs::remove_file(&outside);
+    }
+
+    #[test]
+    fn truncated_asset_is_an_error() {
+        let bytes = write_bundle(&[("a.css", b"0123456789")]);
+        let entry = &BundleReader::new(Cursor::new(&bytes)).unwrap().entries().unwrap()[0];
+        let truncated = &bytes[..entry.offset as usize + 4];
+        assert!(read_all(truncated).is_err());
+
+        // Cutting off the trailer loses the table of contents and the end marker
+        let mut reader = BundleReader::new(Cursor::new(&bytes[..bytes.len() - 4])).unwrap();
+        assert!(reader.entries().is_err());
+        assert!(read_all(&bytes[..entry.offset as usize + 10]).is_err());
+    }
+
+    #[test]
+    fn table_of_contents_allows_random_access() {
+        let bytes = write_bundle(&[("a.css", b"first"), ("icons/b.svg", b"<svg/>"), ("c", b"")]);
+        let mut reader = BundleReader::new(Cursor::new(&bytes)).unwrap();
+
+        let entries = reader.entries().unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["a.css", "icons/b.svg", "c"]);
+        assert_eq!(entries[1].size, 6);
+
+        assert_eq!(reader.read_asset("icons/b.svg").unwrap(), b"<svg/>");
+        assert_eq!(reader.read_entry(&entries[0]).unwrap(), b"first");
+        assert!(reader.read_asset("missing").is_err());
+
+        // Listing rewinds, so sequential reads still see every asset
+        reader.entries().unwrap();
+        let mut count = 0;
+        while let Some(header) = reader.next_entry_header().unwrap() {
+            reader.copy_entry_data(&header, &mut io::sink()).unwrap();
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn compresses_when_it_helps() {
+        let css = "window { color: red; }\n".repeat(500);
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let noise: Vec<u8> = (0..4096).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 32) as u8
+        }).collect();
+        let mut writer = BundleWriter::new(Vec::new(), &test_manifest("Test")).unwrap();
+        writer.add_asset("gtk.css", EntryMetadata::default(), css.len() as u64, css.as_bytes(), Codec::Zstd).unwrap();
+        writer.add_asset("noise.bin", EntryMetadata::default(), noise.len() as u64, &noise[..], Codec::Zstd).unwrap();
+        writer.add_asset("raw.css", EntryMetadata::default(), css.len() as u64, css.as_bytes(), Codec::Store).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = BundleReader::new(Cursor::new(&bytes)).unwrap();
+        let entries = reader.entries().unwrap();
+        assert_eq!(entries[0].codec, Codec::Zstd);
+        assert!(entries[0].stored_size < entries[0].size);
+        assert_eq!(entries[1].codec, Codec::Store); // Incompressible data falls back to raw
+        assert_eq!(entries[2].codec, Codec::Store);
+
+        assert_eq!(reader.read_asset("gtk.css").unwrap(), css.as_bytes());
+        let assets = read_all(&bytes).unwrap();
+        assert_eq!(assets[0].1, css.as_bytes());
+        assert_eq!(assets[1].1, noise);
+        assert_eq!(assets[2].1, css.as_bytes());
+    }
+
+    #[test]
+    fn decompressed_size_must_match() {
+        let css = "a { b: c; }\n".repeat(200);
+        let mut bytes = write_bundle(&[("gtk.css", css.as_bytes())]);
+        let entry = BundleReader::new(Cursor::new(&bytes)).unwrap().entries().unwrap().remove(0);
+        assert_eq!(entry.codec, Codec::Zstd);
+
+        // The uncompressed size sits right before the first chunk
+        let size_at = entry.offset as usize - 8;
+        bytes[size_at..size_at + 8].copy_from_slice(&(entry.size + 1).to_le_bytes());
+        assert!(read_all(&bytes).is_err());
+    }
+
+    #[test]
+    fn verify_accepts_intact_bundles() {
+        let bytes = write_bundle(&[("a.css", b"first"), ("b.css", b"second")]);
+        let report = BundleReader::new(Cursor::new(&bytes)).unwrap().verify().unwrap();
+        assert!(report.valid);
+        assert!(report.has_checksums);
+        assert_eq!(report.entries_checked, 2);
+        assert_eq!(report.bundle_sha256.unwrap().len(), 64);
+    }
+
+    #[test]
+    fn verify_names_the_damaged_entry() {
+        let mut bytes = write_bundle(&[("a.css", b"first"), ("b.css", b"second")]);
+        let entries = BundleReader::new(Cursor::new(&bytes)).unwrap().entries().unwrap();
+        bytes[entries[1].offset as usize + 4] ^= 0xff; // First byte after the chunk length
+
+        let report = BundleReader::new(Cursor::new(&bytes)).unwrap().verify().unwrap();
+        assert!(!report.valid);
+        assert!(report.problems.iter().any(|p| p.entry.is_none())); // Whole-bundle hash
+        let damaged: Vec<_> = report.problems.iter().filter_map(|p| p.entry.as_deref()).collect();
+        assert_eq!(damaged, ["b.css"]);
+
+        // Sequential extraction refuses the damaged asset too
+        let err = read_all(&bytes).unwrap_err();
+        assert!(err.contains("b.css"), "{}", err);
+    }
+
+    #[test]
+    fn verify_reports_missing_checksums_for_old_bundles() {
+        let manifest_json = serde_json::to_vec(&test_manifest("Test")).unwrap();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&(manifest_json.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&manifest_json);
+
+        let report = BundleReader::new(Cursor::new(&bytes)).unwrap().verify().unwrap();
+        assert!(report.valid);
+        assert!(!report.has_checksums);
+        assert!(report.bundle_sha256.is_none());
+    }
+
+    #[test]
+    fn signatures_cover_the_bundle_hash() {
+        let key = SigningKey::from_bytes(&[42u8; 32]);
+        let mut writer = BundleWriter::new(Vec::new(), &test_manifest("Test")).unwrap();
+        writer.add_asset("a.css", EntryMetadata::default(), 5, &b"first"[..], Codec::Store).unwrap();
+        writer.sign_with(key.clone());
+        let mut bytes = writer.finish().unwrap();
+
+        let signature = BundleReader::new(Cursor::new(&bytes)).unwrap().signature().unwrap().unwrap();
+        assert_eq!(signature.public_key, key.verifying_key().to_bytes());
+        assert!(signature.is_valid());
+
+        // Tampering with the recorded hash breaks the signature
+        let len = bytes.len();
+        bytes[len - 133] ^= 0xff;
+        let signature = BundleReader::new(Cursor::new(&bytes)).unwrap().signature().unwrap().unwrap();
+        assert!(!signature.is_valid());
+
+        // Unsigned bundles have no signature block
+        let unsigned = write_bundle(&[("a.css", b"first")]);
+        assert!(BundleReader::new(Cursor::new(&unsigned)).unwrap().signature().unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_table_of_contents_is_rejected() {
+        let mut bytes = write_bundle(&[("a.css", b"first")]);
+        let len = bytes.len();
+        bytes[len - 141..len - 133].copy_from_slice(&(len as u64).to_le_bytes()); // Point the trailer past the end
+        assert!(BundleReader::new(Cursor::new(&bytes)).unwrap().entries().is_err());
+    }
+
+    #[test]
+    fn streams_assets_larger_than_a_chunk() {
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let noise: Vec<u8> = (0..CHUNK_SIZE * 3 + 123).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 32) as u8
+        }).collect();
+        let css = "window { color: red; }\n".repeat(20_000); // Compresses, and is larger than the trial sample
+        let bytes = write_bundle(&[("noise.bin", &noise), ("gtk.css", css.as_bytes())]);
+
+        let mut reader = BundleReader::new(Cursor::new(&bytes)).unwrap();
+        let entries = reader.entries().unwrap();
+        assert!(entries[0].stored_size > CHUNK_SIZE as u64 * 3);
+        assert_eq!(entries[1].codec, Codec::Zstd);
+        assert_eq!(reader.read_entry(&entries[0]).unwrap(), noise);
+        assert!(reader.verify().unwrap().valid);
+
+        let assets = read_all(&bytes).unwrap();
+        assert_eq!(assets[0].1, noise);
+        assert_eq!(assets[1].1, css.as_bytes());
+    }
+
+    #[test]
+    fn declared_size_must_match_the_stream() {
+        let mut writer = BundleWriter::new(Vec::new(), &test_manifest("Test")).unwrap();
+        let err = writer.add_asset("a.css", EntryMetadata::default(), 10, &b"short"[..], Codec::Store).unwrap_err();
+        assert!(err.contains("a.css"), "{}", err);
+    }
+
+    #[test]
+    fn records_entry_kinds_modes_and_mtimes() {
+        let mut writer = BundleWriter::new(Vec::new(), &test_manifest("Test")).unwrap();
+        let dir_meta = EntryMetadata { kind: EntryKind::Directory, mode: 0o755, mtime: 1_700_000_000 };
+        let link_meta = EntryMetadata { kind: EntryKind::Symlink, mode: 0o777, mtime: -5 };
+        writer.add_asset("apps", dir_meta, 0, io::empty(), Codec::Store).unwrap();
+        writer.add_asset("apps/alias.svg", link_meta, 8, &b"real.svg"[..], Codec::Zstd).unwrap();
+        assert!(writer.add_asset("bad", dir_meta, 1, &b"x"[..], Codec::Store).is_err());
+        let bytes = writer.finish().unwrap();
+
+        let mut reader = BundleReader::new(Cursor::new(&bytes)).unwrap();
+        let entries = reader.entries().unwrap();
+        assert_eq!((entries[0].kind, entries[0].mode, entries[0].mtime), (EntryKind::Directory, Some(0o755), Some(1_700_000_000)));
+        assert_eq!((entries[1].kind, entries[1].mtime), (EntryKind::Symlink, Some(-5)));
+        let header = reader.next_entry_header().unwrap().unwrap(); // Listing rewinds to the first entry
+        assert_eq!((header.kind, header.mode), (EntryKind::Directory, Some(0o755)));
+        assert_eq!(reader.read_entry(&entries[1]).unwrap(), b"real.svg");
+    }
+}
@@ -0,0 +1,160 @@
+// Import necessary crates
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::check::GTK_WM_DIRS;
+use crate::structure::has_section;
+use crate::utils::{is_font_file, overlay_entry};
+
+// Context folders of icon themes; size folders such as 48x48 or 32@2x are recognised by their leading digit
+const ICON_CONTEXT_DIRS: &[&str] = &["actions", "animations", "apps", "categories", "devices", "emblems", "emotes",
+                                     "intl", "mimetypes", "panel", "places", "status", "stock", "symbolic"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Route { // Which installed copy an entry of a theme folder goes to
+    Theme, // The themes folder: GTK, window manager and shell themes
+    Icons, // The icons folder, as an icon theme
+    Cursors, // The icons folder, as the cursor part of the theme
+    Fonts, // The fonts folder, flat
+    Other, // Fits no component, like assets/ or a README; goes with the GTK theme, or else the icons
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoutedEntry { // One entry of a theme folder and where it is installed
+    pub route: Route,
+    pub source: PathBuf, // Path in the theme folder
+    pub dest: PathBuf, // Path inside the installed copy
+}
+
+fn is_icon_dir(name: &str) -> bool {
+    name.starts_with("scalable") || name.starts_with(|c: char| c.is_ascii_digit()) || ICON_CONTEXT_DIRS.contains(&name)
+}
+
+// An index.theme goes wherever its sections say it belongs. One that cannot be read, because the
+// update that routes it deleted it, could be in either copy.
+fn index_theme_routes(index: &Path) -> Vec<Route> {
+    let Ok(contents) = fs::read_to_string(index) else {
+        return vec![Route::Theme, Route::Icons];
+    };
+    let mut routes = Vec::new();
+    if has_section(&contents, "Desktop Entry") || has_section(&contents, "X-GNOME-Metatheme") {
+        routes.push(Route::Theme);
+    }
+    if has_section(&contents, "Icon Theme") {
+        routes.push(Route::Icons);
+    }
+    if routes.is_empty() {
+        routes.push(Route::Other);
+    }
+    routes
+}
+
+// Where `relative`, a path inside the theme folder at `source`, is installed, and its path inside the
+// installed copy. Paths that no longer exist can be routed too. The contents of icons/ and fonts/ are
+// unpacked into their copies; reskin.json and non-font files under fonts/ are not installed at all.
+pub fn route_path(source: &Path, relative: &Path) -> Vec<(Route, PathBuf)> {
+    let mut components = relative.components();
+    let Some(first) = components.next() else {
+        return Vec::new();
+    };
+    let first = first.as_os_str().to_string_lossy();
+    let rest = components.as_path();
+    let nested = !rest.as_os_str().is_empty();
+    let font = || relative.file_name().map(|name| (Route::Fonts, PathBuf::from(name)));
+
+    match first.as_ref() {
+        "reskin.json" | ".reskinignore" if !nested => Vec::new(), // Bundle metadata, kept in the install record instead
+        "icons" if nested => vec![(Route::Icons, rest.to_path_buf())],
+        "fonts" if nested => font().filter(|_| is_font_file(relative)).into_iter().collect(),
+        "icons" | "fonts" => Vec::new(), // The folders themselves are not installed
+        "cursors" | "cursor.theme" => vec![(Route::Cursors, relative.to_path_buf())],
+        "index.theme" if !nested => index_theme_routes(&source.join("index.theme")).into_iter()
+            .map(|route| (route, relative.to_path_buf()))
+            .collect(),
+        name if GTK_WM_DIRS.contains(&name) => vec![(Route::Theme, relative.to_path_buf())],
+        name if is_icon_dir(name) => vec![(Route::Icons, relative.to_path_buf())],
+        _ if !nested && is_font_file(relative) => font().into_iter().collect(),
+        _ => vec![(Route::Other, relative.to_path_buf())],
+    }
+}
+
+// Split the top level of a theme folder by where each entry is installed
+pub fn route_theme(source: &Path) -> Vec<RoutedEntry> {
+    let mut relatives = Vec::new();
+    for entry in fs::read_dir(source).into_iter().flatten().flatten() {
+        let name = PathBuf::from(entry.file_name());
+        let unpacked = (name == Path::new("icons") || name == Path::new("fonts")) && entry.file_type().is_ok_and(|t| t.is_dir());
+        if unpacked {
+            relatives.extend(fs::read_dir(entry.path()).into_iter().flatten().flatten().map(|child| name.join(child.file_name())));
+        } else {
+            relatives.push(name);
+        }
+    }
+    relatives.sort();
+
+    relatives.iter()
+        .flat_map(|relative| {
+            let path = source.join(relative);
+            route_path(source, relative).into_iter()
+                .map(move |(route, dest)| RoutedEntry { route, source: path.clone(), dest })
+        })
+        .collect()
+}
+
+// Whether any entry goes to `route`
+pub fn has_route(entries: &[RoutedEntry], route: Route) -> bool {
+    entries.iter().any(|entry| entry.route == route)
+}
+
+// Copy the entries going to any of `routes` into the installed copy at `dest_dir`, replacing what is in
+// the way
+pub fn copy_routed(entries: &[RoutedEntry], routes: &[Route], dest_dir: &Path) -> Result<(), std::io::Error> {
+    for entry in entries.iter().filter(|entry| routes.contains(&entry.route)) {
+        let dest = dest_dir.join(&entry.dest);
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        overlay_entry(&entry.source, &dest)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_each_part_to_its_own_copy() {
+        let root = std::env::temp_dir().join(format!("reskin-routing-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for dir in ["gtk-3.0", "assets", "icons/48x48/apps", "cursors", "fonts"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["reskin.json", "README.md", "Nord.ttf", "fonts/Nord Mono.otf", "fonts/LICENSE", "icons/48x48/apps/term.svg"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join("index.theme"), "[Desktop Entry]\nType=X-GNOME-Metatheme\n").unwrap();
+        fs::write(root.join("icons/index.theme"), "[Icon Theme]\nName=Nord\n").unwrap();
+
+        let routes: Vec<(Route, String)> = route_theme(&root).into_iter()
+            .map(|entry| (entry.route, entry.dest.to_string_lossy().into_owned()))
+            .collect();
+        assert_eq!(routes, [
+            (Route::Fonts, "Nord.ttf".to_string()),
+            (Route::Other, "README.md".to_string()),
+            (Route::Other, "assets".to_string()),
+            (Route::Cursors, "cursors".to_string()),
+            (Route::Fonts, "Nord Mono.otf".to_string()),
+            (Route::Theme, "gtk-3.0".to_string()),
+            (Route::Icons, "48x48".to_string()),
+            (Route::Icons, "index.theme".to_string()),
+            (Route::Theme, "index.theme".to_string()),
+        ]);
+
+        // Deleted paths are routed by name alone
+        assert_eq!(route_path(&root, Path::new("icons/48x48/apps/old.svg")), [(Route::Icons, PathBuf::from("48x48/apps/old.svg"))]);
+        assert_eq!(route_path(&root, Path::new("scalable/apps/old.svg")), [(Route::Icons, PathBuf::from("scalable/apps/old.svg"))]);
+        assert_eq!(route_path(&root, Path::new("fonts/Old.ttf")), [(Route::Fonts, PathBuf::from("Old.ttf"))]);
+        assert!(route_path(&root, Path::new("reskin.json")).is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,157 @@
+// Import necessary crates
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use serde::{Serialize, Deserialize};
+use ed25519_dalek::SigningKey;
+use rand::rngs::OsRng;
+use crate::reskin_format::to_hex;
+use crate::locations::{config_dir, home_dir};
+use crate::verify::open_bundle;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TrustedKey { // Author key the user has chosen to trust
+    pub author: String, // Name shown when a bundle is signed with this key
+    pub public_key: String, // Hex-encoded Ed25519 public key
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SignatureStatus { // Outcome of checking a bundle signature against the trust store
+    Unsigned, // Bundle carries no signature
+    Trusted { author: String, public_key: String }, // Valid signature from a trusted key
+    UnknownKey { public_key: String }, // Valid signature from a key that is not in the trust store
+    Invalid, // Signature does not match the bundle
+}
+
+impl SignatureStatus {
+    pub fn describe(&self) -> String { // Human-readable summary for install results
+        match self {
+            SignatureStatus::Unsigned => "Unsigned".to_string(),
+            SignatureStatus::Trusted { author, .. } => format!("Signed by {} (trusted)", author),
+            SignatureStatus::UnknownKey { public_key } => format!("Signed by an unknown key ({})", public_key),
+            SignatureStatus::Invalid => "Bad signature".to_string(),
+        }
+    }
+}
+
+fn author_key_path() -> Result<PathBuf, String> { // File that stores the author's private signing key
+    Ok(config_dir(&home_dir()?).join("author.key"))
+}
+
+fn trust_store_path() -> Result<PathBuf, String> { // File that stores trusted author keys
+    Ok(config_dir(&home_dir()?).join("trusted_keys.json"))
+}
+
+fn parse_hex_key(hex: &str) -> Result<[u8; 32], String> { // Decode a 32-byte hex key
+    let hex = hex.trim();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err("Keys must be 64 hexadecimal characters".to_string());
+    }
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| "Keys must be 64 hexadecimal characters".to_string())?;
+    }
+    Ok(key)
+}
+
+// Load the author's signing key, if one has been generated
+pub fn load_author_key() -> Result<Option<SigningKey>, String> {
+    let path = author_key_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read signing key: {}", e))?; // Throw error on failure
+    Ok(Some(SigningKey::from_bytes(&parse_hex_key(&content)?)))
+}
+
+fn load_trust_store() -> Result<Vec<TrustedKey>, String> { // Read trusted_keys.json
+    let path = trust_store_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read trusted keys: {}", e))?; // Throw error on failure
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse trusted keys: {}", e))
+}
+
+fn save_trust_store(keys: &[TrustedKey]) -> Result<(), String> { // Write trusted_keys.json
+    fs::create_dir_all(config_dir(&home_dir()?)) // Create config directory and all necessary parent folders
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    let json = serde_json::to_string_pretty(keys)
+        .map_err(|e| format!("Failed to serialize trusted keys: {}", e))?;
+    fs::write(trust_store_path()?, json).map_err(|e| format!("Failed to write trusted keys: {}", e))
+}
+
+// Check a bundle's signature and look its key up in the trust store
+pub fn check_bundle_signature(bundle: &File) -> Result<SignatureStatus, String> {
+    let signature = match open_bundle(bundle)?.signature()? {
+        Some(signature) => signature,
+        None => return Ok(SignatureStatus::Unsigned),
+    };
+
+    if !signature.is_valid() {
+        return Ok(SignatureStatus::Invalid);
+    }
+
+    let public_key = to_hex(&signature.public_key);
+    let trusted = load_trust_store()?
+        .into_iter()
+        .find(|k| k.public_key.eq_ignore_ascii_case(&public_key));
+    Ok(match trusted {
+        Some(key) => SignatureStatus::Trusted { author: key.author, public_key },
+        None => SignatureStatus::UnknownKey { public_key },
+    })
+}
+
+#[tauri::command]
+pub fn get_author_public_key() -> Result<Option<String>, String> { // Public half of the author's signing key
+    Ok(load_author_key()?.map(|key| to_hex(key.verifying_key().as_bytes())))
+}
+
+#[tauri::command]
+pub fn generate_author_key() -> Result<String, String> { // Create a new signing key for bundles this user makes
+    let path = author_key_path()?;
+    if path.exists() {
+        return Err("A signing key already exists".to_string()); // Never silently replace an author's identity
+    }
+
+    fs::create_dir_all(config_dir(&home_dir()?)) // Create config directory and all necessary parent folders
+        .map_err(|e| format!("Failed to create config directory: {}", e))?;
+
+    let key = SigningKey::generate(&mut OsRng);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true) // Never silently replace an author's identity, even if one appeared since the check above
+        .mode(0o600) // Readable only by its owner from the moment it exists
+        .open(&path)
+        .map_err(|e| format!("Failed to create signing key: {}", e))?;
+    file.write_all(to_hex(&key.to_bytes()).as_bytes())
+        .map_err(|e| format!("Failed to write signing key: {}", e))?;
+
+    Ok(to_hex(key.verifying_key().as_bytes()))
+}
+
+#[tauri::command]
+pub fn list_trusted_keys() -> Result<Vec<TrustedKey>, String> { // All keys in the trust store
+    load_trust_store()
+}
+
+#[tauri::command]
+pub fn trust_author_key(author: String, public_key: String) -> Result<(), String> { // Add or rename a trusted key
+    let public_key = to_hex(&parse_hex_key(&public_key)?); // Normalize to lowercase
+    let mut keys = load_trust_store()?;
+    keys.retain(|k| !k.public_key.eq_ignore_ascii_case(&public_key));
+    keys.push(TrustedKey { author, public_key });
+    save_trust_store(&keys)
+}
+
+#[tauri::command]
+pub fn remove_trusted_key(public_key: String) -> Result<(), String> { // Stop trusting a key
+    let mut keys = load_trust_store()?;
+    keys.retain(|k| !k.public_key.eq_ignore_ascii_case(public_key.trim()));
+    save_trust_store(&keys)
+}
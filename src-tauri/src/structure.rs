@@ -0,0 +1,296 @@
+// Import necessary crates
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use crate::lint::Diagnostic;
+use crate::check::{has_cursors, has_icons};
+use crate::bundle::resolve_theme_assets;
+use crate::locations::home_dir;
+
+const XCURSOR_MAGIC: &[u8; 4] = b"Xcur"; // First bytes of every Xcursor file
+
+// Look for problems in the layout of a theme folder that the check::has_* heuristics let through
+pub fn structure_diagnostics(theme_root: &Path) -> Result<Vec<Diagnostic>, String> {
+    let mut diagnostics = Vec::new();
+    check_icon_theme(theme_root, &icon_search_dirs()?, &mut diagnostics);
+    check_cursors(theme_root, &mut diagnostics);
+    check_gtk(theme_root, &mut diagnostics);
+    check_shell(theme_root, &mut diagnostics);
+    check_symlinks(theme_root, &mut diagnostics)?;
+    Ok(diagnostics)
+}
+
+// Directories icon themes are looked up in, following the XDG icon theme spec
+fn icon_search_dirs() -> Result<Vec<PathBuf>, String> {
+    let home = home_dir()?;
+    let data_home = std::env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| format!("{}/.local/share", home));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+
+    let mut dirs = vec![PathBuf::from(&home).join(".icons"), PathBuf::from(data_home).join("icons")];
+    dirs.extend(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(|dir| PathBuf::from(dir).join("icons")));
+    dirs.push(PathBuf::from("/usr/share/pixmaps"));
+    Ok(dirs)
+}
+
+// Value of `key` in `[section]` of a desktop-entry style file such as index.theme
+fn ini_value<'c>(contents: &'c str, section: &str, key: &str) -> Option<&'c str> {
+    let mut in_section = false;
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line == format!("[{}]", section);
+        } else if in_section {
+            if let Some((name, value)) = line.split_once('=') {
+                if name.trim() == key {
+                    return Some(value.trim());
+                }
+            }
+        }
+    }
+    None
+}
+
+pub fn has_section(contents: &str, section: &str) -> bool {
+    contents.lines().any(|line| line.trim() == format!("[{}]", section))
+}
+
+fn list(value: Option<&str>) -> Vec<&str> { // Split a comma-separated index.theme value
+    value.unwrap_or("").split(',').map(str::trim).filter(|item| !item.is_empty()).collect()
+}
+
+fn check_icon_theme(theme_root: &Path, search_dirs: &[PathBuf], diagnostics: &mut Vec<Diagnostic>) {
+    let index_path = theme_root.join("index.theme");
+    if !index_path.is_file() {
+        // A folder of icon sizes without index.theme is ignored by every desktop
+        if has_icons(theme_root) {
+            diagnostics.push(Diagnostic::error("index.theme", "Icon theme has no index.theme"));
+        }
+        return;
+    }
+
+    let contents = match fs::read_to_string(&index_path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            diagnostics.push(Diagnostic::error("index.theme", format!("Cannot be read: {}", e)));
+            return;
+        }
+    };
+    if !has_section(&contents, "Icon Theme") {
+        diagnostics.push(Diagnostic::error("index.theme", "Has no [Icon Theme] section"));
+        return;
+    }
+
+    for directory in list(ini_value(&contents, "Icon Theme", "Directories")) {
+        if !theme_root.join(directory).is_dir() {
+            diagnostics.push(Diagnostic::warning("index.theme", format!("Lists directory {}, which is missing", directory)));
+        }
+    }
+
+    // Parents have to be installed already, unless they are hicolor, which every system ships
+    for parent in list(ini_value(&contents, "Icon Theme", "Inherits")) {
+        let installed = parent == "hicolor"
+            || search_dirs.iter().any(|dir| dir.join(parent).join("index.theme").is_file());
+        if !installed {
+            diagnostics.push(Diagnostic::warning("index.theme", format!("Inherits {}, which is not installed", parent)));
+        }
+    }
+}
+
+fn is_xcursor(path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_ok() && &magic == XCURSOR_MAGIC
+}
+
+fn check_cursors(theme_root: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    if !has_cursors(theme_root) {
+        return;
+    }
+    let cursors_dir = theme_root.join("cursors");
+    if !cursors_dir.is_dir() {
+        // A cursor.theme that only inherits another cursor theme needs no cursors of its own
+        let inherits = fs::read_to_string(theme_root.join("cursor.theme"))
+            .map(|contents| ini_value(&contents, "Icon Theme", "Inherits").is_some())
+            .unwrap_or(false);
+        if !inherits {
+            diagnostics.push(Diagnostic::error("cursors", "Cursor theme has no cursors folder"));
+        }
+        return;
+    }
+
+    let mut cursors = 0;
+    let mut others = Vec::new();
+    for entry in fs::read_dir(&cursors_dir).into_iter().flatten().flatten() {
+        if !entry.path().is_file() {
+            continue;
+        }
+        if is_xcursor(&entry.path()) {
+            cursors += 1;
+        } else {
+            others.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+
+    if cursors == 0 {
+        diagnostics.push(Diagnostic::error("cursors", "Contains no Xcursor files"));
+    } else if !others.is_empty() {
+        others.sort();
+        diagnostics.push(Diagnostic::warning("cursors", format!("{} files are not Xcursor files, e.g. {}", others.len(), others[0])));
+    }
+}
+
+fn check_gtk(theme_root: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    if theme_root.join("gtk-2.0").is_dir() && !theme_root.join("gtk-2.0/gtkrc").is_file() {
+        diagnostics.push(Diagnostic::error("gtk-2.0", "GTK 2 theme has no gtkrc"));
+    }
+    for version in ["gtk-3.0", "gtk-4.0"] {
+        let dir = theme_root.join(version);
+        if !dir.is_dir() {
+            continue;
+        }
+        let css = dir.join("gtk.css");
+        if css.is_file() {
+            check_css_imports(theme_root, &css, diagnostics);
+        } else {
+            diagnostics.push(Diagnostic::error(version, "Has no gtk.css"));
+        }
+        let dark = dir.join("gtk-dark.css");
+        if dark.is_file() {
+            check_css_imports(theme_root, &dark, diagnostics);
+        }
+    }
+}
+
+fn check_shell(theme_root: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    for (dir, stylesheet) in [("gnome-shell", "gnome-shell.css"), ("cinnamon", "cinnamon.css")] {
+        if theme_root.join(dir).is_dir() && !theme_root.join(dir).join(stylesheet).is_file() {
+            diagnostics.push(Diagnostic::error(dir, format!("Has no {}", stylesheet)));
+        }
+    }
+}
+
+// Warn about local stylesheets imported from `css` that are not in the theme
+fn check_css_imports(theme_root: &Path, css: &Path, diagnostics: &mut Vec<Diagnostic>) {
+    let Ok(contents) = fs::read_to_string(css) else {
+        return;
+    };
+    let subject = css.strip_prefix(theme_root).unwrap_or(css).to_string_lossy().into_owned();
+    for import in css_imports(&contents) {
+        if import.contains("://") { // resource:// and other URIs are resolved by GTK itself
+            continue;
+        }
+        let target = css.parent().unwrap_or(theme_root).join(&import);
+        if !target.is_file() {
+            diagnostics.push(Diagnostic::warning(&subject, format!("Imports {}, which is missing", import)));
+        }
+    }
+}
+
+// Targets of the @import rules in a stylesheet, written either as url(...) or as a quoted string
+fn css_imports(css: &str) -> Vec<String> {
+    css.split("@import").skip(1).filter_map(|rule| {
+        let rule = rule.trim_start();
+        let (rule, in_url) = match rule.strip_prefix("url(") {
+            Some(rest) => (rest.trim_start(), true),
+            None => (rule, false),
+        };
+        let target = match rule.chars().next()? {
+            quote @ ('"' | '\'') => rule[1..].split(quote).next()?,
+            _ if in_url => rule.split(')').next()?.trim(),
+            _ => return None,
+        };
+        Some(target.to_string())
+    }).collect()
+}
+
+fn check_symlinks(theme_root: &Path, diagnostics: &mut Vec<Diagnostic>) -> Result<(), String> {
+    for relative_path in resolve_theme_assets(theme_root, &[], &[])? {
+        let path = theme_root.join(&relative_path);
+        let is_symlink = fs::symlink_metadata(&path).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+        if is_symlink && fs::metadata(&path).is_err() {
+            let target = fs::read_link(&path).map(|t| t.display().to_string()).unwrap_or_default();
+            diagnostics.push(Diagnostic::warning(&relative_path, format!("Symlink points to {}, which does not exist", target)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lint::Severity;
+    use std::os::unix::fs::symlink;
+
+    fn theme_dir(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("reskin-structure-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+        diagnostics.iter().map(|d| format!("{:?} {}: {}", d.severity, d.subject, d.message)).collect()
+    }
+
+    #[test]
+    fn accepts_a_well_formed_theme() {
+        let root = theme_dir("good");
+        for dir in ["16x16/apps", "cursors", "gtk-3.0", "gnome-shell"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("index.theme"), "[Icon Theme]\nName=Good\nInherits=hicolor\nDirectories=16x16/apps\n").unwrap();
+        fs::write(root.join("cursors/left_ptr"), b"Xcur\x10\0\0\0").unwrap();
+        symlink("left_ptr", root.join("cursors/default")).unwrap();
+        fs::write(root.join("gtk-3.0/colors.css"), "").unwrap();
+        fs::write(root.join("gtk-3.0/gtk.css"), "@import url(\"colors.css\");\n@import url(\"resource:///org/gtk/x.css\");\n").unwrap();
+        fs::write(root.join("gnome-shell/gnome-shell.css"), "").unwrap();
+
+        assert_eq!(messages(&structure_diagnostics(&root).unwrap()), Vec::<String>::new());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn finds_broken_structure() {
+        let root = theme_dir("broken");
+        for dir in ["scalable", "cursors", "gtk-2.0", "gtk-4.0", "gnome-shell"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("cursors/left_ptr.png"), b"\x89PNG").unwrap();
+        fs::write(root.join("gtk-4.0/gtk-dark.css"), "@import 'missing.css';").unwrap();
+        symlink("nowhere.svg", root.join("scalable/broken.svg")).unwrap();
+
+        assert_eq!(messages(&structure_diagnostics(&root).unwrap()), [
+            "Error index.theme: Icon theme has no index.theme",
+            "Error cursors: Contains no Xcursor files",
+            "Error gtk-2.0: GTK 2 theme has no gtkrc",
+            "Error gtk-4.0: Has no gtk.css",
+            "Warning gtk-4.0/gtk-dark.css: Imports missing.css, which is missing",
+            "Error gnome-shell: Has no gnome-shell.css",
+            "Warning scalable/broken.svg: Symlink points to nowhere.svg, which does not exist",
+        ]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn checks_icon_theme_parents() {
+        let root = theme_dir("inherits");
+        let system = theme_dir("inherits-system");
+        fs::create_dir_all(system.join("Adwaita")).unwrap();
+        fs::write(system.join("Adwaita/index.theme"), "[Icon Theme]\nName=Adwaita\n").unwrap();
+        fs::write(root.join("index.theme"), "[Icon Theme]\nName=Child\nInherits=Adwaita, Papirus,hicolor\nDirectories=48x48\n").unwrap();
+
+        let mut diagnostics = Vec::new();
+        check_icon_theme(&root, std::slice::from_ref(&system), &mut diagnostics);
+        assert_eq!(messages(&diagnostics), [
+            "Warning index.theme: Lists directory 48x48, which is missing",
+            "Warning index.theme: Inherits Papirus, which is not installed",
+        ]);
+        assert!(diagnostics.iter().all(|d| d.severity == Severity::Warning));
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(&system);
+    }
+}
@@ -0,0 +1,241 @@
+// Import necessary crates
+use std::fs::{self, File, Permissions};
+use std::io;
+use std::os::unix::fs::{symlink, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Component, Path};
+use std::process::Command;
+use filetime::FileTime;
+use crate::extract::validate_theme_name;
+use crate::locations::InstallRoots;
+use crate::transaction::InstallTransaction;
+use crate::uninstall::remove_paths;
+
+const HELPER_FLAG: &str = "--reskin-system-helper"; // First argument that starts Reskin as the privileged helper
+
+// Folders every account on the machine reads themes from
+pub fn system_roots() -> InstallRoots {
+    InstallRoots {
+        themes: "/usr/share/themes".into(),
+        icons: "/usr/share/icons".into(),
+        fonts: "/usr/share/fonts".into(),
+    }
+}
+
+type HelperRun<'a> = Box<dyn Fn(&[String]) -> Result<Vec<String>, String> + 'a>; // Helper arguments in, printed lines out
+
+// Runs the helper with root rights. Tests swap pkexec for a stand-in that calls run_helper directly.
+pub struct Elevator<'a> {
+    pub roots: InstallRoots, // Folders the helper installs into
+    run: HelperRun<'a>,
+}
+
+impl<'a> Elevator<'a> {
+    pub fn new(roots: InstallRoots, run: impl Fn(&[String]) -> Result<Vec<String>, String> + 'a) -> Self {
+        Self { roots, run: Box::new(run) }
+    }
+
+    pub fn pkexec() -> Elevator<'static> { // The helper is this same executable, started again through polkit
+        Elevator::new(system_roots(), run_pkexec)
+    }
+
+    // Copy the themes, icons and fonts folders of `staging` into the roots; returns every path written
+    pub fn install(&self, staging: &Path) -> Result<Vec<String>, String> {
+        (self.run)(&["install".to_string(), staging.to_string_lossy().into_owned()])
+    }
+
+    // Delete recorded paths like uninstall does; returns how many were removed and the folders kept
+    pub fn uninstall(&self, files: &[String]) -> Result<(usize, Vec<String>), String> {
+        let args: Vec<String> = std::iter::once("uninstall".to_string()).chain(files.iter().cloned()).collect();
+        let mut output = (self.run)(&args)?.into_iter();
+        let removed = output.next()
+            .and_then(|count| count.parse().ok())
+            .ok_or("System install helper gave no result")?;
+        Ok((removed, output.collect()))
+    }
+}
+
+// pkexec looks up the polkit action by program path, so packaged installs at /usr/bin/reskin get the
+// com.reskin.reskinapp.system-install action from polkit/com.reskin.reskinapp.policy rather than the
+// generic org.freedesktop.policykit.exec one
+fn run_pkexec(args: &[String]) -> Result<Vec<String>, String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to find the Reskin executable: {}", e))?;
+    let output = Command::new("pkexec")
+        .arg(exe)
+        .arg(HELPER_FLAG)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run pkexec: {}", e))?;
+    match output.status.code() {
+        Some(0) => Ok(String::from_utf8_lossy(&output.stdout).split('\0').filter(|line| !line.is_empty()).map(str::to_string).collect()),
+        Some(126) | Some(127) => Err("Administrator authorization was refused".to_string()), // pkexec's codes for a dismissed or failed prompt
+        _ => Err(format!("System install helper failed: {}", String::from_utf8_lossy(&output.stderr).trim())),
+    }
+}
+
+// Entry point for the copy of Reskin started by pkexec; None when this is a normal start
+pub fn helper_main() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) != Some(HELPER_FLAG) {
+        return None;
+    }
+    match run_helper(&args[1..], &system_roots()) {
+        Ok(lines) => {
+            // NUL-separated, since file names can hold newlines
+            lines.iter().for_each(|line| print!("{}\0", line));
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            Some(1)
+        }
+    }
+}
+
+// What the helper does as root. Everything it writes or deletes stays inside `roots`.
+pub fn run_helper(args: &[String], roots: &InstallRoots) -> Result<Vec<String>, String> {
+    match args {
+        [command, staging] if command == "install" => helper_install(Path::new(staging), roots),
+        [command, files @ ..] if command == "uninstall" => {
+            if let Some(file) = files.iter().find(|file| !inside_roots(Path::new(file), roots)) {
+                return Err(format!("Refusing to delete {}, which is not in a system theme folder", file));
+            }
+            let (removed, kept) = remove_paths(files)?;
+            Ok(std::iter::once(removed.to_string()).chain(kept).collect())
+        }
+        _ => Err("Unknown system install helper command".to_string()),
+    }
+}
+
+fn helper_install(staging: &Path, roots: &InstallRoots) -> Result<Vec<String>, String> {
+    let owner = fs::symlink_metadata(staging).map_err(|e| format!("Failed to read {}: {}", staging.display(), e))?.uid();
+    let mut transaction = InstallTransaction::default(); // All parts go in together or not at all
+    for (folder, root) in [("themes", &roots.themes), ("icons", &roots.icons), ("fonts", &roots.fonts)] {
+        let Ok(entries) = fs::read_dir(staging.join(folder)) else {
+            continue; // Part the theme does not have
+        };
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read {}: {}", staging.display(), e))?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            validate_theme_name(&name).map_err(|e| e.to_string())?;
+            if !entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                return Err(format!("{} is not a folder", entry.path().display()));
+            }
+            transaction.replace_dir(&root.join(&name), |dest| {
+                copy_staged(&entry.path(), dest, owner).map_err(|e| format!("Failed to copy {}: {}", name, e))
+            })?;
+        }
+    }
+    let files = transaction.files.clone();
+    transaction.commit();
+    Ok(files)
+}
+
+// Copy a staged folder for every account to use. This runs as root on a folder the user controls, so links
+// are copied as links and never followed, only files of the account that staged them are read, and no mode
+// is taken from the source: folders get 0755 and files 0644, or 0755 when executable, so nothing is setuid.
+fn copy_staged(source: &Path, dest: &Path, owner: u32) -> io::Result<()> {
+    let dir = File::options().read(true).custom_flags(libc::O_NOFOLLOW | libc::O_DIRECTORY).open(source)?;
+    let metadata = dir.metadata()?;
+    check_owner(source, &metadata, owner)?;
+    fs::create_dir_all(dest)?;
+
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        let (source_path, dest_path) = (entry.path(), dest.join(entry.file_name()));
+        let file_type = entry.file_type()?; // Does not follow symlinks
+        if file_type.is_symlink() {
+            symlink(fs::read_link(&source_path)?, &dest_path)?;
+        } else if file_type.is_dir() {
+            copy_staged(&source_path, &dest_path, owner)?;
+        } else {
+            copy_staged_file(&source_path, &dest_path, owner)?;
+        }
+    }
+
+    // Folder metadata comes last, since copying into it changes its mtime
+    fs::set_permissions(dest, Permissions::from_mode(0o755))?;
+    filetime::set_file_mtime(dest, FileTime::from_last_modification_time(&metadata))
+}
+
+fn copy_staged_file(source: &Path, dest: &Path, owner: u32) -> io::Result<()> {
+    // Non-blocking, so a FIFO in place of a file cannot hang the helper
+    let mut file = File::options().read(true).custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK).open(source)?;
+    let metadata = file.metadata()?;
+    if !metadata.is_file() {
+        return Err(io::Error::other(format!("{} is not a regular file", source.display())));
+    }
+    check_owner(source, &metadata, owner)?;
+
+    let mode = if metadata.mode() & 0o111 != 0 { 0o755 } else { 0o644 };
+    let mut copy = File::options().write(true).create_new(true).mode(mode).open(dest)?;
+    io::copy(&mut file, &mut copy)?;
+    copy.set_permissions(Permissions::from_mode(mode))?; // The umask may have taken bits away
+    copy.set_modified(metadata.modified()?)
+}
+
+// A hard link to someone else's file would otherwise let the helper read it with root rights
+fn check_owner(path: &Path, metadata: &fs::Metadata, owner: u32) -> io::Result<()> {
+    if metadata.uid() == owner {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{} belongs to another account", path.display())))
+    }
+}
+
+// Whether `path` is below one of the roots, with no .. and no symlinked folder leading out of them
+fn inside_roots(path: &Path, roots: &InstallRoots) -> bool {
+    let plain = path.is_absolute() && path.components().all(|c| matches!(c, Component::RootDir | Component::Normal(_)));
+    let Some(root) = [&roots.themes, &roots.icons, &roots.fonts].into_iter().find(|root| path.starts_with(root) && path != root.as_path()) else {
+        return false;
+    };
+    // A missing parent means the path is already gone, and there is nothing to delete
+    let real_parent = path.parent().map(fs::canonicalize);
+    plain && match (real_parent, fs::canonicalize(root)) {
+        (Some(Ok(parent)), Ok(root)) => parent.starts_with(root),
+        (Some(Err(_)), _) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn helper_stays_inside_the_system_roots() {
+        let root = std::env::temp_dir().join(format!("reskin-system-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let roots = InstallRoots::resolve(&root.join("usr").to_string_lossy(), &Default::default(), None).unwrap();
+        let staging = root.join("staging");
+        fs::create_dir_all(staging.join("themes/Nordic/gtk-3.0")).unwrap();
+        fs::write(staging.join("themes/Nordic/gtk-3.0/gtk.css"), "").unwrap();
+        fs::write(staging.join("themes/Nordic/install.sh"), "").unwrap();
+        fs::set_permissions(staging.join("themes/Nordic/install.sh"), Permissions::from_mode(0o4775)).unwrap();
+        fs::set_permissions(staging.join("themes/Nordic/gtk-3.0/gtk.css"), Permissions::from_mode(0o600)).unwrap();
+        symlink("gtk-3.0", staging.join("themes/Nordic/gtk-4.0")).unwrap();
+        fs::create_dir_all(root.join("secret")).unwrap();
+        fs::write(root.join("secret/key"), "").unwrap();
+        let elevator = Elevator::new(roots.clone(), |args| run_helper(args, &roots));
+
+        let files = elevator.install(&staging).unwrap();
+        assert!(roots.themes.join("Nordic/gtk-3.0/gtk.css").is_file());
+        assert!(files.contains(&roots.themes.join("Nordic/gtk-3.0/gtk.css").to_string_lossy().into_owned()));
+        // Modes are set rather than copied, so neither setuid bits nor private files carry over
+        let mode = |path: &str| fs::symlink_metadata(roots.themes.join(path)).unwrap().mode() & 0o7777;
+        assert_eq!((mode("Nordic"), mode("Nordic/install.sh"), mode("Nordic/gtk-3.0/gtk.css")), (0o755, 0o755, 0o644));
+        assert_eq!(fs::read_link(roots.themes.join("Nordic/gtk-4.0")).unwrap(), Path::new("gtk-3.0"));
+
+        // Paths outside the roots are refused, whether plainly, through .. or through a link
+        std::os::unix::fs::symlink(root.join("secret"), roots.themes.join("Escape")).unwrap();
+        for path in [root.join("secret/key"), roots.themes.join("../../secret/key"), roots.themes.join("Escape/key"), roots.themes.clone()] {
+            assert!(elevator.uninstall(&[path.to_string_lossy().into_owned()]).is_err());
+        }
+        assert!(root.join("secret/key").is_file());
+
+        let (removed, kept) = elevator.uninstall(&files).unwrap();
+        assert_eq!((removed, kept.len()), (files.len(), 0));
+        assert!(!roots.themes.join("Nordic").exists());
+        assert!(run_helper(&["chmod".to_string()], &roots).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
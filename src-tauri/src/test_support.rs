@@ -0,0 +1,35 @@
+// Fixtures shared by the unit tests
+use std::fs;
+use std::path::PathBuf;
+use crate::reskin_format::{BundleWriter, Codec, EntryMetadata};
+use crate::types::ThemeManifest;
+
+// A manifest that passes validation. Tests override the fields they care about with `..test_manifest(name)`.
+pub fn test_manifest(name: &str) -> ThemeManifest {
+    ThemeManifest {
+        name: name.into(),
+        author: "Tester".into(),
+        description: "A test theme".into(),
+        version: "1.0.0".into(),
+        tags: vec!["dark".into()],
+        license: "MIT".into(),
+        ..Default::default()
+    }
+}
+
+// Bundle bytes holding each (path, data, codec) as a plain file
+pub fn bundle_bytes(manifest: &ThemeManifest, assets: &[(&str, &[u8], Codec)]) -> Vec<u8> {
+    let mut writer = BundleWriter::new(Vec::new(), manifest).unwrap();
+    for (path, data, codec) in assets {
+        writer.add_asset(path, EntryMetadata::default(), data.len() as u64, *data, *codec).unwrap();
+    }
+    writer.finish().unwrap()
+}
+
+// Fresh, empty folder for one test. Tests run in parallel, so `tag` must be unique across the crate.
+pub fn scratch_dir(tag: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("reskin-test-{}-{}", tag, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
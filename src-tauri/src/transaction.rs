@@ -0,0 +1,170 @@
+// Import necessary crates
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::registry::tree_paths;
+
+struct Swap { // One target that was replaced
+    target: PathBuf, // Where the new copy now is
+    backup: Option<PathBuf>, // Where the previous copy was moved to, None if there was nothing
+}
+
+// Everything one install run replaced. New copies are built next to their targets and renamed into
+// place, and the copies they replaced are kept until commit(). Dropping an uncommitted transaction
+// puts every target back the way it was.
+#[derive(Default)]
+pub struct InstallTransaction {
+    swaps: Vec<Swap>, // Targets replaced so far, oldest first
+    pub files: Vec<String>, // Every path written, for the install record
+    pub configs: Vec<String>, // Config files written, for the install record
+    committed: bool,
+}
+
+// Hidden path in the same folder as `target`, so renaming between the two never crosses file systems
+fn sibling(target: &Path, purpose: &str) -> PathBuf {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    target.with_file_name(format!(".{}.reskin-{}-{}", name, purpose, std::process::id()))
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+impl InstallTransaction {
+    // Build a new folder with `fill` next to `target` and swap it in for whatever is there
+    pub fn replace_dir(&mut self, target: &Path, fill: impl FnOnce(&Path) -> Result<(), String>) -> Result<(), String> {
+        let staging = sibling(target, "new");
+        self.stage(target, &staging)?;
+        fs::create_dir(&staging)
+            .map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+        if let Err(e) = fill(&staging) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        self.swap_in(&staging, target)?;
+        self.files.extend(tree_paths(target));
+        Ok(())
+    }
+
+    // Copy `source` over the file at `target` the same way, copying a symlink as the link itself. The
+    // caller records it, since config files are tracked apart from theme files.
+    pub fn replace_file(&mut self, target: &Path, source: &Path) -> Result<(), String> {
+        let staging = sibling(target, "new");
+        self.stage(target, &staging)?;
+        let copied = match fs::symlink_metadata(source) {
+            Ok(meta) if meta.file_type().is_symlink() => fs::read_link(source).and_then(|link| std::os::unix::fs::symlink(link, &staging)),
+            _ => fs::copy(source, &staging).map(|_| ()),
+        };
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&staging);
+            return Err(format!("Failed to copy {} to {}: {}", source.display(), target.display(), e));
+        }
+        self.swap_in(&staging, target)
+    }
+
+    // Make room for a new copy of `target`, clearing anything an interrupted run left behind
+    fn stage(&self, target: &Path, staging: &Path) -> Result<(), String> {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+        remove_path(staging).map_err(|e| format!("Failed to clear {}: {}", staging.display(), e))
+    }
+
+    fn swap_in(&mut self, staging: &Path, target: &Path) -> Result<(), String> {
+        let backup = if fs::symlink_metadata(target).is_ok() {
+            // Numbered, since a target replaced twice in one run needs a backup for each swap
+            let backup = sibling(target, &format!("old{}", self.swaps.len()));
+            remove_path(&backup).and_then(|_| fs::rename(target, &backup)).map_err(|e| {
+                let _ = remove_path(staging);
+                format!("Failed to move {} aside: {}", target.display(), e)
+            })?;
+            Some(backup)
+        } else {
+            None
+        };
+
+        if let Err(e) = fs::rename(staging, target) {
+            let _ = remove_path(staging);
+            if let Some(backup) = &backup {
+                let _ = fs::rename(backup, target);
+            }
+            return Err(format!("Failed to move {} into place: {}", target.display(), e));
+        }
+        self.swaps.push(Swap { target: target.to_path_buf(), backup });
+        Ok(())
+    }
+
+    // Keep the new copies and delete the ones they replaced
+    pub fn commit(mut self) {
+        self.committed = true;
+        for swap in &self.swaps {
+            if let Some(backup) = &swap.backup {
+                let _ = remove_path(backup);
+            }
+        }
+    }
+
+    // Undo every swap, newest first, so a target replaced twice ends up as the original
+    fn rollback(&mut self) {
+        while let Some(swap) = self.swaps.pop() {
+            let _ = remove_path(&swap.target);
+            if let Some(backup) = swap.backup {
+                let _ = fs::rename(backup, &swap.target);
+            }
+        }
+    }
+}
+
+impl Drop for InstallTransaction {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.rollback();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_back_everything_on_failure() {
+        let root = std::env::temp_dir().join(format!("reskin-transaction-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let theme = root.join("themes/Nordic");
+        fs::create_dir_all(&theme).unwrap();
+        fs::write(theme.join("gtk.css"), "old").unwrap();
+        let config = root.join("kitty.conf");
+        fs::write(&config, "old").unwrap();
+        let new_config = root.join("new.conf");
+        fs::write(&new_config, "new").unwrap();
+
+        // A failing component leaves the earlier ones and the original theme as they were
+        {
+            let mut transaction = InstallTransaction::default();
+            transaction.replace_dir(&theme, |dest| fs::write(dest.join("gtk.css"), "new").map_err(|e| e.to_string())).unwrap();
+            transaction.replace_file(&config, &new_config).unwrap();
+            transaction.replace_dir(&root.join("icons/Nordic"), |dest| fs::write(dest.join("index.theme"), "").map_err(|e| e.to_string())).unwrap();
+            assert_eq!(fs::read_to_string(theme.join("gtk.css")).unwrap(), "new");
+            assert!(transaction.replace_dir(&theme, |_| Err("copy failed".to_string())).is_err());
+        }
+        assert_eq!(fs::read_to_string(theme.join("gtk.css")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(&config).unwrap(), "old");
+        assert!(!root.join("icons/Nordic").exists());
+        assert_eq!(fs::read_dir(root.join("themes")).unwrap().count(), 1); // No staging or backup folders left
+
+        // Committing keeps the new copies and removes the old ones
+        let mut transaction = InstallTransaction::default();
+        transaction.replace_dir(&theme, |dest| fs::write(dest.join("gtk.css"), "new").map_err(|e| e.to_string())).unwrap();
+        transaction.replace_dir(&theme, |dest| fs::write(dest.join("gtk.css"), "newer").map_err(|e| e.to_string())).unwrap();
+        transaction.commit();
+        assert_eq!(fs::read_to_string(theme.join("gtk.css")).unwrap(), "newer");
+        assert_eq!(fs::read_dir(root.join("themes")).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,21 +1,166 @@
 // Import necessary crates
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, Deserializer};
 
-#[derive(Serialize, Deserialize, Clone)]
+pub const MANIFEST_VERSION: u32 = 2; // Manifest schema written by this build
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
 pub struct ThemeManifest { // Theme manifest
+    #[serde(default = "legacy_manifest_version")]
+    pub manifest_version: u32, // Manifest schema version, 1 for manifests from before it was recorded
     pub name: String, // Theme name
     pub author: String, // Theme author
     pub description: String, // Theme description
     pub version: String, // Theme version
-    pub tags: String, // Theme tags
+    #[serde(default, deserialize_with = "deserialize_tags")]
+    pub tags: Vec<String>, // Theme tags, stored as one comma-joined string in v1 manifests
     pub license: String, // Theme license
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub homepage: Option<String>, // Theme website
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>, // Source repository of the theme
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>, // Changelog URL or path inside the bundle
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<ThemeComponent>, // Components the author says the theme ships
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub desktops: Vec<DesktopSupport>, // Desktops the theme was made for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub screenshots: Vec<String>, // Screenshot URLs or paths inside the bundle
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_image: Option<String>, // Path inside the bundle of the image shown on theme cards
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_reskin_version: Option<String>, // Oldest Reskin release that can install the theme
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<ThemeVariant>, // Flavours of the theme shipped in this bundle, e.g. light and dark
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_variant: Option<String>, // Variant applied after installing, the first one if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pack: Option<RicePack>, // Themes and config files that together make up a whole desktop look
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<ThemeDelta>, // Set when the bundle only holds the changes since an earlier version
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeComponent { // Kind of asset a theme can ship
+    Gtk,
+    Icons,
+    Cursors,
+    Fonts,
+    Shell,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DesktopSupport { // Desktop environment a theme supports
+    pub name: String, // Desktop name as in XDG_CURRENT_DESKTOP, e.g. "GNOME" or "XFCE"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<String>, // Oldest supported release of that desktop
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ThemeVariant { // One flavour of a theme, installed as a theme of its own
+    pub name: String, // Short name such as "dark" or "compact"
+    pub path: String, // Directory inside the bundle that holds this variant
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<ThemeComponent>, // What to install from that directory, detected from the files when empty
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RicePack { // Several themes installed and applied together as one look
+    pub themes: Vec<PackTheme>, // Sub-themes, each installed under its own name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub configs: Vec<PackConfig>, // Config files copied into the user's home
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PackTheme { // One theme of a rice pack
+    pub name: String, // Name it is installed and applied under, e.g. "Papirus-Dark"
+    pub path: String, // Directory inside the bundle that holds it
+    pub component: ThemeComponent, // What kind of theme it is
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PackConfig { // Config file shipped in a rice pack
+    pub source: String, // File inside the bundle
+    pub target: String, // Where it goes in the home directory, e.g. "~/.config/kitty/kitty.conf"
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ThemeDelta { // What a delta bundle upgrades from and to; its assets are the changed and added ones
+    pub base_version: String, // Theme version the delta applies to
+    pub base_sha256: String, // Hex bundle hash of that version, which must be the one installed
+    pub target_sha256: String, // Hex bundle hash of the full bundle the delta was made from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deleted: Vec<String>, // Paths that are gone in the new version
+}
+
+// Name a variant is installed and applied under, e.g. "Nordic-dark"
+pub fn variant_theme_name(theme_name: &str, variant: &str) -> String {
+    format!("{}-{}", theme_name, variant)
+}
+
+fn legacy_manifest_version() -> u32 {
+    1
+}
+
+// Accept tags either as a list or as the comma-joined string v1 manifests used
+fn deserialize_tags<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Tags {
+        List(Vec<String>),
+        Joined(String),
+    }
+
+    let tags = match Tags::deserialize(deserializer)? {
+        Tags::List(tags) => tags,
+        Tags::Joined(joined) => joined.split(',').map(str::to_string).collect(),
+    };
+    Ok(tags.into_iter().map(|tag| tag.trim().to_string()).filter(|tag| !tag.is_empty()).collect())
+}
+
+impl ThemeManifest {
+    pub fn variant(&self, name: &str) -> Option<&ThemeVariant> { // Look up a variant by name
+        self.variants.iter().find(|variant| variant.name == name)
+    }
+
+    // Image inside the bundle to show for the theme: the preview, or else the first local screenshot
+    pub fn preview_path(&self) -> Option<&str> {
+        self.preview_image.as_deref()
+            .or_else(|| self.screenshots.iter().map(String::as_str).find(|s| !s.contains("://")))
+    }
+
+    // Minimum Reskin version the theme asks for, if this build is older than that
+    pub fn requires_newer_reskin(&self) -> Option<&str> {
+        let required = self.min_reskin_version.as_deref()?;
+        (version_numbers(required) > version_numbers(env!("CARGO_PKG_VERSION"))).then_some(required)
+    }
+}
+
+// Numeric parts of a dotted version such as "1.8.1" or "2.0.0-beta", for ordering versions
+pub fn version_numbers(version: &str) -> Vec<u64> {
+    let release = version.trim().trim_start_matches('v').split(['-', '+']).next().unwrap_or("");
+    let mut numbers: Vec<u64> = release.split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect();
+    while numbers.last() == Some(&0) { // "2.0" and "2.0.0" are the same version
+        numbers.pop();
+    }
+    numbers
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct BundleRequest { // Data to bundle the theme with
     pub manifest: ThemeManifest, // Theme manifest
     pub output_path: String, // Output for the bundled .reskin file
     pub assets: Vec<String>, // Theme assets
     pub theme_directory: Option<String>, // Directory where theme files are located
+    #[serde(default)]
+    pub include: Vec<String>, // Only bundle entries matching one of these patterns, unless empty
+    #[serde(default)]
+    pub exclude: Vec<String>, // Extra .reskinignore-style patterns to leave out
+    #[serde(default)]
+    pub normalize_metadata: bool, // Record 644/755 modes and one fixed mtime instead of what is on disk
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -24,4 +169,69 @@ pub struct RecentTheme { // Entry to recently installed themes
     pub author: String, // Theme author
     pub description: String, // Theme description
     pub installed_at: u64, // Theme installation Unix timestamp
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InstallScope { // Who a theme is installed for
+    #[default]
+    User, // The current account, under the install roots in Settings
+    System, // Every account, under /usr/share through the pkexec helper
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct InstalledTheme { // Record of an installed theme, kept under ~/.config/reskin/installed
+    pub manifest: ThemeManifest, // Manifest it was installed with, which also says where its parts went
+    pub bundle_sha256: Option<String>, // Hex hash of the bundle it came from, None for folders and legacy bundles
+    pub installed_at: u64, // Installation Unix timestamp
+    #[serde(default)]
+    pub files: Vec<String>, // Every file, link and folder the install created, as absolute paths
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub configs: Vec<String>, // Config files a pack wrote, put back from their .bak copies on uninstall
+    #[serde(default)]
+    pub scope: InstallScope, // System installs are removed through the pkexec helper
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_v1_manifests() {
+        let json = r#"{"name":"Old","author":"A","description":"D","version":"1.0","tags":"dark, flat,,","license":"MIT"}"#;
+        let manifest: ThemeManifest = serde_json::from_str(json).unwrap();
+        assert_eq!(manifest.manifest_version, 1);
+        assert_eq!(manifest.tags, ["dark", "flat"]);
+        assert!(manifest.components.is_empty() && manifest.homepage.is_none());
+    }
+
+    #[test]
+    fn round_trips_v2_manifests() {
+        let manifest = ThemeManifest {
+            manifest_version: MANIFEST_VERSION,
+            name: "New".into(),
+            tags: vec!["dark".into(), "nord".into()],
+            homepage: Some("https://example.org".into()),
+            components: vec![ThemeComponent::Gtk, ThemeComponent::Icons],
+            desktops: vec![DesktopSupport { name: "GNOME".into(), min_version: Some("45".into()) }],
+            screenshots: vec!["screenshots/desktop.png".into()],
+            min_reskin_version: Some("1.8".into()),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert!(json.contains(r#""tags":["dark","nord"]"#) && json.contains(r#""components":["gtk","icons"]"#));
+        assert!(!json.contains("repository")); // Unset optional fields are left out
+        assert_eq!(serde_json::from_str::<ThemeManifest>(&json).unwrap(), manifest);
+    }
+
+    #[test]
+    fn compares_minimum_reskin_versions() {
+        assert!(version_numbers("2.0") == version_numbers("v2.0.0-rc1"));
+        assert!(version_numbers("1.10.0") > version_numbers("1.9.3"));
+
+        let mut manifest = ThemeManifest { min_reskin_version: Some("1.0".into()), ..Default::default() };
+        assert_eq!(manifest.requires_newer_reskin(), None);
+        manifest.min_reskin_version = Some("999.0".into());
+        assert_eq!(manifest.requires_newer_reskin(), Some("999.0"));
+    }
+}
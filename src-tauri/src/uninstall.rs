@@ -0,0 +1,141 @@
+// Import necessary crates
+use std::fs;
+use std::path::Path;
+use crate::locations::home_dir;
+use crate::pack::forget_installed_pack;
+use crate::recent::remove_recent_theme;
+use crate::registry::{forget_installed_theme, load_installed_theme};
+use crate::system::Elevator;
+use crate::types::InstallScope;
+
+#[tauri::command]
+pub fn uninstall_theme(name: String) -> Result<String, String> {
+    // Remove exactly what installing the theme wrote, as listed in its install record
+    let home_dir = home_dir()?;
+    let message = uninstall(&home_dir, &name, &Elevator::pkexec())?;
+    remove_recent_theme(&name)?;
+    Ok(message)
+}
+
+// Delete the recorded files of an installed theme. Folders that still hold files Reskin did not
+// put there are left alone, and config files a pack replaced get their .bak copies back. System-wide
+// installs are deleted by the helper `elevator` runs.
+pub fn uninstall(home_dir: &str, theme_name: &str, elevator: &Elevator) -> Result<String, String> {
+    let record = load_installed_theme(home_dir, theme_name)?
+        .ok_or_else(|| format!("Theme '{}' was not installed by Reskin, so there is no record of its files", theme_name))?;
+
+    for config in &record.configs {
+        restore_config(Path::new(config))?;
+    }
+
+    let (removed, kept) = match record.scope {
+        InstallScope::User => remove_paths(&record.files)?,
+        InstallScope::System => elevator.uninstall(&record.files)?,
+    };
+
+    forget_installed_pack(home_dir, theme_name);
+    forget_installed_theme(home_dir, theme_name);
+
+    let mut message = format!("Theme '{}' uninstalled\nRemoved {} files", theme_name, removed);
+    if !record.configs.is_empty() {
+        message.push_str(&format!("\nRestored {} config files", record.configs.len()));
+    }
+    if !kept.is_empty() {
+        message.push_str(&format!("\nKept folders with other files in them: {}", kept.join(", ")));
+    }
+    Ok(message)
+}
+
+// Delete files and then their folders, keeping folders that are not empty. Returns how many paths were
+// removed and the folders that were kept.
+pub fn remove_paths(files: &[String]) -> Result<(usize, Vec<String>), String> {
+    // Children sort after their parents, so going backwards empties each folder before it is reached
+    let mut files = files.to_vec();
+    files.sort();
+    let (mut removed, mut kept) = (0, Vec::new());
+    for file in files.iter().rev() {
+        let path = Path::new(file);
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.is_dir() => match fs::remove_dir(path) {
+                Ok(()) => removed += 1,
+                Err(_) => kept.push(file.clone()),
+            },
+            Ok(_) => {
+                fs::remove_file(path).map_err(|e| format!("Failed to delete {}: {}", file, e))?;
+                removed += 1;
+            }
+            Err(_) => {} // Already gone
+        }
+    }
+    Ok((removed, kept))
+}
+
+// Put back the file a pack config replaced, or remove the config if there was nothing before it
+fn restore_config(target: &Path) -> Result<(), String> {
+    let mut backup = target.as_os_str().to_owned();
+    backup.push(".bak");
+    if fs::symlink_metadata(&backup).is_ok() { // The backup may be a symlink, which is restored as one
+        fs::rename(&backup, target).map_err(|e| format!("Failed to restore {}: {}", target.display(), e))
+    } else {
+        match fs::remove_file(target) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to delete {}: {}", target.display(), e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::install::install_pack_contents;
+    use crate::locations::InstallRoots;
+    use crate::transaction::InstallTransaction;
+    use crate::registry::record_install;
+    use crate::types::{RicePack, ThemeManifest};
+
+    #[test]
+    fn uninstalls_exactly_the_recorded_files() {
+        let root = std::env::temp_dir().join(format!("reskin-uninstall-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let bundle = root.join("bundle");
+        fs::create_dir_all(bundle.join("gtk/gtk-3.0")).unwrap();
+        fs::create_dir_all(bundle.join("dotfiles")).unwrap();
+        fs::write(bundle.join("gtk/gtk-3.0/gtk.css"), "").unwrap();
+        fs::write(bundle.join("dotfiles/kitty.conf"), "background #2e3440\n").unwrap();
+        fs::write(bundle.join("dotfiles/alacritty.toml"), "").unwrap();
+        let home = root.join("home");
+        let home_dir = home.to_string_lossy();
+        fs::create_dir_all(home.join(".config/kitty")).unwrap();
+        fs::write(home.join(".config/kitty/kitty.conf"), "background #000000\n").unwrap();
+
+        let pack: RicePack = serde_json::from_str(r#"{
+            "themes": [{"name": "Nord", "path": "gtk", "component": "gtk"}],
+            "configs": [
+                {"source": "dotfiles/kitty.conf", "target": "~/.config/kitty/kitty.conf"},
+                {"source": "dotfiles/alacritty.toml", "target": "~/.config/alacritty/alacritty.toml"}
+            ]
+        }"#).unwrap();
+        let mut transaction = InstallTransaction::default();
+        let roots = InstallRoots::resolve(&home_dir, &Default::default(), None).unwrap();
+        install_pack_contents(&bundle, "Nord Rice", &pack, &home_dir, &roots, &mut transaction).unwrap();
+        let manifest = ThemeManifest { name: "Nord Rice".into(), pack: Some(pack), ..Default::default() };
+        record_install(&home_dir, "Nord Rice", &manifest, InstallScope::User, transaction.files.clone(), transaction.configs.clone()).unwrap();
+        transaction.commit();
+
+        // A file the user added inside the theme keeps its folder
+        fs::write(roots.themes.join("Nord/gtk-3.0/custom.css"), "").unwrap();
+        let elevator = Elevator::new(roots.clone(), |_| Err("Not a system install".to_string())); // Never asked for
+        let message = uninstall(&home_dir, "Nord Rice", &elevator).unwrap();
+        assert!(message.contains("Kept folders"), "{}", message);
+
+        assert!(!roots.themes.join("Nord/gtk-3.0/gtk.css").exists() && !roots.themes.join("Nord/index.theme").exists());
+        assert!(roots.themes.join("Nord/gtk-3.0/custom.css").is_file());
+        assert_eq!(fs::read_to_string(home.join(".config/kitty/kitty.conf")).unwrap(), "background #000000\n");
+        assert!(!home.join(".config/kitty/kitty.conf.bak").exists());
+        assert!(!home.join(".config/alacritty/alacritty.toml").exists());
+        assert!(load_installed_theme(&home_dir, "Nord Rice").unwrap().is_none());
+        assert!(crate::pack::load_installed_pack(&home_dir, "Nord Rice").unwrap().is_none());
+        assert!(uninstall(&home_dir, "Nord Rice", &elevator).is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -1,56 +1,26 @@
 // Import necessary crates
 use std::fs;
-use std::path::PathBuf;
-use dirs::home_dir;
+use std::path::{Path, PathBuf};
+use crate::locations::home_dir;
+use filetime::FileTime;
 
-pub fn install_icons(staging_dir: &str, theme_name: &str, home_dir: &str) -> Result<(), String> { // Function to install icons included in the theme
-    let icons_dir = format!("{}/.local/share/icons", home_dir);
-    let dest_dir = format!("{}/{}", icons_dir, theme_name);
-    
-    fs::create_dir_all(&icons_dir).map_err(|e| format!("Failed to create icons directory: {}", e))?;
-        
-    if std::path::Path::new(&dest_dir).exists() {
-        fs::remove_dir_all(&dest_dir).map_err(|e| format!("Failed to remove existing icons: {}", e))?;
-    }
-    
-    copy_dir_recursive(staging_dir, &dest_dir).map_err(|e| format!("Failed to install icons: {}", e))?;
-    Ok(())
-}
-
-pub fn install_cursors(staging_dir: &str, theme_name: &str, home_dir: &str) -> Result<(), String> { // Function to install cursors included in the theme
-    let cursors_dir = format!("{}/.local/share/icons", home_dir);
-    let dest_dir = format!("{}/{}", cursors_dir, theme_name);
-    
-    fs::create_dir_all(&cursors_dir).map_err(|e| format!("Failed to create cursors directory: {}", e))?;
-        
-    if std::path::Path::new(&dest_dir).exists() {
-        fs::remove_dir_all(&dest_dir).map_err(|e| format!("Failed to remove existing cursors: {}", e))?;
+pub fn install_cursor_index(theme_name: &str, dest_dir: &Path) -> Result<(), String> { // Give a cursor theme the index.theme it needs to be listed
+    let index = dest_dir.join("index.theme");
+    if index.exists() {
+        return Ok(()); // The icon theme installed alongside already has one
     }
-    
-    copy_dir_recursive(staging_dir, &dest_dir).map_err(|e| format!("Failed to install cursors: {}", e))?;
-    Ok(())
+    fs::write(&index, format!("[Icon Theme]\nName={}\nComment={} cursors\n", theme_name, theme_name))
+        .map_err(|e| format!("Failed to write {}: {}", index.display(), e))
 }
 
-pub fn install_fonts(staging_dir: &str, theme_name: &str, home_dir: &str) -> Result<(), String> { // Function to install fonts included in the theme
-    let fonts_dir = format!("{}/.local/share/fonts/{}", home_dir, theme_name);
-    fs::create_dir_all(&fonts_dir).map_err(|e| format!("Failed to create fonts directory: {}", e))?;
-        
-    if let Ok(entries) = fs::read_dir(staging_dir) {
-        for entry in entries.flatten() {
-            if let Some(ext) = entry.path().extension() {
-                if ["ttf", "otf", "woff", "woff2", "eot"].contains(&ext.to_str().unwrap_or("")) {
-                    let dest_path = std::path::Path::new(&fonts_dir).join(entry.file_name());
-                    fs::copy(entry.path(), dest_path).map_err(|e| format!("Failed to copy font: {}", e))?;
-                }
-            }
-        }
-    }
-    
-    Ok(())
+pub fn is_font_file(path: &std::path::Path) -> bool { // Font files install_fonts picks up
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ["ttf", "otf", "woff", "woff2", "eot"].contains(&ext))
 }
 
 #[allow(non_snake_case)]
-pub fn copy_dir_recursive(src: &str, dst: &str) -> Result<(), std::io::Error> {
+pub fn copy_dir_recursive(src: &str, dst: &str) -> Result<(), std::io::Error> { // Copy a tree, keeping symlinks, modes and mtimes
     use std::path::Path;
     
     fs::create_dir_all(dst)?;
@@ -59,21 +29,67 @@ pub fn copy_dir_recursive(src: &str, dst: &str) -> Result<(), std::io::Error> {
         let entry = entry?;
         let srcPath = entry.path();
         let dst_path = Path::new(dst).join(entry.file_name());
+        let file_type = entry.file_type()?;
         
-        if srcPath.is_dir() {
+        if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(&srcPath)?, &dst_path)?; // Recreate the link instead of copying its target
+        } else if file_type.is_dir() {
             copy_dir_recursive(&srcPath.to_string_lossy(), &dst_path.to_string_lossy())?;
         } else {
-            fs::copy(&srcPath, &dst_path)?;
+            fs::copy(&srcPath, &dst_path)?; // Also copies the permission bits
         }
+        copy_mtime(&srcPath, &dst_path)?;
     }
     
+    // Directory metadata comes last, since copying into it changes its mtime
+    fs::set_permissions(dst, fs::metadata(src)?.permissions())?;
+    copy_mtime(Path::new(src), Path::new(dst))
+}
+
+pub fn overlay_dir(src: &str, dst: &str) -> Result<(), std::io::Error> { // Copy a tree over an existing one, replacing whatever is in the way
+    use std::path::Path;
+
+    fs::create_dir_all(dst)?;
+
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        overlay_entry(&entry.path(), &Path::new(dst).join(entry.file_name()))?;
+    }
+
     Ok(())
 }
 
+pub fn overlay_entry(src_path: &Path, dst_path: &Path) -> Result<(), std::io::Error> { // Copy one file, link or tree over whatever is at dst_path
+    let file_type = fs::symlink_metadata(src_path)?.file_type();
+
+    // Directories are merged; anything else at the destination is replaced, and a symlink is never written through
+    match fs::symlink_metadata(dst_path) {
+        Ok(meta) if meta.is_dir() && file_type.is_dir() => {}
+        Ok(meta) if meta.is_dir() => fs::remove_dir_all(dst_path)?,
+        Ok(_) => fs::remove_file(dst_path)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    if file_type.is_symlink() {
+        std::os::unix::fs::symlink(fs::read_link(src_path)?, dst_path)?;
+    } else if file_type.is_dir() {
+        overlay_dir(&src_path.to_string_lossy(), &dst_path.to_string_lossy())?;
+    } else {
+        fs::copy(src_path, dst_path)?;
+    }
+    copy_mtime(src_path, dst_path)
+}
+
+fn copy_mtime(src: &std::path::Path, dst: &std::path::Path) -> Result<(), std::io::Error> { // Copy a modification time without following symlinks
+    let mtime = FileTime::from_last_modification_time(&fs::symlink_metadata(src)?);
+    filetime::set_symlink_file_times(dst, mtime, mtime)
+}
+
 #[tauri::command]
 pub fn apply_config_file(file_data: Vec<u8>, file_name: String, dest_path: String) -> Result<String, String> { // Function to apply configuration file
     let path = if dest_path.starts_with("~") {
-        let mut home = home_dir().ok_or("Could not determine home directory")?;
+        let mut home = PathBuf::from(home_dir()?);
         home.push(&dest_path[2..]);
         home
     } else {
@@ -0,0 +1,39 @@
+// Import necessary crates
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use crate::reskin_format::{BundleReader, VerifyReport};
+
+#[tauri::command]
+pub fn verify_bundle(bundle_path: String) -> Result<VerifyReport, String> {
+    // Check every asset checksum and the whole-bundle hash
+    let file = File::open(&bundle_path)
+        .map_err(|e| format!("Failed to open bundle: {}", e))?;
+    let mut reader = BundleReader::new(BufReader::new(file))?;
+    reader.verify()
+}
+
+// Read a bundle from the start of a file that is already open. Checks that share one handle all see the
+// same file, even if the path is pointed somewhere else in between.
+pub fn open_bundle(file: &File) -> Result<BundleReader<BufReader<&File>>, String> {
+    let mut file = file;
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to seek in bundle: {}", e))?;
+    BundleReader::new(BufReader::new(file))
+}
+
+// Refuse to continue with a bundle that fails verification
+pub fn ensure_bundle_is_valid(bundle: &File) -> Result<(), String> {
+    let report = open_bundle(bundle)?.verify()?;
+    if report.valid {
+        return Ok(());
+    }
+
+    let problems: Vec<String> = report.problems
+        .iter()
+        .map(|p| match &p.entry {
+            Some(entry) => format!(" - {}: {}", entry, p.message),
+            None => format!(" - {}", p.message),
+        })
+        .collect();
+    Err(format!("Bundle failed verification:\n{}", problems.join("\n")))
+}
@@ -8,7 +8,7 @@ import Titlebar from "./Titlebar";
 import React, { useEffect, useState, useRef, useLayoutEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import ThemeDetails from "./ThemeDetails";
-import Settings from "./Settings";
+import Settings, { migrateInstallLocation } from "./Settings";
 import UploadTheme from "./UploadTheme";
 import Marketplace from "./Marketplace";
 import ConfigurationFiles from "./ConfigurationFiles";
@@ -32,16 +32,16 @@ export default function App(props) {
   // Get recently installed themes from localStorage
   useEffect(() => {
 	invoke("ensure_reskin_folder").catch(() => {}); // Ensure /tmp/reskin exists
+	migrateInstallLocation().catch(() => {}); // Installs must go to the folder picked in older versions
 	(async () => {
 	  try {
 		const recent = await invoke("get_recent_themes");
-		const homeDir = window.__TAURI__ ? await invoke('get_home_dir') : '';
 		const hydrated = await Promise.all(
 		  (recent || []).map(async (t) => {
 			try {
-			  const bundlePath = `${homeDir}/.themes/${t.name}/reskin.json`;
-			  const loaded = await invoke('extract_theme_info_from_file', { filePath: bundlePath });
-			  // Use manifest from ~/.themes
+			  const loaded = await invoke('get_installed_theme', { name: t.name });
+			  if (!loaded) throw new Error("Theme is not installed");
+			  // Use the manifest recorded at install time
 			  return { ...loaded, installs: t.installs, installed_at: t.installed_at };
 			} catch {
 			  return { name: t.name, author: t.author, description: t.description, installs: t.installs, installed_at: t.installed_at };
@@ -63,8 +63,8 @@ export default function App(props) {
   const addToRecentlyInstalled = async (theme) => {
 	let manifest = null;
 	try {
-	  const bundlePath = `${window.__TAURI__ ? await invoke('get_home_dir') : ''}/.themes/${theme.name}/${theme.name}.reskin`;
-	  const loaded = await invoke('extract_theme_info_from_file', { filePath: bundlePath });
+	  const loaded = await invoke('get_installed_theme', { name: theme.name });
+	  if (!loaded) throw new Error("Theme is not installed");
 	  manifest = { ...loaded };
 	} catch (e) {
 	  manifest = { name: theme.name, author: theme.author, description: theme.description };
@@ -98,6 +98,7 @@ export default function App(props) {
 		<ThemeDetails
 		  theme={selectedTheme}
 		  onBack={() => setCurrentView('home')}
+		  onUninstalled={(name) => setInstalledThemes(prev => prev.filter(t => t.name !== name))}
 		/>
 	  );
 	case 'settings':
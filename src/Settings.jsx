@@ -4,16 +4,33 @@ import { invoke } from "@tauri-apps/api/core";
 import "./Settings.css";
 import { getTranslationObject, getLanguageOptions } from "./locales/index.js";
 
+// Move the themes folder older versions kept in localStorage to the backend, which installs there now.
+// It is only sent while the backend has no themes folder of its own, and the old key is dropped after.
+export async function migrateInstallLocation() {
+    const settings = await invoke("get_install_locations");
+    const legacy = localStorage.getItem("reskin_install_location");
+    if (legacy === null) return settings;
+    localStorage.removeItem("reskin_install_location"); // A folder the backend refuses would be refused every time
+    if (settings.locations.themes) return settings;
+    try {
+        return await invoke("set_install_locations", { locations: { ...settings.locations, themes: legacy } });
+    } catch (err) {
+        console.error("Failed to move the install location to the backend:", err); // Throw error on failure
+        return settings;
+    }
+}
+
 export default function Settings() {
-    const [installLocation, setInstallLocation] = useState(
-        localStorage.getItem("reskin_install_location") || "~/.themes" // Install location for themes
-    );
+    const [installLocations, setInstallLocations] = useState({ themes: "", icons: "", fonts: "" }); // Install folders, blank for the default
+    const [installRoots, setInstallRoots] = useState(null); // Folders installs currently go to
     const [autoApply, setAutoApply] = useState(
         localStorage.getItem("reskin_auto_apply") === "true" // Automatically apply themes after installation
     );
     const [backupConfig, setBackupConfig] = useState(
         localStorage.getItem("reskin_backup_config") === "true" // Back up current configuration file before applying a new one
     );
+    const [installPolicy, setInstallPolicy] = useState({ require_signed: false, scope: "user" }); // Signature and scope rules the backend installs with
+    const [authorKey, setAuthorKey] = useState(null); // Public key used to sign bundles made on this machine
     const [theme, setTheme] = useState(
         localStorage.getItem("reskin_theme") || "dark" // Application theme
     )
@@ -37,13 +54,56 @@ export default function Settings() {
             }
         };
         getVersion();
+        invoke("get_author_public_key").then(setAuthorKey).catch(() => setAuthorKey(null)); // Get author signing key
+        migrateInstallLocation().then(showInstallLocations).catch((err) => {
+            console.error("Failed to get install locations:", err); // Throw error on failure
+        });
+        invoke("get_install_policy").then(setInstallPolicy).catch((err) => {
+            console.error("Failed to get install policy:", err); // Throw error on failure
+        });
     }, []);
 
-    useEffect(() => { // Set localStorage entries for the settings to match the frontend
-        localStorage.setItem("reskin_install_location", installLocation);
-    }, [installLocation]);
+    const showInstallLocations = (settings) => { // Fill the install folder fields from the backend
+        setInstallLocations({
+            themes: settings.locations.themes || "",
+            icons: settings.locations.icons || "",
+            fonts: settings.locations.fonts || "",
+        });
+        setInstallRoots(settings.roots);
+    };
 
-    useEffect(() => {
+    const saveInstallLocations = async () => { // The backend installs to these folders, so they are stored there
+        try {
+            showInstallLocations(await invoke("set_install_locations", {
+                locations: {
+                    themes: installLocations.themes || null,
+                    icons: installLocations.icons || null,
+                    fonts: installLocations.fonts || null,
+                },
+            }));
+        } catch (err) {
+            console.error("Failed to save install locations:", err); // Throw error on failure
+            alert(t.settings.status["status.install_location_failure"].replace("{error}", err?.toString() || ""));
+        }
+    };
+
+    const saveInstallPolicy = async (changes) => { // Every install path reads these from the backend
+        try {
+            setInstallPolicy(await invoke("set_install_policy", { policy: { ...installPolicy, ...changes } }));
+        } catch (err) {
+            console.error("Failed to save install policy:", err); // Throw error on failure
+        }
+    };
+
+    const handleGenerateKey = async () => { // Create a signing key for bundles made on this machine
+        try {
+            setAuthorKey(await invoke("generate_author_key"));
+        } catch (err) {
+            console.error("Failed to generate signing key:", err); // Throw error on failure
+        }
+    };
+
+    useEffect(() => { // Set localStorage entries for the settings to match the frontend
         localStorage.setItem("reskin_auto_apply", autoApply.toString());
     }, [autoApply]);
 
@@ -71,17 +131,25 @@ export default function Settings() {
             <h2>{t.settings["title"]}</h2>
             <div className="settings-section">
                 <h3>{t.settings.section["section.general"]}</h3>
-                <div className="settings-row">
-                    <label htmlFor="installLocation" title={t.settings.tooltip["tooltip.install_location"]}>
-                        {t.settings.label["label.install_location"]}
-                    </label>
-                    <input
-                        id="installLocation"
-                        type="text"
-                        value={installLocation}
-                        onChange={(e) => setInstallLocation(e.target.value)}
-                    />
-                </div>
+                {[
+                    ["themes", "install_location"],
+                    ["icons", "icon_location"],
+                    ["fonts", "font_location"],
+                ].map(([kind, key]) => (
+                    <div className="settings-row" key={kind}>
+                        <label htmlFor={`installLocation-${kind}`} title={t.settings.tooltip[`tooltip.${key}`]}>
+                            {t.settings.label[`label.${key}`]}
+                        </label>
+                        <input
+                            id={`installLocation-${kind}`}
+                            type="text"
+                            value={installLocations[kind]}
+                            placeholder={installRoots?.[kind] || ""} // Show where installs go when left blank
+                            onChange={(e) => setInstallLocations({ ...installLocations, [kind]: e.target.value })}
+                            onBlur={saveInstallLocations}
+                        />
+                    </div>
+                ))}
                 <div className="settings-row">
                     <label htmlFor="autoApply" title={t.settings.tooltip["tooltip.auto_apply"]}>
                         {t.settings.label["label.auto_apply"]}
@@ -104,6 +172,38 @@ export default function Settings() {
                         onChange={(e) => setBackupConfig(e.target.checked)}
                     />
                 </div>
+                <div className="settings-row">
+                    <label htmlFor="requireSigned" title={t.settings.tooltip["tooltip.require_signed"]}>
+                        {t.settings.label["label.require_signed"]}
+                    </label>
+                    <input
+                        id="requireSigned"
+                        type="checkbox"
+                        checked={installPolicy.require_signed}
+                        onChange={(e) => saveInstallPolicy({ require_signed: e.target.checked })}
+                    />
+                </div>
+                <div className="settings-row">
+                    <label htmlFor="systemWide" title={t.settings.tooltip["tooltip.system_wide"]}>
+                        {t.settings.label["label.system_wide"]}
+                    </label>
+                    <input
+                        id="systemWide"
+                        type="checkbox"
+                        checked={installPolicy.scope === "system"}
+                        onChange={(e) => saveInstallPolicy({ scope: e.target.checked ? "system" : "user" })}
+                    />
+                </div>
+                <div className="settings-row">
+                    <span title={t.settings.tooltip["tooltip.signing_key"]}>
+                        {t.settings.label["label.signing_key"]}
+                    </span>
+                    {authorKey ? (
+                        <code style={{ wordBreak: "break-all", maxWidth: "60%" }}>{authorKey}</code>
+                    ) : (
+                        <button onClick={handleGenerateKey}>{t.settings.label["label.generate_key"]}</button>
+                    )}
+                </div>
                 <div className="settings-row">
                     <label htmlFor="theme" title={t.settings.tooltip["tooltip.theme"]}>
                         {t.settings.label["label.theme"]}
@@ -1,6 +1,7 @@
 // Import necessary components
 import React, { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import "./ThemeBundler.css";
 import { getTranslationObject } from "./locales/index.js";
 
@@ -27,11 +28,21 @@ export default function ThemeBundler() {
     description: "",
     version: "",
     license: "",
-    tags: ""
+    homepage: "",
+    repository: "",
+    changelog: "",
+    preview_image: ""
   });
   const [tags, setTags] = useState([]);
+  const [components, setComponents] = useState([]); // Components declared in the manifest
+  const [variantsText, setVariantsText] = useState(""); // Variants as "name=folder" pairs, comma-separated
   const [dragOver, setDragOver] = useState(false);
   const [selectedFolder, setSelectedFolder] = useState(null);
+  const [progress, setProgress] = useState(null); // Latest bundle-progress event
+  const [excludeText, setExcludeText] = useState(""); // Extra exclude patterns, comma-separated
+  const [assetPreview, setAssetPreview] = useState(null); // Entries the backend would bundle
+  const [diagnostics, setDiagnostics] = useState([]); // Manifest and theme problems from the last check
+  const [installedName, setInstalledName] = useState(""); // Installed theme to export
 
   const storedUser = JSON.parse(localStorage.getItem('reskin_user') || "{}");
 
@@ -48,6 +59,25 @@ export default function ThemeBundler() {
     checkTauri();
   }, []);
 
+  useEffect(() => { // Follow backend progress while a bundle is being written
+    const unlisten = listen("bundle-progress", (event) => setProgress(event.payload));
+    return () => { unlisten.then((stop) => stop()); };
+  }, []);
+
+  const formatMegabytes = (bytes) => (bytes / (1024 * 1024)).toFixed(1);
+
+  const excludePatterns = excludeText.split(",").map(p => p.trim()).filter(Boolean);
+
+  useEffect(() => { // Dry run: show what .reskinignore and the exclude patterns leave in the bundle
+    if (!selectedFolder?.path) {
+      setAssetPreview(null);
+      return;
+    }
+    invoke("preview_bundle_assets", { themeDirectory: selectedFolder.path, exclude: excludePatterns })
+      .then(setAssetPreview)
+      .catch(() => setAssetPreview(null));
+  }, [selectedFolder, excludeText]);
+
   const showStatus = (message, type = "info") => {
     setStatus(message);
     setStatusType(type);
@@ -73,6 +103,10 @@ export default function ThemeBundler() {
 
   const removeTag = (tag) => setTags(tags.filter(t => t !== tag));
 
+  const toggleComponent = (component) => setComponents(components.includes(component)
+    ? components.filter(c => c !== component)
+    : [...components, component]);
+
   const handleDragOver = (e) => { e.preventDefault(); setDragOver(true); };
   const handleDragLeave = (e) => { e.preventDefault(); setDragOver(false); };
 
@@ -143,10 +177,28 @@ export default function ThemeBundler() {
         author: finalAuthor,
         description: formData.description || t.bundler.manifest.description_default,
         version: finalVersion,
-        tags: tags.join(","),
+        tags,
         license: formData.license || "MIT",
+        homepage: themeData.homepage || null,
+        repository: themeData.repository || null,
+        changelog: themeData.changelog || null,
+        preview_image: themeData.preview_image || null,
+        components,
+        variants: variantsText.split(",").map(pair => pair.split("=").map(part => part.trim()))
+          .filter(([name, path]) => name && path)
+          .map(([name, path]) => ({ name, path })),
       };
 
+      // Check the manifest (and the folder, when the backend can read it) before writing anything
+      const found = selectedFolder.path
+        ? await invoke("lint_theme", { themeDirectory: selectedFolder.path, manifest })
+        : await invoke("validate_manifest", { manifest });
+      setDiagnostics(found);
+      if (found.some(d => d.severity === "error")) {
+        showStatus(t.bundler.status["status.lint_errors"], "error");
+        return;
+      }
+
       let homeDir = '';
       try { homeDir = await invoke('get_home_dir'); } 
       catch { homeDir = '/home/' + (window.process?.env?.USER || 'user'); }
@@ -160,6 +212,7 @@ export default function ThemeBundler() {
         manifest,
         theme_directory: selectedFolder.path,
         assets: fileData.map(f => f.path),
+        exclude: excludePatterns,
         output_path: outputPath,
       };
 
@@ -167,14 +220,71 @@ export default function ThemeBundler() {
       if (selectedFolder.path) result = await invoke("bundle_theme", { request });
       else result = await invoke("bundle_theme_from_directory", { request });
 
+      setProgress(null);
       console.log("Bundle result:", result);
       showStatus(t.bundler.status["status.bundle_success"].replace("{outputPath}", outputPath), "success");
     } catch (error) {
+      setProgress(null);
       console.error("Bundle error:", error);
       showStatus(t.bundler.status["status.bundle_failure"].replace("{error}", error.message || error), "error");
     }
   };
 
+  const handleConvertArchive = async () => { // Turn a plain .tar.gz/.tar.xz/.zip theme archive into .reskin bundles
+    let archivePath;
+    try {
+      archivePath = await invoke("select_archive", { title: t.bundler.button["button.convert_archive"] });
+    } catch {
+      return; // Dialog was closed
+    }
+
+    showStatus(t.bundler.status["status.bundling"], "info");
+    try {
+      const imported = await invoke("convert_theme_archive", { archivePath }); // Saved to ~/.cache/reskin/imports
+      setProgress(null);
+      showStatus(t.bundler.status["status.convert_success"].replace("{bundles}", imported.map(theme => theme.bundle_path).join(", ")), "success");
+    } catch (error) {
+      setProgress(null);
+      showStatus(t.bundler.status["status.bundle_failure"].replace("{error}", error.message || error), "error");
+    }
+  };
+
+  const handleCreateDelta = async () => { // Package the changes between two releases as a small update bundle
+    let basePath, newPath;
+    try {
+      basePath = await invoke("select_file", { title: t.bundler.dropzone["dropzone.delta_base"] });
+      newPath = await invoke("select_file", { title: t.bundler.dropzone["dropzone.delta_new"] });
+    } catch {
+      return; // Dialog was closed
+    }
+    const outputPath = newPath.replace(/\.reskin$/, "") + ".delta.reskin";
+
+    showStatus(t.bundler.status["status.bundling"], "info");
+    try {
+      await invoke("create_delta", { basePath, newPath, outputPath });
+      setProgress(null);
+      showStatus(t.bundler.status["status.delta_success"].replace("{path}", outputPath), "success");
+    } catch (error) {
+      setProgress(null);
+      showStatus(t.bundler.status["status.bundle_failure"].replace("{error}", error.message || error), "error");
+    }
+  };
+
+  const handleExportInstalled = async () => { // Bundle a theme from ~/.themes, ~/.local/share/icons and the system folders
+    const name = installedName.trim();
+    if (!name) return;
+
+    showStatus(t.bundler.status["status.bundling"], "info");
+    try {
+      const outputPath = await invoke("export_installed_theme", { name }); // Saved to ~/.cache/reskin/exports
+      setProgress(null);
+      showStatus(t.bundler.status["status.export_installed_success"].replace("{path}", outputPath), "success");
+    } catch (error) {
+      setProgress(null);
+      showStatus(t.bundler.status["status.bundle_failure"].replace("{error}", error.message || error), "error");
+    }
+  };
+
   const getStatusColor = () => {
     switch (statusType) {
       case "error": return "#ff5555";
@@ -236,7 +346,7 @@ export default function ThemeBundler() {
             >
               <option value="">{t.bundler.manifest.license_default}</option>
               <option value="MIT">MIT</option>
-              <option value="GPL-3.0">GPL-3.0</option>
+              <option value="GPL-3.0-or-later">GPL-3.0-or-later</option>
               <option value="Apache-2.0">Apache-2.0</option>
               <option value="BSD-3-Clause">BSD-3-Clause</option>
             </select>
@@ -255,6 +365,44 @@ export default function ThemeBundler() {
               />
             </div>
           </div>
+          <div className="themebundler-meta-flex">
+            {["homepage", "repository", "changelog"].map(field => (
+              <input
+                key={field}
+                name={field}
+                type="url"
+                placeholder={t.bundler.manifest[`manifest.${field}`]}
+                value={themeData[field]}
+                onChange={handleInputChange}
+                className="themebundler-input"
+              />
+            ))}
+            <input
+              placeholder={t.bundler.manifest["manifest.variants_placeholder"]}
+              value={variantsText}
+              onChange={e => setVariantsText(e.target.value)}
+              className="themebundler-input"
+            />
+            <input
+              name="preview_image"
+              placeholder={t.bundler.manifest["manifest.preview_image"]}
+              value={themeData.preview_image}
+              onChange={handleInputChange}
+              className="themebundler-input"
+            />
+          </div>
+          <div className="themebundler-components">
+            {["gtk", "icons", "cursors", "fonts", "shell"].map(component => (
+              <label key={component}>
+                <input
+                  type="checkbox"
+                  checked={components.includes(component)}
+                  onChange={() => toggleComponent(component)}
+                />
+                {t.bundler.manifest[`manifest.component_${component}`]}
+              </label>
+            ))}
+          </div>
         </div>
       </div>
 
@@ -278,6 +426,23 @@ export default function ThemeBundler() {
         )}
       </div>
 
+      <input
+        type="text"
+        placeholder={t.bundler.dropzone["dropzone.exclude_placeholder"]}
+        value={excludeText}
+        onChange={e => setExcludeText(e.target.value)}
+        className="themebundler-input"
+      />
+
+      {assetPreview && (
+        <details className="themebundler-asset-preview">
+          <summary>{t.bundler.dropzone["dropzone.preview_title"].replace("{count}", assetPreview.length)}</summary>
+          <ul>
+            {assetPreview.map(asset => <li key={asset}>{asset}</li>)}
+          </ul>
+        </details>
+      )}
+
       <input
         type="file"
         webkitdirectory
@@ -295,9 +460,50 @@ export default function ThemeBundler() {
         📦 {t.bundler.button["button.bundle"]}
       </button>
 
+      <button onClick={handleConvertArchive} disabled={!isReady} className="themebundler-convert-btn">
+        {t.bundler.button["button.convert_archive"]}
+      </button>
+
+      <button onClick={handleCreateDelta} disabled={!isReady} className="themebundler-convert-btn">
+        {t.bundler.button["button.create_delta"]}
+      </button>
+
+      <div className="themebundler-export-installed">
+        <input
+          type="text"
+          value={installedName}
+          onChange={e => setInstalledName(e.target.value)}
+          placeholder={t.bundler.manifest["manifest.installed_placeholder"]}
+        />
+        <button onClick={handleExportInstalled} disabled={!isReady || !installedName.trim()} className="themebundler-convert-btn">
+          {t.bundler.button["button.export_installed"]}
+        </button>
+      </div>
+
+      {progress && progress.bytes_total > 0 && (
+        <div className="reskin-progress">
+          <progress value={progress.bytes_done} max={progress.bytes_total} />
+          <span>
+            {t.bundler.status["status.bundling_progress"]
+              .replace("{done}", formatMegabytes(progress.bytes_done))
+              .replace("{total}", formatMegabytes(progress.bytes_total))}
+          </span>
+        </div>
+      )}
+
       <div className="themebundler-status" style={{ color: getStatusColor() }}>
         {status}
       </div>
+
+      {diagnostics.length > 0 && (
+        <ul className="themebundler-diagnostics">
+          {diagnostics.map((d, i) => (
+            <li key={i} className={`themebundler-diagnostic-${d.severity}`}>
+              <strong>{d.subject}</strong>: {d.message}
+            </li>
+          ))}
+        </ul>
+      )}
     </div>
   );
 }
@@ -2,27 +2,33 @@
 import React, { useEffect, useState } from "react";
 import "./ThemeCard.css";
 import { getTranslationObject } from "./locales/index.js";
-import "@tauri-apps/api/core";
+import { invoke } from "@tauri-apps/api/core";
+
+const localPreviews = new Map(); // Thumbnails already read from local bundles, by bundle path
 
 export default function ThemeCard({ theme, onClick }) {
   const language = localStorage.getItem("reskin_language") || "en"; // Use selected language or fall back to English
   const t = getTranslationObject(language); // Translation object
 
   const [missing, setMissing] = useState(false);
+  const [localPreview, setLocalPreview] = useState(null); // Thumbnail embedded in the local bundle
 
   useEffect(() => {
     // Only check for recently viewed themes in Tauri
     if (window.__TAURI__) {
-      const checkFile = async () => {
+      const loadPreview = async () => {
+        const filePath = `/tmp/reskin/${theme.name}.reskin`;
         try {
-          const filePath = `/tmp/reskin/${theme.name}.reskin`;
-          const fileExists = await exists(filePath);
-          setMissing(!fileExists);
-        } catch {
-          setMissing(true);
+          if (!localPreviews.has(filePath)) { // The backend reads the preview without extracting the bundle
+            localPreviews.set(filePath, await invoke("get_bundle_preview", { bundlePath: filePath }));
+          }
+          setLocalPreview(localPreviews.get(filePath));
+          setMissing(false);
+        } catch (err) {
+          setMissing(String(err).startsWith("Failed to open bundle")); // A bundle with a broken preview is still there
         }
       };
-      checkFile();
+      loadPreview();
     }
   }, [theme.name]);
 
@@ -34,7 +40,7 @@ export default function ThemeCard({ theme, onClick }) {
       style={{ cursor: "pointer", opacity: missing ? 0.5 : 1 }}
     >
       <img
-        src={theme.preview}
+        src={localPreview || theme.preview}
         alt={theme.name || t.themecard.preview_alt}
         onError={e => { e.target.onerror = null; e.target.src = "/default-preview.png"; }}
         style={{ opacity: missing ? 0.5 : 1 }}
@@ -11,22 +11,23 @@ const client = new Client()
 const databases = new Databases(client);
 const account = new Account(client);
 
-export default function ThemeDetails({ theme, onBack }) {
+export default function ThemeDetails({ theme, onBack, onUninstalled }) {
   const language = localStorage.getItem("reskin_language") || "en"; // Use selected language or fall back to English
   const t = getTranslationObject(language); // Translation object
 
   const [manifest, setManifest] = useState(theme); // Theme manifest
   const [isInstalled, setIsInstalled] = useState(false); // Installation state
+  const [variant, setVariant] = useState(""); // Variant to apply, for themes that ship several
   const databaseId = "reskin"; // Database ID
   const collectionId = "reports"; // Reports collection ID
 
   useEffect(() => {
     async function checkIfInstalled() {
-      const homeDir = `/home/${window.process?.env?.USER || 'user'}`; // Get user's home directory
       try {
-        const bundlePath = `${homeDir}/.themes/${theme.name}/reskin.json`; // Manifest path in the installed theme
-        const realManifest = await invoke('extract_theme_info_from_file', { filePath: bundlePath }); // Extract theme manifest to the manifest path
-        setManifest(realManifest); // Set manifest to the extracted manifest
+        const realManifest = await invoke('get_installed_theme', { name: theme.name }); // Manifest recorded when the theme was installed
+        if (!realManifest) throw new Error("Theme is not installed");
+        setManifest(realManifest); // Set manifest to the installed manifest
+        setVariant(realManifest.default_variant || realManifest.variants?.[0]?.name || "");
         setIsInstalled(true); // Set installation state to true
       } catch {
         setManifest(theme); 
@@ -41,18 +42,33 @@ export default function ThemeDetails({ theme, onBack }) {
   const handleApply = async () => { // Handle theme application
     if (!manifest || !manifest.name) return;
     try {
-      await invoke('apply_theme', { themeName: manifest.name }); // Attempt to apply theme
+      await invoke('apply_theme', { themeName: manifest.name, variant: variant || null }); // Attempt to apply theme
     } catch (e) {
       console.error('Apply Theme error:', e); // Throw error on failure
     }
   };
 
+  // Deltas the marketplace lists for this theme, stored as JSON strings in the document
+  const deltas = (theme.deltas || []).map(delta => typeof delta === "string" ? JSON.parse(delta) : delta);
+  const hasUpdate = isInstalled && theme.file && theme.version && theme.version !== manifest.version;
+
+  const handleUpdate = async () => { // Fetch the new version, as a delta when there is one for the installed version
+    try {
+      await invoke('download_theme', { themeFileId: theme.file, themeName: theme.name, deltas });
+      alert(t.themedetails.status["status.update_success"]);
+    } catch (e) {
+      console.error('Update Theme error:', e);
+      alert(t.themedetails.status["status.install_failure"]);
+    }
+  };
+
   const handleInstall = async () => { // Handle theme installation
     if (!manifest || !manifest.file) return;
     try {
       await invoke('download_theme', { // Download theme from the marketplace
         themeFileId: manifest.file,
         themeName: manifest.name,
+        deltas,
       });
       setIsInstalled(true); // Set installation state to true
       alert(t.themedetails.status["install_success"]); // Return success
@@ -62,6 +78,20 @@ export default function ThemeDetails({ theme, onBack }) {
     }
   };
 
+  const handleUninstall = async () => { // Remove the files the install wrote
+    if (!confirm(t.themedetails.prompt["prompt.uninstall_confirm"].replace("{name}", manifest.name))) return;
+    try {
+      await invoke('uninstall_theme', { name: manifest.name });
+      setIsInstalled(false);
+      onUninstalled?.(manifest.name);
+      alert(t.themedetails.status["status.uninstall_success"]);
+      onBack();
+    } catch (e) {
+      console.error('Uninstall Theme error:', e);
+      alert(t.themedetails.status["status.uninstall_failure"].replace("{error}", e));
+    }
+  };
+
   const handleButtonAction = isInstalled ? handleApply : handleInstall; // Install or apply the theme depending on the installation state
 
   const getUser = async () => { // Get logged in user
@@ -115,12 +145,33 @@ export default function ThemeDetails({ theme, onBack }) {
           {manifest.name && <h1 style={{ margin: 0 }}>{manifest.name}</h1>}
           {manifest.author && <h2 style={{ margin: "8px 0 16px 0", fontWeight: 400 }}>{t.themedetails.label["label.author_prefix"]} {manifest.author}</h2>}
           {manifest.description && <p style={{ maxWidth: "400px" }}>{manifest.description}</p>}
+          {isInstalled && manifest.variants?.length > 0 && (
+            <select className="settings-dropdown" value={variant} onChange={e => setVariant(e.target.value)}>
+              {manifest.variants.map(v => <option key={v.name} value={v.name}>{v.name}</option>)}
+            </select>
+          )}
           <button
             style={{ marginTop: "18px", padding: "10px 24px", border: "none", borderRadius: "8px", fontWeight: "bold", fontSize: "1rem", cursor: "pointer" }}
             onClick={handleButtonAction}
           >
             {isInstalled ? `🎨 ${t.themedetails.button["button.apply"]}` : `⬇️ ${t.themedetails.button["button.install"]}`}
           </button>
+          {isInstalled && (
+            <button
+              style={{ marginTop: "18px", marginLeft: "12px", padding: "10px 24px", border: "none", borderRadius: "8px", fontWeight: "bold", fontSize: "1rem", cursor: "pointer" }}
+              onClick={handleUninstall}
+            >
+              {t.themedetails.button["button.uninstall"]}
+            </button>
+          )}
+          {hasUpdate && (
+            <button
+              style={{ marginTop: "18px", marginLeft: "12px", padding: "10px 24px", border: "none", borderRadius: "8px", fontWeight: "bold", fontSize: "1rem", cursor: "pointer" }}
+              onClick={handleUpdate}
+            >
+              {t.themedetails.button["button.update"].replace("{version}", theme.version)}
+            </button>
+          )}
           <button
             style={{ marginTop: "12px", marginLeft: "12px", padding: "10px 24px", border: "none", borderRadius: "8px", fontWeight: "bold", fontSize: "1rem", cursor: "pointer", backgroundColor: "#ff4d4f", color: "white" }}
             onClick={handleReport}
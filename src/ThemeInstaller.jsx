@@ -1,6 +1,8 @@
 // Import necessary components
-import React, { useState } from "react";
+import React, { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { listenForFileDrops, readThemeInfo, uploadFile } from "./bundleFiles.js";
 import "./ThemeInstaller.css";
 import { getTranslationObject } from "./locales/index.js";
 
@@ -14,17 +16,67 @@ export default function ThemeInstaller({ onThemeInstalled }) {
   const [statusType, setStatusType] = useState("info"); // Status message type (determines the color of it)
   const [isInstalling, setIsInstalling] = useState(false); // Installation state
   const [dragOver, setDragOver] = useState(false);
+  const [progress, setProgress] = useState(null); // Latest install-progress event
+  const [diagnostics, setDiagnostics] = useState([]); // Problems the backend found in the installed theme
+  const [selectedVariants, setSelectedVariants] = useState([]); // Variants to install, when the theme has any
+  const dropzoneRef = useRef(null);
+
+  useEffect(() => { // Follow backend progress while a theme is being installed from disk or imported
+    const unlisteners = ["install-progress", "import-progress"]
+      .map((name) => listen(name, (event) => setProgress(event.payload)));
+    return () => { unlisteners.forEach((unlisten) => unlisten.then((stop) => stop())); };
+  }, []);
+
+  useEffect(() => { // Install every variant unless the user unticks some
+    setSelectedVariants((themeInfo?.variants || []).map(v => v.name));
+  }, [themeInfo]);
+
+  const toggleVariant = (name) => setSelectedVariants(selectedVariants.includes(name)
+    ? selectedVariants.filter(v => v !== name)
+    : [...selectedVariants, name]);
 
   const showStatus = (msg, type = "info") => { // Show status message with message and type
     setStatus(msg); // Set status message
     setStatusType(type); // Set status type
   };
 
-  const handleDrop = async (e) => { // Handle drag-and-drop file seection
-    e.preventDefault();
-    setDragOver(false);
-    const file = e.dataTransfer.files[0];
-    if (!file || !file.name.endsWith(".reskin")) { 
+  const selectPath = async (filePath) => { // Use a file on disk, so it can be streamed from there
+    const fileName = filePath.split("/").pop();
+    if (!fileName.endsWith(".reskin")) {
+      showStatus(t.themeinstaller.status.error_not_reskin, "error"); // Throw an error if the file format is not .reskin
+      return;
+    }
+    setSelectedFile({ name: fileName, path: filePath });
+    showStatus(t.themeinstaller.status.success_select.replace("{filePath}", fileName), "success");
+
+    try {
+      const info = await invoke("extract_theme_info_from_file", { filePath }); // Only the header is read
+      setThemeInfo(info);
+      showStatus(t.themeinstaller.status.info_loaded, "success");
+    } catch {
+      showStatus(t.themeinstaller.status.error_info_load, "error");
+      setThemeInfo(null);
+    }
+  };
+
+  useEffect(() => // Dropped files arrive with their paths through Tauri rather than as browser File objects
+    listenForFileDrops(() => dropzoneRef.current, { onHover: setDragOver, onDrop: selectPath }), []);
+
+  const handleFileSelect = async () => { // Pick a file with the native dialog so it can be streamed from disk
+    let filePath;
+    try {
+      filePath = await invoke("select_file", { title: t.themeinstaller.title });
+    } catch {
+      showStatus(t.themeinstaller.status.error_select_failed, "info");
+      document.getElementById("fileInput").click(); // Fall back to the browser file picker
+      return;
+    }
+    await selectPath(filePath);
+  };
+
+  const handleFileInput = async (e) => { // Browser file picker, which gives a File without a path
+    const file = e.target.files[0];
+    if (!file || !file.name.endsWith(".reskin")) {
       showStatus(t.themeinstaller.status.error_not_reskin, "error"); // Throw an error if the file format is not .reskin
       return;
     }
@@ -36,9 +88,7 @@ export default function ThemeInstaller({ onThemeInstalled }) {
     ); // Return success
 
     try {
-      const buffer = await file.arrayBuffer();
-      const bytes = Array.from(new Uint8Array(buffer));
-      const info = await invoke("extract_theme_info", { fileData: bytes }); // Attempt to extract theme info
+      const info = await readThemeInfo(file); // Attempt to extract theme info
       setThemeInfo(info); // Set theme info as the extracted info
       showStatus(t.themeinstaller.status.info_loaded, "success"); // Return success
     } catch {
@@ -47,31 +97,54 @@ export default function ThemeInstaller({ onThemeInstalled }) {
     }
   };
 
-  const handleFileInput = (e) => // Handle file input
-    handleDrop({
-      dataTransfer: { files: e.target.files },
-      preventDefault: () => {}
-    });
-
   const handleInstall = async () => { // Handle theme installation
     if (!selectedFile) {
       showStatus(t.themeinstaller.status.error_no_theme_to_apply, "error"); // Throw error (no theme to apply) if file is not selected
       return;
     }
 
+    if (themeInfo?.variants?.length > 0 && selectedVariants.length === 0) {
+      showStatus(t.themeinstaller.status.error_no_variants, "error"); // An empty list would install all of them
+      return;
+    }
+
+    // Packs write files outside the themes folder, so the user has to agree to each one
+    const configs = themeInfo?.pack?.configs?.map(config => config.target) || [];
+    const allowConfigs = configs.length > 0;
+    if (allowConfigs && !confirm(t.themeinstaller.status.configs_confirm.replace("{configs}", configs.join(", ")))) return;
+
     setIsInstalling(true); // Set installation state to true
+    setDiagnostics([]);
     showStatus(t.themeinstaller.status.installing, "info"); // Show installing status
 
     try {
-      const arrayBuffer = await selectedFile.arrayBuffer();
-      const fileData = Array.from(new Uint8Array(arrayBuffer));
-      await invoke("install_theme_from_data", { // Install theme from extracted data
-        fileData,
-        fileName: selectedFile.name,
-        autoApply: true
-      });
-
-      showStatus(t.themeinstaller.status.install_success, "success"); // Return success
+      // Signature and scope rules live in the backend, which applies them to every install
+      const { require_signed: requireSigned, scope } = await invoke("get_install_policy");
+      let report;
+      if (selectedFile.path) {
+        report = await invoke("install_theme_from_file", { // Stream the bundle from disk with progress events
+          filePath: selectedFile.path,
+          autoApply: true,
+          requireSigned,
+          variants: selectedVariants,
+          scope,
+          allowConfigs
+        });
+      } else {
+        const uploadId = await uploadFile(selectedFile); // Sent in chunks, then installed like a file on disk
+        report = await invoke("install_theme_from_upload", {
+          uploadId,
+          autoApply: true,
+          requireSigned,
+          variants: selectedVariants,
+          scope,
+          allowConfigs
+        });
+      }
+
+      const written = report.configs.length > 0 ? " " + t.themeinstaller.status.configs_written.replace("{configs}", report.configs.join(", ")) : "";
+      showStatus(t.themeinstaller.status.install_success + written, "success"); // Return success
+      setDiagnostics(report.diagnostics);
       onThemeInstalled && onThemeInstalled(selectedFile);
     } catch (err) { // Set status to error message on failure
       showStatus(
@@ -84,6 +157,52 @@ export default function ThemeInstaller({ onThemeInstalled }) {
     }
 
     setIsInstalling(false); // Set installation state to false
+    setProgress(null);
+  };
+
+  const handleImportArchive = async () => { // Install the themes in a plain .tar.gz/.tar.xz/.zip archive
+    let archivePath;
+    try {
+      archivePath = await invoke("select_archive", { title: t.themeinstaller.button.import_archive });
+    } catch {
+      return; // Dialog was closed
+    }
+
+    setIsInstalling(true);
+    setDiagnostics([]);
+    showStatus(t.themeinstaller.status.installing, "info");
+    try {
+      const { require_signed: requireSigned, scope } = await invoke("get_install_policy");
+      const imported = await invoke("import_theme_archive", { archivePath, autoApply: true, requireSigned, scope });
+      showStatus(t.themeinstaller.status.import_success.replace("{themes}", imported.map(theme => theme.manifest.name).join(", ")) + " " + t.themeinstaller.status.import_unsigned, "success");
+      setDiagnostics(imported.flatMap(theme => theme.diagnostics));
+      imported.forEach(theme => onThemeInstalled && onThemeInstalled(theme.manifest));
+    } catch (err) {
+      showStatus(t.themeinstaller.status.install_failure.replace("{error.message || error}", err.message || err), "error");
+    }
+    setIsInstalling(false);
+    setProgress(null);
+  };
+
+  const handleExport = async () => { // Save the selected bundle as a .tar.gz next to it, for people without Reskin
+    const outputPath = selectedFile.path.replace(/\.reskin$/, "") + ".tar.gz";
+    try {
+      await invoke("export_bundle", { bundlePath: selectedFile.path, outputPath });
+      showStatus(t.themeinstaller.status.export_success.replace("{outputPath}", outputPath), "success");
+    } catch (err) {
+      showStatus(t.themeinstaller.status.export_failure.replace("{error}", err.message || err), "error");
+    }
+  };
+
+  const progressLabel = () => { // Describe the current install stage
+    if ((progress.stage === "extracting" || progress.stage === "unpacking") && progress.bytes_total > 0) {
+      const percent = Math.floor((progress.bytes_done / progress.bytes_total) * 100);
+      const label = progress.stage === "unpacking" ? t.themeinstaller.status.progress_unpacking : t.themeinstaller.status.progress_extracting;
+      return label.replace("{percent}", percent);
+    }
+    if (progress.stage === "bundling") return t.themeinstaller.status.progress_bundling;
+    if (progress.stage === "installing") return t.themeinstaller.status.progress_installing;
+    return t.themeinstaller.status.progress_verifying;
   };
 
   // Return HTML content
@@ -95,16 +214,8 @@ export default function ThemeInstaller({ onThemeInstalled }) {
         className={`themebundler-dropzone${
           dragOver ? " themebundler-dropzone-active" : ""
         }`}
-        onDragOver={(e) => {
-          e.preventDefault();
-          setDragOver(true);
-        }}
-        onDragLeave={(e) => {
-          e.preventDefault();
-          setDragOver(false);
-        }}
-        onDrop={handleDrop}
-        onClick={() => document.getElementById("fileInput").click()}
+        ref={dropzoneRef}
+        onClick={handleFileSelect}
       >
         {selectedFile ? (
           <div>
@@ -153,6 +264,55 @@ export default function ThemeInstaller({ onThemeInstalled }) {
           <div>
             {t.themeinstaller.info_preview.license} {themeInfo.license}
           </div>
+          {themeInfo.homepage && (
+            <div>
+              {t.themeinstaller.info_preview.homepage} {themeInfo.homepage}
+            </div>
+          )}
+          {themeInfo.components?.length > 0 && (
+            <div>
+              {t.themeinstaller.info_preview.components} {themeInfo.components.join(", ")}
+            </div>
+          )}
+          {themeInfo.desktops?.length > 0 && (
+            <div>
+              {t.themeinstaller.info_preview.desktops} {themeInfo.desktops
+                .map(d => d.min_version ? `${d.name} ${d.min_version}+` : d.name)
+                .join(", ")}
+            </div>
+          )}
+          {themeInfo.variants?.length > 0 && (
+            <div className="theme-variants">
+              {t.themeinstaller.info_preview.variants}
+              {themeInfo.variants.map(variant => (
+                <label key={variant.name}>
+                  <input
+                    type="checkbox"
+                    checked={selectedVariants.includes(variant.name)}
+                    onChange={() => toggleVariant(variant.name)}
+                  />
+                  {variant.name}
+                </label>
+              ))}
+            </div>
+          )}
+          {themeInfo.pack?.themes?.length > 0 && (
+            <div>
+              {t.themeinstaller.info_preview.pack} {themeInfo.pack.themes
+                .map(theme => `${theme.name} (${theme.component})`)
+                .join(", ")}
+            </div>
+          )}
+          {themeInfo.pack?.configs?.length > 0 && (
+            <div>
+              {t.themeinstaller.info_preview.pack_configs} {themeInfo.pack.configs.map(c => c.target).join(", ")}
+            </div>
+          )}
+          {themeInfo.min_reskin_version && (
+            <div>
+              {t.themeinstaller.info_preview.requires} {themeInfo.min_reskin_version}+
+            </div>
+          )}
         </div>
       )}
 
@@ -160,9 +320,39 @@ export default function ThemeInstaller({ onThemeInstalled }) {
         {t.themeinstaller.button.install}
       </button>
 
+      {selectedFile?.path && (
+        <button onClick={handleExport} disabled={isInstalling} id="export-button">
+          {t.themeinstaller.button.export_archive}
+        </button>
+      )}
+
+      <button onClick={handleImportArchive} disabled={isInstalling} id="import-archive-button">
+        {t.themeinstaller.button.import_archive}
+      </button>
+
+      {isInstalling && progress && (
+        <div className="reskin-progress">
+          <progress value={progress.bytes_total > 0 ? progress.bytes_done : undefined} max={progress.bytes_total || undefined} />
+          <span>{progressLabel()}</span>
+        </div>
+      )}
+
       <div id="status" className={statusType}>
         {status}
       </div>
+
+      {diagnostics.length > 0 && (
+        <div className="theme-diagnostics">
+          <div>{t.themeinstaller.status.theme_warnings}</div>
+          <ul>
+            {diagnostics.map((d, i) => (
+              <li key={i} className={`theme-diagnostic-${d.severity}`}>
+                <strong>{d.subject}</strong>: {d.message}
+              </li>
+            ))}
+          </ul>
+        </div>
+      )}
     </div>
   );
 }
\ No newline at end of file
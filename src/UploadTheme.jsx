@@ -1,7 +1,7 @@
 // Import necessary components
 import React, { useState } from "react";
 import { Client, Storage, Databases, Account, Query } from "appwrite";
-import { invoke } from "@tauri-apps/api/core";
+import { readThemeInfo } from "./bundleFiles.js";
 
 export default function UploadTheme({ onNavigate }) {
   // Use stored language or fallback to English
@@ -50,6 +50,13 @@ export default function UploadTheme({ onNavigate }) {
     return hashArray.map(b => b.toString(16).padStart(2, "0")).join("");
   };
 
+  // The marketplace stores tags as one comma-joined string of at most 32 characters,
+  // so keep as many whole tags as fit instead of cutting one in half
+  const joinTags = (tags) => tags.reduce((joined, tag) => {
+    const next = joined ? `${joined},${tag}` : tag;
+    return next.length <= 32 ? next : joined;
+  }, "");
+
   // Funcrtion to handle file change
   const handleFileChange = async (e) => {
     const f = e.target.files[0];
@@ -57,15 +64,8 @@ export default function UploadTheme({ onNavigate }) {
     setFile(f);
     showStatus("Reading theme metadata...");
     try {
-      const arrayBuffer = await f.arrayBuffer();
-      const uint8Array = Array.from(new Uint8Array(arrayBuffer));
-      const info = await invoke("extract_theme_info", { fileData: uint8Array }); // Invoke backend to extract theme info from the .reskin file
-      const safeTags = typeof info.tags === "string" // Separate tags with commas
-        ? info.tags.slice(0, 32)
-        : Array.isArray(info.tags)
-          ? info.tags.join(",").slice(0, 32)
-          : "";
-      setThemeInfo({ ...info, tags: safeTags }); // Set theme info to the extracted info
+      const info = await readThemeInfo(f); // Invoke backend to extract theme info from the header of the .reskin file
+      setThemeInfo({ ...info, tags: info.tags || [] }); // The backend always returns tags as a list
       showStatus("Metadata loaded! Ready to upload");
     } catch (err) {
       // Throw error on failure
@@ -114,7 +114,7 @@ export default function UploadTheme({ onNavigate }) {
         description: themeInfo.description || "",
         author: themeInfo.author || "Unknown",
         version: themeInfo.version || "",
-        tags: themeInfo.tags || "",
+        tags: joinTags(themeInfo.tags),
         license: themeInfo.license || "MIT",
         file: fileId,
         hash,
@@ -155,7 +155,7 @@ export default function UploadTheme({ onNavigate }) {
           <p><strong>Author:</strong> {themeInfo.author}</p>
           <p><strong>Description:</strong> {themeInfo.description}</p>
           <p><strong>Version:</strong> {themeInfo.version}</p>
-          <p><strong>Tags:</strong> {themeInfo.tags}</p>
+          <p><strong>Tags:</strong> {themeInfo.tags.join(", ")}</p>
           <p><strong>License:</strong> {themeInfo.license}</p>
         </div>
       )}
@@ -0,0 +1,52 @@
+// Import necessary components
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWebview } from "@tauri-apps/api/webview";
+
+const UPLOAD_CHUNK_SIZE = 4 * 1024 * 1024; // Bytes sent to the backend per call when a file has no path
+const MAX_MANIFEST_LENGTH = 16 * 1024 * 1024; // Same cap as the backend
+
+// Read the manifest of a .reskin File. It sits right after a short header, so only that part of the
+// file is sent to the backend, never the whole bundle.
+export async function readThemeInfo(file) {
+  const head = new DataView(await file.slice(0, 13).arrayBuffer());
+  const legacy = head.getUint8(12) === 0x7b; // Bundles without a version byte have "{" where the manifest starts
+  const manifestLength = Math.min(Number(head.getBigUint64(legacy ? 4 : 5, true)), MAX_MANIFEST_LENGTH);
+  const header = await file.slice(0, 13 + manifestLength).arrayBuffer();
+  return invoke("extract_theme_info", { fileData: Array.from(new Uint8Array(header)) });
+}
+
+// Send a File without a path to the backend in raw chunks, returning the id to install it by
+export async function uploadFile(file) {
+  const uploadId = await invoke("begin_upload");
+  for (let offset = 0; offset < file.size; offset += UPLOAD_CHUNK_SIZE) {
+    const chunk = await file.slice(offset, offset + UPLOAD_CHUNK_SIZE).arrayBuffer();
+    await invoke("append_upload", new Uint8Array(chunk), { headers: { "upload-id": uploadId } });
+  }
+  return uploadId;
+}
+
+// Follow files dragged from outside the app onto the element `getElement` returns. Tauri hands over
+// their paths, so the backend can read them from disk. Returns a function that stops listening.
+export function listenForFileDrops(getElement, { onHover, onDrop }) {
+  const isOver = (position) => {
+    const element = getElement();
+    if (!element || !position) return false;
+    const x = position.x / window.devicePixelRatio; // Tauri reports physical pixels
+    const y = position.y / window.devicePixelRatio;
+    const rect = element.getBoundingClientRect();
+    return x >= rect.left && x <= rect.right && y >= rect.top && y <= rect.bottom;
+  };
+
+  const unlisten = getCurrentWebview().onDragDropEvent((event) => {
+    const { type, position, paths } = event.payload;
+    if (type === "leave") {
+      onHover(false);
+    } else if (type === "drop") {
+      onHover(false);
+      if (isOver(position) && paths?.length > 0) onDrop(paths[0]);
+    } else {
+      onHover(isOver(position));
+    }
+  });
+  return () => { unlisten.then((stop) => stop()); };
+}